@@ -59,3 +59,59 @@ fn sequential_run(bencher: Bencher) {
         }
     })
 }
+
+#[divan::bench]
+fn gen_kill_parallel_run(bencher: Bencher) {
+    let programs = prepare_bench_programs();
+    let cfgs: Vec<_> = programs
+        .iter()
+        .flat_map(|program| {
+            program
+                .functions()
+                .map(|function| bril_cfg::build_cfg(&function))
+        })
+        .collect();
+    let problems: Vec<_> = cfgs
+        .iter()
+        .map(bril_analysis::analysis::liveness_gen_kill)
+        .collect();
+    bencher.bench_local(|| {
+        for (cfg, problem) in cfgs.iter().zip(&problems) {
+            black_box(bril_analysis::parallel::solve_gen_kill(
+                cfg,
+                problem,
+                4,
+                bril_analysis::SolveOptions::default(),
+            ));
+        }
+    })
+}
+
+#[divan::bench]
+fn gen_kill_sequential_run(bencher: Bencher) {
+    let programs = prepare_bench_programs();
+    let cfgs: Vec<_> = programs
+        .iter()
+        .flat_map(|program| {
+            program
+                .functions()
+                .map(|function| bril_cfg::build_cfg(&function))
+        })
+        .collect();
+    let problems: Vec<_> = cfgs
+        .iter()
+        .map(bril_analysis::analysis::liveness_gen_kill)
+        .collect();
+    let boundary = std::collections::HashMap::new();
+    bencher.bench_local(|| {
+        for (cfg, problem) in cfgs.iter().zip(&problems) {
+            black_box(bril_analysis::sequential::solve_gen_kill(
+                cfg,
+                &(),
+                problem,
+                &boundary,
+                bril_analysis::SolveOptions::default(),
+            ));
+        }
+    })
+}
@@ -0,0 +1,169 @@
+use bril::{
+    interp::interpret_observed,
+    ir::{Program, Type, Value, Variable},
+};
+use bril_analysis::analysis::{
+    AbstractEnv, AbstractValue, Parity, Sign, abstract_interp,
+    abstract_interp_instr, const_prop, const_prop_instr,
+};
+use bril_cfg::build_cfg;
+use clap::Parser;
+use std::{
+    fmt::Display,
+    io::{BufReader, Read},
+};
+
+const FUEL: usize = 1_000_000;
+
+const OPERATIONS: [&str; 4] = ["add", "sub", "mul", "div"];
+const COMPARISONS: [&str; 5] = ["eq", "lt", "le", "gt", "ge"];
+/// arguments around zero and the wrap-around points
+const VALUES: [i64; 7] = [i64::MIN, -2, -1, 0, 1, 2, i64::MAX];
+
+#[derive(Parser)]
+struct Args {
+    #[arg(short)]
+    f: Option<String>,
+}
+
+/// Branches on `a <first> k`, then on `b <second> a`, and applies
+/// `operation` to `a` and `b` once both hold. Each edge of the branches
+/// refines the operands, so the operation sees every combination of their
+/// abstract values the domains can tell apart.
+fn refinement_program(
+    operation: &str,
+    first: &str,
+    second: &str,
+    k: i64,
+) -> String {
+    format!(
+        r#"{{"functions": [{{"name": "main", "args": [
+    {{"name": "a", "type": "int"}}, {{"name": "b", "type": "int"}}
+], "instrs": [
+    {{"dest": "k", "type": "int", "op": "const", "value": {k}}},
+    {{"dest": "c", "type": "bool", "op": "{first}", "args": ["a", "k"]}},
+    {{"op": "br", "args": ["c"], "labels": ["first", "not_first"]}},
+    {{"label": "not_first"}},
+    {{"op": "print", "args": ["a"]}},
+    {{"op": "ret", "args": []}},
+    {{"label": "first"}},
+    {{"dest": "c", "type": "bool", "op": "{second}", "args": ["b", "a"]}},
+    {{"op": "br", "args": ["c"], "labels": ["second", "not_second"]}},
+    {{"label": "not_second"}},
+    {{"dest": "r", "type": "int", "op": "{operation}", "args": ["a", "b"]}},
+    {{"op": "print", "args": ["r"]}},
+    {{"op": "ret", "args": []}},
+    {{"label": "second"}},
+    {{"dest": "r", "type": "int", "op": "{operation}", "args": ["a", "b"]}},
+    {{"op": "print", "args": ["r"]}}
+]}}]}}"#
+    )
+}
+
+/// Per function, the abstract state before every instruction, or `None` if
+/// the analysis finds it unreachable.
+fn analyze<V: AbstractValue>(
+    prog: &Program,
+) -> Vec<Vec<Option<AbstractEnv<V>>>> {
+    prog.functions()
+        .map(|function| {
+            let cfg = build_cfg(&function);
+            let blocks = abstract_interp::<V>(&cfg);
+            let instr_facts = abstract_interp_instr(&cfg, &blocks);
+            let mut states = vec![None; function.instructions.len()];
+            for (block_idx, block) in cfg.vertices.iter() {
+                let Some(points) = instr_facts.points(block_idx) else {
+                    continue;
+                };
+                for (i, env) in points.into_iter().enumerate() {
+                    if i < block.instructions.len() {
+                        states[block.offset + i] = Some(env);
+                    }
+                }
+            }
+            states
+        })
+        .collect()
+}
+
+/// Runs `prog` and checks that every `int` value held right before an
+/// instruction is described by its abstract value there.
+fn check_run<V: AbstractValue + Display>(
+    prog: &Program,
+    states: &[Vec<Option<AbstractEnv<V>>>],
+    args: &[Value],
+) {
+    // programs may trap, diverge or expect arguments, which only ends the
+    // run early
+    let _ = interpret_observed(prog, args, FUEL, |function, pc, variables| {
+        let env = states[function.0 as usize][pc]
+            .as_ref()
+            .expect("reached an instruction deemed unreachable");
+        for (&number, value) in variables {
+            if let Value::Int(value) = value {
+                let abstract_value = env
+                    .get(Variable(number, Type::Int))
+                    .expect("reached a point deemed unreachable");
+                assert!(
+                    abstract_value.may_equal(*value),
+                    "x{number} = {value} is not described by {abstract_value}"
+                );
+            }
+        }
+    });
+}
+
+fn check_refinements<V: AbstractValue + Display>() {
+    for operation in OPERATIONS {
+        for first in COMPARISONS {
+            for second in COMPARISONS {
+                for k in [0, 1] {
+                    let json = refinement_program(operation, first, second, k);
+                    let prog = bril::shim::flattened_program_repr(
+                        serde_json::from_str(&json).unwrap(),
+                    );
+                    let states = analyze::<V>(&prog);
+                    for a in VALUES {
+                        for b in VALUES {
+                            check_run(
+                                &prog,
+                                &states,
+                                &[Value::Int(a), Value::Int(b)],
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn main() -> std::io::Result<()> {
+    let args = Args::parse();
+    let mut reader: Box<dyn Read> = if let Some(ref f) = args.f {
+        Box::new(BufReader::new(std::fs::File::open(f)?))
+    } else {
+        Box::new(BufReader::new(std::io::stdin()))
+    };
+    let mut buf = String::new();
+    assert!(reader.read_to_string(&mut buf)? > 0);
+
+    let bril_prog: bril_rs::Program = serde_json::from_str(&buf).unwrap();
+    let prog = bril::shim::flattened_program_repr(bril_prog);
+    check_run(&prog, &analyze::<Sign>(&prog), &[]);
+    check_run(&prog, &analyze::<Parity>(&prog), &[]);
+    check_refinements::<Sign>();
+    check_refinements::<Parity>();
+
+    for function in prog.functions() {
+        let cfg = build_cfg(&function);
+        let constants = const_prop(&cfg);
+        let instr_res = const_prop_instr(&cfg, &constants);
+        for (block_idx, facts) in &constants {
+            let points = instr_res.points(block_idx).unwrap();
+            assert_eq!(points.last(), Some(&facts.after));
+        }
+    }
+    eprintln!("passed!");
+    Ok(())
+}
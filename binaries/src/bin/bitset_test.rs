@@ -0,0 +1,128 @@
+use bril::{builder::BasicBlockIdx, ir::FunctionIdx};
+use bril_analysis::{
+    BlockFacts,
+    analysis::{
+        liveness, liveness_in, liveness_para_in, reaching_def, reaching_def_in,
+        reaching_def_para_in,
+    },
+    bitset::{BitSet, ChunkedBitSet, SparseBitSet},
+};
+use bril_cfg::build_cfg;
+use bril_rs::Program;
+use clap::Parser;
+use fixedbitset::FixedBitSet;
+use slotmap::SecondaryMap;
+use std::io::{BufReader, Read};
+
+/// Elements of every fact, which compare equal across representations.
+fn elements<S: BitSet>(
+    solution: impl IntoIterator<Item = (BasicBlockIdx, BlockFacts<S>)>,
+) -> SecondaryMap<BasicBlockIdx, BlockFacts<Vec<usize>>> {
+    solution
+        .into_iter()
+        .map(|(block_idx, facts)| {
+            let facts = BlockFacts {
+                before: facts.before.ones().collect(),
+                after: facts.after.ones().collect(),
+            };
+            (block_idx, facts)
+        })
+        .collect()
+}
+
+/// Runs the same operations on every representation, over a universe wide
+/// enough for chunks to switch between arrays and bitmaps.
+fn check_set_operations() {
+    let mut seed: u64 = 0x2545f4914f6cdd1d;
+    let mut next = |bound: usize| {
+        seed ^= seed << 13;
+        seed ^= seed >> 7;
+        seed ^= seed << 17;
+        seed as usize % bound
+    };
+    let mut dense = [FixedBitSet::new(), FixedBitSet::new()];
+    let mut sparse = [SparseBitSet::default(), SparseBitSet::default()];
+    let mut chunked = [ChunkedBitSet::default(), ChunkedBitSet::default()];
+    for round in 0..16 {
+        let width = if round % 2 == 0 { 1 << 13 } else { 1 << 18 };
+        for _ in 0..next(8192) {
+            let (i, bit) = (next(2), next(width));
+            if next(4) == 0 {
+                BitSet::remove(&mut dense[i], bit);
+                sparse[i].remove(bit);
+                chunked[i].remove(bit);
+            } else {
+                BitSet::insert(&mut dense[i], bit);
+                sparse[i].insert(bit);
+                chunked[i].insert(bit);
+            }
+        }
+        let (i, j) = (next(2), next(2));
+        if next(2) == 0 {
+            let (other, sparse_other, chunked_other) =
+                (dense[j].clone(), sparse[j].clone(), chunked[j].clone());
+            BitSet::union_with(&mut dense[i], &other);
+            sparse[i].union_with(&sparse_other);
+            chunked[i].union_with(&chunked_other);
+        } else {
+            let (other, sparse_other, chunked_other) =
+                (dense[j].clone(), sparse[j].clone(), chunked[j].clone());
+            BitSet::difference_with(&mut dense[i], &other);
+            sparse[i].difference_with(&sparse_other);
+            chunked[i].difference_with(&chunked_other);
+        }
+        for i in 0..2 {
+            let expected: Vec<_> = dense[i].ones().collect();
+            assert_eq!(sparse[i].ones().collect::<Vec<_>>(), expected);
+            assert_eq!(chunked[i].ones().collect::<Vec<_>>(), expected);
+            assert_eq!(sparse[i].count(), expected.len());
+            assert_eq!(chunked[i].count(), expected.len());
+            let probe = next(1 << 18);
+            assert_eq!(chunked[i].contains(probe), dense[i].contains(probe));
+        }
+    }
+}
+
+#[derive(Parser)]
+struct Args {
+    #[arg(short)]
+    f: Option<String>,
+}
+
+fn main() -> std::io::Result<()> {
+    let args = Args::parse();
+    let mut reader: Box<dyn Read> = if let Some(ref f) = args.f {
+        Box::new(BufReader::new(std::fs::File::open(f)?))
+    } else {
+        Box::new(BufReader::new(std::io::stdin()))
+    };
+    let mut buf = String::new();
+    assert!(reader.read_to_string(&mut buf)? > 0);
+
+    let bril_prog: Program = serde_json::from_str(&buf).unwrap();
+    let prog = bril::shim::flattened_program_repr(bril_prog);
+
+    check_set_operations();
+    for idx in 0..prog.functions().count() {
+        let cfg = build_cfg(&prog.get_function(FunctionIdx(idx as u32)));
+        let live = elements(liveness(&cfg));
+        assert_eq!(elements(liveness_in::<SparseBitSet>(&cfg)), live);
+        assert_eq!(elements(liveness_in::<ChunkedBitSet>(&cfg)), live);
+        assert_eq!(elements(liveness_para_in::<SparseBitSet>(&cfg, 4)), live);
+        assert_eq!(elements(liveness_para_in::<ChunkedBitSet>(&cfg, 4)), live);
+
+        let reaching = elements(reaching_def(&cfg));
+        assert_eq!(elements(reaching_def_in::<SparseBitSet>(&cfg)), reaching);
+        assert_eq!(elements(reaching_def_in::<ChunkedBitSet>(&cfg)), reaching);
+        assert_eq!(
+            elements(reaching_def_para_in::<SparseBitSet>(&cfg, 4)),
+            reaching
+        );
+        assert_eq!(
+            elements(reaching_def_para_in::<ChunkedBitSet>(&cfg, 4)),
+            reaching
+        );
+    }
+    eprintln!("passed!");
+    Ok(())
+}
@@ -0,0 +1,83 @@
+use bril::ir::Instruction;
+use bril_analysis::call_graph::CallGraph;
+use bril_rs::Program;
+use clap::Parser;
+use std::io::{BufReader, Read};
+
+#[derive(Parser)]
+struct Args {
+    #[arg(short)]
+    f: Option<String>,
+}
+
+fn main() -> std::io::Result<()> {
+    let args = Args::parse();
+    let mut reader: Box<dyn Read> = if let Some(ref f) = args.f {
+        Box::new(BufReader::new(std::fs::File::open(f)?))
+    } else {
+        Box::new(BufReader::new(std::io::stdin()))
+    };
+    let mut buf = String::new();
+    assert!(reader.read_to_string(&mut buf)? > 0);
+
+    let bril_prog: Program = serde_json::from_str(&buf).unwrap();
+    let prog = bril::shim::flattened_program_repr(bril_prog);
+    let call_graph = CallGraph::new(&prog);
+
+    let bottom_up: Vec<_> = call_graph.bottom_up().collect();
+    let top_down: Vec<_> = call_graph.top_down().collect();
+    assert_eq!(bottom_up.len(), prog.functions().count());
+    assert_eq!(top_down.len(), bottom_up.len());
+    assert_eq!(
+        top_down
+            .first()
+            .map(|&function| call_graph.component(function)),
+        call_graph.components().len().checked_sub(1)
+    );
+    let mut sorted = bottom_up.clone();
+    sorted.sort();
+    sorted.dedup();
+    assert_eq!(sorted.len(), bottom_up.len());
+
+    let position = |function| {
+        bottom_up
+            .iter()
+            .position(|other| *other == function)
+            .unwrap()
+    };
+    for &caller in &bottom_up {
+        let instructions = prog.get_function(caller).instructions;
+        for instruction in instructions {
+            if let Instruction::Call(_, callee, _) = instruction {
+                assert!(call_graph.callees(caller).contains(callee));
+                assert!(call_graph.callers(*callee).contains(&caller));
+                if call_graph.component(caller) != call_graph.component(*callee)
+                {
+                    assert!(position(*callee) < position(caller));
+                } else {
+                    assert!(call_graph.is_recursive(caller));
+                }
+            }
+        }
+    }
+
+    let levels = call_graph.bottom_up_levels();
+    let mut level_of = vec![usize::MAX; call_graph.components().len()];
+    for (level, components) in levels.iter().enumerate() {
+        for &comp_idx in components {
+            level_of[comp_idx] = level;
+        }
+    }
+    for (comp_idx, component) in call_graph.components().iter().enumerate() {
+        for &function in &component.vertices {
+            for &callee in call_graph.callees(function) {
+                let callee_comp = call_graph.component(callee);
+                if callee_comp != comp_idx {
+                    assert!(level_of[callee_comp] < level_of[comp_idx]);
+                }
+            }
+        }
+    }
+    eprintln!("passed!");
+    Ok(())
+}
@@ -0,0 +1,54 @@
+use bril::interp::interpret;
+use bril_cfg::{OwnedCfg, build_cfg, lower_program};
+use bril_rs::Program;
+use clap::Parser;
+use std::io::{BufReader, Read};
+
+const FUEL: usize = 1_000_000;
+
+#[derive(Parser)]
+struct Args {
+    #[arg(short)]
+    f: Option<String>,
+}
+
+fn main() -> std::io::Result<()> {
+    let args = Args::parse();
+    let mut reader: Box<dyn Read> = if let Some(ref f) = args.f {
+        Box::new(BufReader::new(std::fs::File::open(f)?))
+    } else {
+        Box::new(BufReader::new(std::io::stdin()))
+    };
+    let mut buf = String::new();
+    assert!(reader.read_to_string(&mut buf)? > 0);
+
+    let bril_prog: Program = serde_json::from_str(&buf).unwrap();
+    let prog = bril::shim::flattened_program_repr(bril_prog);
+    let mut cfgs = vec![];
+    for function in prog.functions() {
+        let cfg = build_cfg(&function);
+        let critical = cfg.critical_edges();
+        let mut owned = OwnedCfg::from_cfg(&cfg);
+        assert_eq!(owned.critical_edges(), critical);
+        let split = owned.split_critical_edges();
+        assert_eq!(split.len(), critical.len());
+        assert!(owned.critical_edges().is_empty());
+        for (split_idx, (from, to)) in split.into_iter().zip(critical) {
+            assert_eq!(owned.predecessors(split_idx), [from]);
+            assert_eq!(owned.successors(split_idx), [to]);
+            assert!(owned.successors(from).contains(&split_idx));
+            assert!(owned.predecessors(to).contains(&split_idx));
+        }
+        cfgs.push(owned);
+    }
+    let lowered = lower_program(&prog, &cfgs);
+
+    // programs which trap or diverge may behave differently once lowered
+    if let Ok(expected) = interpret(&prog, &[], FUEL) {
+        let outcome = interpret(&lowered, &[], FUEL).unwrap();
+        assert_eq!(outcome.output, expected.output);
+        assert_eq!(outcome.return_value, expected.return_value);
+    }
+    eprintln!("passed!");
+    Ok(())
+}
@@ -0,0 +1,77 @@
+use bril::{
+    interp::{InterpError, interpret},
+    ir::{Instruction, Program},
+};
+use bril_opt::dce::eliminate_dead_code;
+use clap::Parser;
+use std::io::{BufReader, Read};
+
+const FUEL: usize = 1_000_000;
+
+/// both divisions are dead, but only the second is known not to trap
+const DEAD_DIVISIONS: &str = r#"{"functions": [{"name": "main", "args": [],
+"instrs": [
+    {"dest": "zero", "type": "int", "op": "const", "value": 0},
+    {"dest": "two", "type": "int", "op": "const", "value": 2},
+    {"dest": "a", "type": "int", "op": "div", "args": ["two", "zero"]},
+    {"dest": "b", "type": "int", "op": "div", "args": ["zero", "two"]},
+    {"op": "print", "args": ["two"]}
+]}]}"#;
+
+#[derive(Parser)]
+struct Args {
+    #[arg(short)]
+    f: Option<String>,
+}
+
+fn parse(json: &str) -> Program {
+    bril::shim::flattened_program_repr(serde_json::from_str(json).unwrap())
+}
+
+/// Checks that eliminating dead code in `prog` reaches a fixpoint and keeps
+/// its behavior, returning the optimized program.
+fn check(prog: Program) -> Program {
+    let mut optimized = prog.clone();
+    eliminate_dead_code(&mut optimized);
+    assert_eq!(eliminate_dead_code(&mut optimized.clone()), 0);
+
+    // diverging programs may run for longer once optimized
+    match interpret(&prog, &[], FUEL) {
+        Ok(expected) => {
+            let outcome = interpret(&optimized, &[], FUEL).unwrap();
+            assert_eq!(outcome.output, expected.output);
+            assert_eq!(outcome.return_value, expected.return_value);
+        }
+        Err(InterpError::DivisionByZero) => {
+            assert_eq!(
+                interpret(&optimized, &[], FUEL),
+                Err(InterpError::DivisionByZero)
+            );
+        }
+        Err(_) => {}
+    }
+    optimized
+}
+
+fn main() -> std::io::Result<()> {
+    let args = Args::parse();
+    let mut reader: Box<dyn Read> = if let Some(ref f) = args.f {
+        Box::new(BufReader::new(std::fs::File::open(f)?))
+    } else {
+        Box::new(BufReader::new(std::io::stdin()))
+    };
+    let mut buf = String::new();
+    assert!(reader.read_to_string(&mut buf)? > 0);
+
+    check(parse(&buf));
+
+    let optimized = check(parse(DEAD_DIVISIONS));
+    let divisions = optimized
+        .functions()
+        .flat_map(|function| function.instructions)
+        .filter(|instruction| matches!(instruction, Instruction::Div(..)))
+        .count();
+    assert_eq!(divisions, 1);
+    eprintln!("passed!");
+    Ok(())
+}
@@ -0,0 +1,122 @@
+use bril::{builder::BasicBlockIdx, ir::Variable};
+use bril_analysis::analysis::{DefUseChains, InstructionExt};
+use bril_cfg::{Cfg, build_cfg};
+use bril_rs::Program;
+use clap::Parser;
+use std::{
+    collections::{BTreeSet, HashSet},
+    io::{BufReader, Read},
+};
+
+#[derive(Parser)]
+struct Args {
+    #[arg(short)]
+    f: Option<String>,
+}
+
+fn reachable(cfg: &Cfg) -> HashSet<BasicBlockIdx> {
+    let mut reachable = HashSet::from([cfg.entry]);
+    let mut stack = vec![cfg.entry];
+    while let Some(current) = stack.pop() {
+        for next in cfg.successors(current) {
+            if reachable.insert(next) {
+                stack.push(next);
+            }
+        }
+    }
+    reachable
+}
+
+/// Definitions of `variable` reaching the `i`-th instruction of `block_idx`,
+/// found by walking every path backward from it to the last definition.
+fn walk_back(
+    cfg: &Cfg,
+    reachable: &HashSet<BasicBlockIdx>,
+    block_idx: BasicBlockIdx,
+    i: usize,
+    variable: Variable,
+) -> BTreeSet<usize> {
+    let last_def = |block_idx: BasicBlockIdx, end: usize| {
+        let block = &cfg.vertices[block_idx];
+        block.instructions[..end]
+            .iter()
+            .rposition(|instruction| {
+                instruction.dest().is_some_and(|dest| dest.0 == variable.0)
+            })
+            .map(|at| block.offset + at)
+    };
+    if let Some(def) = last_def(block_idx, i) {
+        return BTreeSet::from([def]);
+    }
+    let mut defs = BTreeSet::new();
+    let mut visited = HashSet::new();
+    let mut stack = vec![block_idx];
+    while let Some(current) = stack.pop() {
+        for predecessor in cfg.predecessors(current) {
+            if !reachable.contains(&predecessor) || !visited.insert(predecessor)
+            {
+                continue;
+            }
+            let end = cfg.vertices[predecessor].instructions.len();
+            match last_def(predecessor, end) {
+                Some(def) => {
+                    defs.insert(def);
+                }
+                None => stack.push(predecessor),
+            }
+        }
+    }
+    defs
+}
+
+fn main() -> std::io::Result<()> {
+    let args = Args::parse();
+    let mut reader: Box<dyn Read> = if let Some(ref f) = args.f {
+        Box::new(BufReader::new(std::fs::File::open(f)?))
+    } else {
+        Box::new(BufReader::new(std::io::stdin()))
+    };
+    let mut buf = String::new();
+    assert!(reader.read_to_string(&mut buf)? > 0);
+
+    let bril_prog: Program = serde_json::from_str(&buf).unwrap();
+    let prog = bril::shim::flattened_program_repr(bril_prog);
+
+    for function in prog.functions() {
+        let cfg = build_cfg(&function);
+        let chains = DefUseChains::new(&cfg);
+        for block in cfg.vertices.values() {
+            for offset in block.offset..block.offset + block.instructions.len()
+            {
+                for defs in chains.operand_defs(offset) {
+                    for &def in defs {
+                        assert!(chains.uses(def).contains(&offset));
+                    }
+                }
+            }
+        }
+
+        let reachable = reachable(&cfg);
+        for (block_idx, block) in cfg.vertices.iter() {
+            if !reachable.contains(&block_idx) {
+                continue;
+            }
+            for (i, instruction) in block.instructions.iter().enumerate() {
+                let offset = block.offset + i;
+                for (operand, variable) in
+                    instruction.operands().into_iter().enumerate()
+                {
+                    let expected =
+                        walk_back(&cfg, &reachable, block_idx, i, variable);
+                    let found = chains.reaching_defs(offset, operand);
+                    assert_eq!(
+                        found.iter().copied().collect::<BTreeSet<_>>(),
+                        expected
+                    );
+                }
+            }
+        }
+    }
+    eprintln!("passed!");
+    Ok(())
+}
@@ -0,0 +1,113 @@
+use bril_analysis::{
+    Direction, EdgeTransfers, SolveOptions, Widening,
+    analysis::branch_conditions, parallel, sequential,
+};
+use bril_cfg::{Cfg, build_cfg};
+use bril_rs::Program;
+use clap::Parser;
+use fixedbitset::FixedBitSet;
+use std::{
+    collections::HashMap,
+    io::{BufReader, Read},
+};
+
+#[derive(Parser)]
+struct Args {
+    #[arg(short)]
+    f: Option<String>,
+}
+
+/// Blocks executed since the last branch, where blocks are identified by
+/// their offset.
+fn check(cfg: &Cfg) {
+    let len = cfg
+        .vertices
+        .values()
+        .map(|block| block.offset + 1)
+        .max()
+        .unwrap_or_default();
+    let mut edge_transfers = EdgeTransfers::new();
+    for ((from, to), _) in branch_conditions(cfg) {
+        edge_transfers.insert(
+            (from, to),
+            Box::new(move |_: &FixedBitSet| FixedBitSet::with_capacity(len))
+                as Box<dyn Fn(&FixedBitSet) -> FixedBitSet + Send + Sync>,
+        );
+    }
+    let merge = |mut in1: FixedBitSet, in2: &FixedBitSet| {
+        in1.union_with(in2);
+        in1
+    };
+    let transfer = |block_idx, mut fact: FixedBitSet| {
+        fact.grow(len);
+        fact.insert(cfg.vertices[block_idx].offset);
+        fact
+    };
+
+    // Facts only grow, so widening with the union leaves the fixpoint alone.
+    let widen = |old: &FixedBitSet, mut new: FixedBitSet| {
+        new.union_with(old);
+        new
+    };
+    let narrow = |_: &FixedBitSet, new: FixedBitSet| new;
+    let widening = Widening {
+        widen: &widen,
+        narrow: &narrow,
+        narrowing_passes: 1,
+    };
+
+    for widening in [None, Some(widening)] {
+        let parallel_res = parallel::solve_dataflow(
+            cfg,
+            Direction::Forward,
+            FixedBitSet::with_capacity(len),
+            merge,
+            transfer,
+            4,
+            SolveOptions {
+                edge_transfers: Some(&edge_transfers),
+                widening,
+                ..Default::default()
+            },
+        );
+        let sequential_res = sequential::solve_dataflow(
+            cfg,
+            &(),
+            Direction::Forward,
+            HashMap::from([(cfg.entry, FixedBitSet::with_capacity(len))]),
+            merge,
+            transfer,
+            SolveOptions {
+                edge_transfers: Some(&edge_transfers),
+                widening,
+                ..Default::default()
+            },
+        );
+        for (block_idx, facts) in &sequential_res {
+            let other = &parallel_res[block_idx];
+            assert!(facts.before.ones().eq(other.before.ones()));
+            assert!(facts.after.ones().eq(other.after.ones()));
+        }
+    }
+}
+
+fn main() -> std::io::Result<()> {
+    let args = Args::parse();
+    let mut reader: Box<dyn Read> = if let Some(ref f) = args.f {
+        Box::new(BufReader::new(std::fs::File::open(f)?))
+    } else {
+        Box::new(BufReader::new(std::io::stdin()))
+    };
+    let mut buf = String::new();
+    assert!(reader.read_to_string(&mut buf)? > 0);
+
+    let bril_prog: Program = serde_json::from_str(&buf).unwrap();
+    let prog = bril::shim::flattened_program_repr(bril_prog);
+
+    for function in prog.functions() {
+        let cfg = build_cfg(&function);
+        check(&cfg);
+    }
+    eprintln!("passed!");
+    Ok(())
+}
@@ -0,0 +1,103 @@
+use bril::{builder::BasicBlockIdx, ir::FunctionIdx};
+use bril_analysis::{
+    BlockFacts, SolveOptions,
+    analysis::{
+        liveness_gen_kill, liveness_in, reaching_def_gen_kill, reaching_def_in,
+    },
+    parallel, sequential,
+};
+use bril_cfg::build_cfg;
+use bril_rs::Program;
+use clap::Parser;
+use fixedbitset::FixedBitSet;
+use slotmap::SecondaryMap;
+use std::{
+    collections::HashMap,
+    io::{BufReader, Read},
+};
+
+/// Ones of every fact, as the gen/kill solvers give all facts the same
+/// length.
+fn ones(
+    solution: impl IntoIterator<Item = (BasicBlockIdx, BlockFacts)>,
+) -> SecondaryMap<BasicBlockIdx, BlockFacts<Vec<usize>>> {
+    solution
+        .into_iter()
+        .map(|(block_idx, facts)| {
+            let facts = BlockFacts {
+                before: facts.before.ones().collect(),
+                after: facts.after.ones().collect(),
+            };
+            (block_idx, facts)
+        })
+        .collect()
+}
+
+#[derive(Parser)]
+struct Args {
+    #[arg(short)]
+    f: Option<String>,
+}
+
+fn main() -> std::io::Result<()> {
+    let args = Args::parse();
+    let mut reader: Box<dyn Read> = if let Some(ref f) = args.f {
+        Box::new(BufReader::new(std::fs::File::open(f)?))
+    } else {
+        Box::new(BufReader::new(std::io::stdin()))
+    };
+    let mut buf = String::new();
+    assert!(reader.read_to_string(&mut buf)? > 0);
+
+    let bril_prog: Program = serde_json::from_str(&buf).unwrap();
+    let prog = bril::shim::flattened_program_repr(bril_prog);
+
+    for idx in 0..prog.functions().count() {
+        let cfg = build_cfg(&prog.get_function(FunctionIdx(idx as u32)));
+        let problem = liveness_gen_kill(&cfg);
+        let live = ones(liveness_in::<FixedBitSet>(&cfg));
+        assert_eq!(
+            ones(sequential::solve_gen_kill(
+                &cfg,
+                &(),
+                &problem,
+                &HashMap::new(),
+                SolveOptions::default(),
+            )),
+            live
+        );
+        assert_eq!(
+            ones(parallel::solve_gen_kill(
+                &cfg,
+                &problem,
+                4,
+                SolveOptions::default()
+            )),
+            live
+        );
+
+        let problem = reaching_def_gen_kill(&cfg);
+        let reaching = ones(reaching_def_in::<FixedBitSet>(&cfg));
+        assert_eq!(
+            ones(sequential::solve_gen_kill(
+                &cfg,
+                &(),
+                &problem,
+                &HashMap::new(),
+                SolveOptions::default(),
+            )),
+            reaching
+        );
+        assert_eq!(
+            ones(parallel::solve_gen_kill(
+                &cfg,
+                &problem,
+                4,
+                SolveOptions::default()
+            )),
+            reaching
+        );
+    }
+    eprintln!("passed!");
+    Ok(())
+}
@@ -0,0 +1,37 @@
+use bril::interp::interpret;
+use bril_opt::gvn::global_value_numbering;
+use bril_rs::Program;
+use clap::Parser;
+use std::io::{BufReader, Read};
+
+const FUEL: usize = 1_000_000;
+
+#[derive(Parser)]
+struct Args {
+    #[arg(short)]
+    f: Option<String>,
+}
+
+fn main() -> std::io::Result<()> {
+    let args = Args::parse();
+    let mut reader: Box<dyn Read> = if let Some(ref f) = args.f {
+        Box::new(BufReader::new(std::fs::File::open(f)?))
+    } else {
+        Box::new(BufReader::new(std::io::stdin()))
+    };
+    let mut buf = String::new();
+    assert!(reader.read_to_string(&mut buf)? > 0);
+
+    let bril_prog: Program = serde_json::from_str(&buf).unwrap();
+    let prog = bril::shim::flattened_program_repr(bril_prog);
+    let (optimized, _) = global_value_numbering(&prog);
+
+    // programs which trap or diverge may behave differently once optimized
+    if let Ok(expected) = interpret(&prog, &[], FUEL) {
+        let outcome = interpret(&optimized, &[], FUEL).unwrap();
+        assert_eq!(outcome.output, expected.output);
+        assert_eq!(outcome.return_value, expected.return_value);
+    }
+    eprintln!("passed!");
+    Ok(())
+}
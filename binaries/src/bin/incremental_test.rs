@@ -0,0 +1,76 @@
+use bril::ir::{FunctionIdx, Instruction};
+use bril_analysis::analysis::{
+    liveness, liveness_incremental, liveness_para, liveness_para_incremental,
+    reaching_def, reaching_def_incremental, reaching_def_para,
+    reaching_def_para_incremental,
+};
+use bril_cfg::build_cfg;
+use bril_rs::Program;
+use clap::Parser;
+use std::{
+    collections::HashSet,
+    io::{BufReader, Read},
+};
+
+#[derive(Parser)]
+struct Args {
+    #[arg(short)]
+    f: Option<String>,
+}
+
+fn main() -> std::io::Result<()> {
+    let args = Args::parse();
+    let mut reader: Box<dyn Read> = if let Some(ref f) = args.f {
+        Box::new(BufReader::new(std::fs::File::open(f)?))
+    } else {
+        Box::new(BufReader::new(std::io::stdin()))
+    };
+    let mut buf = String::new();
+    assert!(reader.read_to_string(&mut buf)? > 0);
+
+    let bril_prog: Program = serde_json::from_str(&buf).unwrap();
+    let prog = bril::shim::flattened_program_repr(bril_prog);
+
+    for idx in 0..prog.functions().count() {
+        let function_idx = FunctionIdx(idx as u32);
+        let cfg = build_cfg(&prog.get_function(function_idx));
+        let live = liveness(&cfg);
+        let live_para = liveness_para(&cfg, 4);
+        let reaching = reaching_def(&cfg);
+        let reaching_para = reaching_def_para(&cfg, 4);
+
+        // erase the first instruction of every third block, keeping the
+        // offsets of all instructions
+        let mut edited = prog.clone();
+        let mut changed = HashSet::new();
+        let instructions = edited.function_instructions_mut(function_idx);
+        for (block_idx, block) in cfg.vertices.iter().step_by(3) {
+            if let Some(instruction) = block.instructions.first()
+                && !matches!(
+                    instruction,
+                    Instruction::Jmp(_) | Instruction::Br(..)
+                )
+            {
+                instructions[block.offset] = Instruction::Nop;
+                changed.insert(block_idx);
+            }
+        }
+
+        let cfg = build_cfg(&edited.get_function(function_idx));
+        assert_eq!(liveness_incremental(&cfg, live, &changed), liveness(&cfg));
+        assert_eq!(
+            reaching_def_incremental(&cfg, reaching, &changed),
+            reaching_def(&cfg)
+        );
+        assert_eq!(
+            liveness_para_incremental(&cfg, live_para, &changed, 4),
+            liveness_para(&cfg, 4)
+        );
+        assert_eq!(
+            reaching_def_para_incremental(&cfg, reaching_para, &changed, 4),
+            reaching_def_para(&cfg, 4)
+        );
+    }
+    eprintln!("passed!");
+    Ok(())
+}
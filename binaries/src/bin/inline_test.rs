@@ -0,0 +1,53 @@
+use bril::{interp::interpret, ir::Instruction};
+use bril_analysis::call_graph::CallGraph;
+use bril_opt::inline::{InlineThreshold, inline_calls};
+use bril_rs::Program;
+use clap::Parser;
+use std::io::{BufReader, Read};
+
+const FUEL: usize = 1_000_000;
+
+#[derive(Parser)]
+struct Args {
+    #[arg(short)]
+    f: Option<String>,
+}
+
+fn main() -> std::io::Result<()> {
+    let args = Args::parse();
+    let mut reader: Box<dyn Read> = if let Some(ref f) = args.f {
+        Box::new(BufReader::new(std::fs::File::open(f)?))
+    } else {
+        Box::new(BufReader::new(std::io::stdin()))
+    };
+    let mut buf = String::new();
+    assert!(reader.read_to_string(&mut buf)? > 0);
+
+    let bril_prog: Program = serde_json::from_str(&buf).unwrap();
+    let prog = bril::shim::flattened_program_repr(bril_prog);
+    let unlimited = InlineThreshold {
+        max_callee_size: usize::MAX,
+        max_caller_size: usize::MAX,
+    };
+    let (inlined, _) = inline_calls(&prog, InlineThreshold::default());
+    let (fully_inlined, _) = inline_calls(&prog, unlimited);
+
+    // without a threshold, only calls to recursive functions are left
+    let call_graph = CallGraph::new(&prog);
+    for instruction in &fully_inlined.instructions {
+        if let Instruction::Call(_, callee, _) = instruction {
+            assert!(call_graph.is_recursive(*callee));
+        }
+    }
+
+    // programs which trap or diverge may behave differently once optimized
+    if let Ok(expected) = interpret(&prog, &[], FUEL) {
+        for optimized in [&inlined, &fully_inlined] {
+            let outcome = interpret(optimized, &[], FUEL).unwrap();
+            assert_eq!(outcome.output, expected.output);
+            assert_eq!(outcome.return_value, expected.return_value);
+        }
+    }
+    eprintln!("passed!");
+    Ok(())
+}
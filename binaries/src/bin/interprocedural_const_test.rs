@@ -0,0 +1,94 @@
+use bril::{
+    interp::interpret,
+    ir::{FunctionIdx, Instruction},
+};
+use bril_analysis::{
+    call_graph::CallGraph,
+    interprocedural_const::{
+        interprocedural_const_prop, interprocedural_const_prop_para,
+    },
+};
+use bril_opt::rebuild::{Block, layout, rebuild};
+use bril_rs::Program;
+use clap::Parser;
+use std::io::{BufReader, Read};
+
+const FUEL: usize = 1_000_000;
+
+#[derive(Parser)]
+struct Args {
+    #[arg(short)]
+    f: Option<String>,
+}
+
+fn main() -> std::io::Result<()> {
+    let args = Args::parse();
+    let mut reader: Box<dyn Read> = if let Some(ref f) = args.f {
+        Box::new(BufReader::new(std::fs::File::open(f)?))
+    } else {
+        Box::new(BufReader::new(std::io::stdin()))
+    };
+    let mut buf = String::new();
+    assert!(reader.read_to_string(&mut buf)? > 0);
+
+    let bril_prog: Program = serde_json::from_str(&buf).unwrap();
+    let prog = bril::shim::flattened_program_repr(bril_prog);
+    let call_graph = CallGraph::new(&prog);
+    let solution = interprocedural_const_prop(&prog, &call_graph);
+    assert_eq!(
+        solution,
+        interprocedural_const_prop_para(&prog, &call_graph, 4)
+    );
+
+    // pinning the parameters and call results to the constants found must
+    // not change the behavior of the program
+    let constant_arguments = solution.constant_arguments(&prog);
+    let pinned = rebuild(&prog, |function_idx, cfg| {
+        let parameters = prog.get_function(function_idx).parameters;
+        let mut blocks = vec![];
+        for block_idx in layout(cfg) {
+            let mut block = Block::from_cfg(cfg, block_idx);
+            let mut instructions = vec![];
+            if block_idx == cfg.entry && cfg.predecessors(block_idx).is_empty()
+            {
+                for (function, arguments) in &constant_arguments {
+                    if *function == function_idx {
+                        for (i, value) in arguments {
+                            instructions.push(Instruction::Const(
+                                parameters[*i],
+                                value.clone(),
+                            ));
+                        }
+                    }
+                }
+            }
+            for instruction in block.instructions {
+                let pin = match &instruction {
+                    Instruction::Call(Some(dest), callee, _) => solution
+                        .return_value(*callee)
+                        .constant()
+                        .map(|value| Instruction::Const(*dest, value.clone())),
+                    _ => None,
+                };
+                instructions.push(instruction);
+                instructions.extend(pin);
+            }
+            block.instructions = instructions;
+            blocks.push(block);
+        }
+        blocks
+    });
+
+    for (idx, function) in prog.functions().enumerate() {
+        if function.name == "main" {
+            assert!(solution.entry(FunctionIdx(idx as u32)).is_reachable());
+        }
+    }
+    if let Ok(expected) = interpret(&prog, &[], FUEL) {
+        let outcome = interpret(&pinned, &[], FUEL).unwrap();
+        assert_eq!(outcome.output, expected.output);
+        assert_eq!(outcome.return_value, expected.return_value);
+    }
+    eprintln!("passed!");
+    Ok(())
+}
@@ -0,0 +1,192 @@
+use bril::{
+    interp::interpret_observed,
+    ir::{Instruction, Program, Type, Value, Variable},
+};
+use bril_analysis::analysis::{
+    AbstractEnv, Interval, abstract_interp, abstract_interp_instr,
+    nonzero_divisors,
+};
+use bril_cfg::build_cfg;
+use clap::Parser;
+use std::{
+    collections::HashSet,
+    io::{BufReader, Read},
+};
+
+const FUEL: usize = 1_000_000;
+
+/// counts up to a bound the analysis knows nothing about, so the interval
+/// of `i` only stabilizes once widened
+const UNBOUNDED_LOOP: &str = r#"{"functions": [{"name": "main",
+"args": [{"name": "n", "type": "int"}], "instrs": [
+    {"dest": "i", "type": "int", "op": "const", "value": 0},
+    {"dest": "one", "type": "int", "op": "const", "value": 1},
+    {"label": "head"},
+    {"dest": "c", "type": "bool", "op": "lt", "args": ["i", "n"]},
+    {"op": "br", "args": ["c"], "labels": ["body", "done"]},
+    {"label": "body"},
+    {"dest": "i", "type": "int", "op": "add", "args": ["i", "one"]},
+    {"op": "jmp", "args": [], "labels": ["head"]},
+    {"label": "done"},
+    {"op": "print", "args": ["i"]}
+]}]}"#;
+
+/// counts up to 10, which narrowing recovers after widening
+const BOUNDED_LOOP: &str = r#"{"functions": [{"name": "main", "args": [],
+"instrs": [
+    {"dest": "i", "type": "int", "op": "const", "value": 0},
+    {"dest": "one", "type": "int", "op": "const", "value": 1},
+    {"dest": "ten", "type": "int", "op": "const", "value": 10},
+    {"label": "head"},
+    {"dest": "c", "type": "bool", "op": "lt", "args": ["i", "ten"]},
+    {"op": "br", "args": ["c"], "labels": ["body", "done"]},
+    {"label": "body"},
+    {"dest": "i", "type": "int", "op": "add", "args": ["i", "one"]},
+    {"op": "jmp", "args": [], "labels": ["head"]},
+    {"label": "done"},
+    {"op": "print", "args": ["i"]}
+]}]}"#;
+
+/// divides only by positive values of `x`
+const GUARDED_DIVISION: &str = r#"{"functions": [{"name": "main",
+"args": [{"name": "x", "type": "int"}], "instrs": [
+    {"dest": "zero", "type": "int", "op": "const", "value": 0},
+    {"dest": "c", "type": "bool", "op": "gt", "args": ["x", "zero"]},
+    {"op": "br", "args": ["c"], "labels": ["divide", "done"]},
+    {"label": "divide"},
+    {"dest": "q", "type": "int", "op": "div", "args": ["zero", "x"]},
+    {"op": "print", "args": ["q"]},
+    {"label": "done"},
+    {"dest": "q", "type": "int", "op": "div", "args": ["x", "x"]},
+    {"op": "print", "args": ["q"]}
+]}]}"#;
+
+#[derive(Parser)]
+struct Args {
+    #[arg(short)]
+    f: Option<String>,
+}
+
+/// Per function, the abstract state before every instruction, or `None` if
+/// the analysis finds it unreachable, and the `div` instructions it proves
+/// to never divide by zero.
+type Analyzed = Vec<(Vec<Option<AbstractEnv<Interval>>>, HashSet<usize>)>;
+
+fn analyze(prog: &Program) -> Analyzed {
+    prog.functions()
+        .map(|function| {
+            let cfg = build_cfg(&function);
+            let blocks = abstract_interp::<Interval>(&cfg);
+            let instr_facts = abstract_interp_instr(&cfg, &blocks);
+            let mut states = vec![None; function.instructions.len()];
+            for (block_idx, block) in cfg.vertices.iter() {
+                let Some(points) = instr_facts.points(block_idx) else {
+                    continue;
+                };
+                for (i, env) in points.into_iter().enumerate() {
+                    if i < block.instructions.len() {
+                        states[block.offset + i] = Some(env);
+                    }
+                }
+            }
+            let nonzero = nonzero_divisors(&cfg, &blocks).into_iter().collect();
+            (states, nonzero)
+        })
+        .collect()
+}
+
+/// Runs `prog` and checks that every `int` value held right before an
+/// instruction lies in its interval there, and that no division proven safe
+/// divides by zero.
+fn check_run(prog: &Program, analyzed: &Analyzed, args: &[Value]) {
+    let functions: Vec<_> = prog.functions().collect();
+    // programs may trap, diverge or expect arguments, which only ends the
+    // run early
+    let _ = interpret_observed(prog, args, FUEL, |function, pc, variables| {
+        let (states, nonzero) = &analyzed[function.0 as usize];
+        let env = states[pc]
+            .as_ref()
+            .expect("reached an instruction deemed unreachable");
+        for (&number, value) in variables {
+            if let Value::Int(value) = value {
+                let interval = env
+                    .get(Variable(number, Type::Int))
+                    .expect("reached a point deemed unreachable");
+                assert!(
+                    interval.contains(*value),
+                    "x{number} = {value} is not in {interval}"
+                );
+            }
+        }
+        if nonzero.contains(&pc) {
+            let Instruction::Div(_, _, divisor) =
+                functions[function.0 as usize].instructions[pc]
+            else {
+                unreachable!("only divisions are reported");
+            };
+            assert_ne!(variables.get(&divisor.0), Some(&Value::Int(0)));
+        }
+    });
+}
+
+fn parse(json: &str) -> Program {
+    bril::shim::flattened_program_repr(serde_json::from_str(json).unwrap())
+}
+
+/// Interval of the value printed at the end of the single function of
+/// `prog`.
+fn printed(prog: &Program, analyzed: &Analyzed) -> Interval {
+    let function = prog.functions().next().unwrap();
+    let Instruction::Print(args) = function.instructions.last().unwrap() else {
+        unreachable!("programs end by printing");
+    };
+    let (states, _) = &analyzed[0];
+    states
+        .last()
+        .unwrap()
+        .as_ref()
+        .unwrap()
+        .get(args[0])
+        .unwrap()
+}
+
+fn main() -> std::io::Result<()> {
+    let args = Args::parse();
+    let mut reader: Box<dyn Read> = if let Some(ref f) = args.f {
+        Box::new(BufReader::new(std::fs::File::open(f)?))
+    } else {
+        Box::new(BufReader::new(std::io::stdin()))
+    };
+    let mut buf = String::new();
+    assert!(reader.read_to_string(&mut buf)? > 0);
+
+    let prog = parse(&buf);
+    check_run(&prog, &analyze(&prog), &[]);
+
+    // solving loops terminates thanks to widening
+    let unbounded = parse(UNBOUNDED_LOOP);
+    let analyzed = analyze(&unbounded);
+    assert_eq!(printed(&unbounded, &analyzed).hi, i64::MAX);
+    for n in [-3, 0, 5] {
+        check_run(&unbounded, &analyzed, &[Value::Int(n)]);
+    }
+    let bounded = parse(BOUNDED_LOOP);
+    let analyzed = analyze(&bounded);
+    assert_eq!(printed(&bounded, &analyzed), Interval { lo: 10, hi: 10 });
+    check_run(&bounded, &analyzed, &[]);
+
+    // only the division guarded by `x > 0` is safe
+    let guarded = parse(GUARDED_DIVISION);
+    let analyzed = analyze(&guarded);
+    let function = guarded.functions().next().unwrap();
+    let divisions: Vec<_> = (0..function.instructions.len())
+        .filter(|&pc| matches!(function.instructions[pc], Instruction::Div(..)))
+        .collect();
+    assert_eq!(analyzed[0].1, HashSet::from([divisions[0]]));
+    for x in [-2, 0, 7] {
+        check_run(&guarded, &analyzed, &[Value::Int(x)]);
+    }
+
+    eprintln!("passed!");
+    Ok(())
+}
@@ -0,0 +1,57 @@
+use bril::interp::interpret;
+use bril_cfg::{OwnedCfg, build_cfg, lower_program};
+use bril_rs::Program;
+use clap::Parser;
+use std::io::{BufReader, Read};
+
+const FUEL: usize = 1_000_000;
+
+#[derive(Parser)]
+struct Args {
+    #[arg(short)]
+    f: Option<String>,
+}
+
+fn main() -> std::io::Result<()> {
+    let args = Args::parse();
+    let mut reader: Box<dyn Read> = if let Some(ref f) = args.f {
+        Box::new(BufReader::new(std::fs::File::open(f)?))
+    } else {
+        Box::new(BufReader::new(std::io::stdin()))
+    };
+    let mut buf = String::new();
+    assert!(reader.read_to_string(&mut buf)? > 0);
+
+    let bril_prog: Program = serde_json::from_str(&buf).unwrap();
+    let prog = bril::shim::flattened_program_repr(bril_prog);
+    let cfgs: Vec<_> = prog
+        .functions()
+        .map(|function| {
+            let mut cfg = OwnedCfg::from_cfg(&build_cfg(&function));
+            cfg.split_critical_edges();
+            assert!(cfg.critical_edges().is_empty());
+            // nothing falls through anymore
+            cfg.layout.reverse();
+            cfg.merge_straight_line();
+            for block_idx in cfg.layout.clone() {
+                if let [predecessor] = cfg.predecessors(block_idx)[..]
+                    && predecessor != block_idx
+                    && block_idx != cfg.entry
+                {
+                    assert!(cfg.successors(predecessor).len() > 1);
+                }
+            }
+            cfg
+        })
+        .collect();
+    let lowered = lower_program(&prog, &cfgs);
+
+    // programs which trap or diverge may behave differently once lowered
+    if let Ok(expected) = interpret(&prog, &[], FUEL) {
+        let outcome = interpret(&lowered, &[], FUEL).unwrap();
+        assert_eq!(outcome.output, expected.output);
+        assert_eq!(outcome.return_value, expected.return_value);
+    }
+    eprintln!("passed!");
+    Ok(())
+}
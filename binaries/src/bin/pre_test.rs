@@ -0,0 +1,58 @@
+use bril::{interp::interpret, ir::Program};
+use bril_opt::pre::partial_redundancy_elimination;
+use clap::Parser;
+use std::io::{BufReader, Read};
+
+const FUEL: usize = 1_000_000;
+
+/// `dead` is unreachable from the entry, yet jumps to a block computing the
+/// same expression
+const DEAD_PREDECESSOR: &str = r#"{"functions": [{"name": "main", "args": [],
+"instrs": [
+    {"dest": "a", "type": "int", "op": "const", "value": 3},
+    {"dest": "b", "type": "int", "op": "const", "value": 4},
+    {"label": "top"},
+    {"dest": "s", "type": "int", "op": "add", "args": ["a", "b"]},
+    {"op": "print", "args": ["s"]},
+    {"op": "ret", "args": []},
+    {"label": "dead"},
+    {"dest": "s", "type": "int", "op": "add", "args": ["a", "b"]},
+    {"op": "jmp", "args": [], "labels": ["top"]}
+]}]}"#;
+
+#[derive(Parser)]
+struct Args {
+    #[arg(short)]
+    f: Option<String>,
+}
+
+fn parse(json: &str) -> Program {
+    bril::shim::flattened_program_repr(serde_json::from_str(json).unwrap())
+}
+
+fn check(prog: Program) {
+    let (optimized, _) = partial_redundancy_elimination(&prog);
+
+    // programs which trap or diverge may behave differently once optimized
+    if let Ok(expected) = interpret(&prog, &[], FUEL) {
+        let outcome = interpret(&optimized, &[], FUEL).unwrap();
+        assert_eq!(outcome.output, expected.output);
+        assert_eq!(outcome.return_value, expected.return_value);
+    }
+}
+
+fn main() -> std::io::Result<()> {
+    let args = Args::parse();
+    let mut reader: Box<dyn Read> = if let Some(ref f) = args.f {
+        Box::new(BufReader::new(std::fs::File::open(f)?))
+    } else {
+        Box::new(BufReader::new(std::io::stdin()))
+    };
+    let mut buf = String::new();
+    assert!(reader.read_to_string(&mut buf)? > 0);
+
+    check(parse(&buf));
+    check(parse(DEAD_PREDECESSOR));
+    eprintln!("passed!");
+    Ok(())
+}
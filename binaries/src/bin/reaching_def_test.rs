@@ -1,7 +1,6 @@
 use bril_cfg::build_cfg;
 use bril_rs::Program;
 use clap::Parser;
-use slotmap::SecondaryMap;
 use std::io::{BufReader, Read};
 
 #[derive(Parser)]
@@ -25,13 +24,15 @@ fn main() -> std::io::Result<()> {
 
     for function in prog.functions() {
         let cfg = build_cfg(&function);
-        let parallel_res: SecondaryMap<_, _> =
-            bril_analysis::analysis::reaching_def_para(&cfg, 4)
-                .into_iter()
-                .map(|(k, v)| (k, v.clone()))
-                .collect();
+        let parallel_res = bril_analysis::analysis::reaching_def_para(&cfg, 4);
         let sequential_res = bril_analysis::analysis::reaching_def(&cfg);
         assert_eq!(parallel_res, sequential_res);
+        let instr_res =
+            bril_analysis::analysis::reaching_def_instr(&cfg, &sequential_res);
+        for (block_idx, facts) in &sequential_res {
+            let points = instr_res.points(block_idx).unwrap();
+            assert!(points.last().unwrap().ones().eq(facts.after.ones()));
+        }
     }
     eprintln!("passed!");
     Ok(())
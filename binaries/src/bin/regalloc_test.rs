@@ -0,0 +1,71 @@
+use bril::{interp::interpret, ir::Instruction};
+use bril_analysis::analysis::InstructionExt;
+use bril_opt::regalloc::{RegAllocError, allocate_registers};
+use bril_rs::Program;
+use clap::Parser;
+use std::io::{BufReader, Read};
+
+const FUEL: usize = 1_000_000;
+const REGISTERS: usize = 4;
+
+#[derive(Parser)]
+struct Args {
+    #[arg(short)]
+    f: Option<String>,
+}
+
+fn main() -> std::io::Result<()> {
+    let args = Args::parse();
+    let mut reader: Box<dyn Read> = if let Some(ref f) = args.f {
+        Box::new(BufReader::new(std::fs::File::open(f)?))
+    } else {
+        Box::new(BufReader::new(std::io::stdin()))
+    };
+    let mut buf = String::new();
+    assert!(reader.read_to_string(&mut buf)? > 0);
+
+    let bril_prog: Program = serde_json::from_str(&buf).unwrap();
+    let prog = bril::shim::flattened_program_repr(bril_prog);
+    // every failure reports more registers than were given, for the first
+    // function that did not fit
+    let mut registers = REGISTERS;
+    let allocated = loop {
+        match allocate_registers(&prog, registers) {
+            Ok((allocated, _)) => break allocated,
+            Err(RegAllocError::TooFewRegisters { needed, .. }) => {
+                assert!(needed > registers);
+                registers = needed;
+            }
+        }
+    };
+
+    // slots are only copied from and to registers
+    let is_register =
+        |variable: &bril::ir::Variable| (variable.0 as usize) < registers;
+    for function in allocated.functions() {
+        assert!(function.parameters.iter().all(is_register));
+        for instruction in function.instructions {
+            match instruction {
+                Instruction::Id(dest, src) => {
+                    assert!(is_register(dest) || is_register(src))
+                }
+                _ => assert!(
+                    instruction
+                        .operands()
+                        .iter()
+                        .chain(instruction.dest().as_ref())
+                        .all(is_register)
+                ),
+            }
+        }
+    }
+
+    // programs which trap or diverge may behave differently once allocated
+    if let Ok(expected) = interpret(&prog, &[], FUEL) {
+        let outcome = interpret(&allocated, &[], FUEL).unwrap();
+        assert_eq!(outcome.output, expected.output);
+        assert_eq!(outcome.return_value, expected.return_value);
+    }
+    eprintln!("passed!");
+    Ok(())
+}
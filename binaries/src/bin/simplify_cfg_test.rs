@@ -0,0 +1,58 @@
+use bril::{interp::interpret, ir::Program};
+use bril_opt::simplify_cfg::{SimplifyStats, simplify_cfg};
+use clap::Parser;
+use std::io::{BufReader, Read};
+
+const FUEL: usize = 1_000_000;
+
+/// `top` is only reached through the jump, so it merges into the entry
+const JUMP_TO_SINGLE_PREDECESSOR: &str = r#"{"functions": [{"name": "main",
+"args": [], "instrs": [
+    {"dest": "a", "type": "int", "op": "const", "value": 3},
+    {"op": "jmp", "args": [], "labels": ["top"]},
+    {"label": "top"},
+    {"op": "print", "args": ["a"]}
+]}]}"#;
+
+#[derive(Parser)]
+struct Args {
+    #[arg(short)]
+    f: Option<String>,
+}
+
+fn check(prog: &Program) -> SimplifyStats {
+    let (optimized, stats) = simplify_cfg(prog);
+    assert!(stats.blocks_after <= stats.blocks_before);
+    let (_, again) = simplify_cfg(&optimized);
+    assert_eq!(again.blocks_before, stats.blocks_after);
+    assert_eq!(again.blocks_after, stats.blocks_after);
+
+    // programs which trap or diverge may behave differently once optimized
+    if let Ok(expected) = interpret(prog, &[], FUEL) {
+        let outcome = interpret(&optimized, &[], FUEL).unwrap();
+        assert_eq!(outcome.output, expected.output);
+        assert_eq!(outcome.return_value, expected.return_value);
+    }
+    stats
+}
+
+fn main() -> std::io::Result<()> {
+    let args = Args::parse();
+    let mut reader: Box<dyn Read> = if let Some(ref f) = args.f {
+        Box::new(BufReader::new(std::fs::File::open(f)?))
+    } else {
+        Box::new(BufReader::new(std::io::stdin()))
+    };
+    let mut buf = String::new();
+    assert!(reader.read_to_string(&mut buf)? > 0);
+
+    check(&bril::shim::flattened_program_repr(
+        serde_json::from_str(&buf).unwrap(),
+    ));
+    let merged = check(&bril::shim::flattened_program_repr(
+        serde_json::from_str(JUMP_TO_SINGLE_PREDECESSOR).unwrap(),
+    ));
+    assert_eq!(merged.blocks_after, 1);
+    eprintln!("passed!");
+    Ok(())
+}
@@ -0,0 +1,74 @@
+use bril::{
+    interp::{InterpError, interpret},
+    ir::FunctionIdx,
+};
+use bril_analysis::{
+    analysis::{liveness, liveness_with},
+    call_graph::CallGraph,
+    summary::Summaries,
+};
+use bril_cfg::build_cfg;
+use bril_rs::Program;
+use clap::Parser;
+use std::io::{BufReader, Read};
+
+const FUEL: usize = 1_000_000;
+
+#[derive(Parser)]
+struct Args {
+    #[arg(short)]
+    f: Option<String>,
+}
+
+fn main() -> std::io::Result<()> {
+    let args = Args::parse();
+    let mut reader: Box<dyn Read> = if let Some(ref f) = args.f {
+        Box::new(BufReader::new(std::fs::File::open(f)?))
+    } else {
+        Box::new(BufReader::new(std::io::stdin()))
+    };
+    let mut buf = String::new();
+    assert!(reader.read_to_string(&mut buf)? > 0);
+
+    let bril_prog: Program = serde_json::from_str(&buf).unwrap();
+    let prog = bril::shim::flattened_program_repr(bril_prog);
+    let call_graph = CallGraph::new(&prog);
+    let summaries = Summaries::new(&prog, &call_graph);
+    let summaries_para = Summaries::new_para(&prog, &call_graph, 4);
+
+    for (idx, function) in prog.functions().enumerate() {
+        let function_idx = FunctionIdx(idx as u32);
+        let summary = summaries.get(function_idx);
+        assert_eq!(summary, summaries_para.get(function_idx));
+        if call_graph.is_recursive(function_idx) {
+            assert!(summary.may_not_return);
+        }
+        for &callee in call_graph.callees(function_idx) {
+            assert!(summary.prints >= summaries.get(callee).prints);
+        }
+
+        // skipping the arguments callees never read only shrinks liveness
+        let cfg = build_cfg(&function);
+        let live = liveness(&cfg);
+        let live_with =
+            liveness_with(&cfg, |instruction| summaries.operands(instruction));
+        for (block_idx, facts) in &live_with {
+            assert!(facts.before.is_subset(&live[block_idx].before));
+            assert!(facts.after.is_subset(&live[block_idx].after));
+        }
+
+        if function.name == "main" {
+            match interpret(&prog, &[], FUEL) {
+                Ok(outcome) if !summary.prints => {
+                    assert!(outcome.output.is_empty())
+                }
+                Err(InterpError::OutOfFuel | InterpError::DivisionByZero) => {
+                    assert!(summary.may_not_return)
+                }
+                _ => {}
+            }
+        }
+    }
+    eprintln!("passed!");
+    Ok(())
+}
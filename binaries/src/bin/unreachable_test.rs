@@ -0,0 +1,81 @@
+use bril::builder::BasicBlockIdx;
+use bril_analysis::analysis::{liveness, reaching_def};
+use bril_cfg::{Cfg, build_cfg};
+use bril_rs::Program;
+use clap::Parser;
+use std::{
+    collections::HashSet,
+    io::{BufReader, Read},
+};
+
+/// `dead` is unreachable from the entry, yet jumps to a block that is solved
+const DEAD_PREDECESSOR: &str = r#"{"functions": [{"name": "main", "args": [],
+"instrs": [
+    {"dest": "a", "type": "int", "op": "const", "value": 3},
+    {"label": "top"},
+    {"op": "print", "args": ["a"]},
+    {"op": "ret", "args": []},
+    {"label": "dead"},
+    {"dest": "a", "type": "int", "op": "const", "value": 4},
+    {"dest": "b", "type": "int", "op": "add", "args": ["a", "a"]},
+    {"op": "jmp", "args": [], "labels": ["top"]}
+]}]}"#;
+
+#[derive(Parser)]
+struct Args {
+    #[arg(short)]
+    f: Option<String>,
+}
+
+fn reachable(cfg: &Cfg) -> HashSet<BasicBlockIdx> {
+    let mut reachable = HashSet::from([cfg.entry]);
+    let mut stack = vec![cfg.entry];
+    while let Some(current) = stack.pop() {
+        for next in cfg.successors(current) {
+            if reachable.insert(next) {
+                stack.push(next);
+            }
+        }
+    }
+    reachable
+}
+
+fn check(bril_prog: Program) {
+    let prog = bril::shim::flattened_program_repr(bril_prog);
+    for function in prog.functions() {
+        let cfg = build_cfg(&function);
+        let reachable = reachable(&cfg);
+
+        let live = liveness(&cfg);
+        assert_eq!(live.keys().collect::<HashSet<_>>(), reachable);
+
+        // definitions in unreachable blocks reach nothing
+        let reaching = reaching_def(&cfg);
+        assert_eq!(reaching.keys().collect::<HashSet<_>>(), reachable);
+        for (block_idx, block) in cfg.vertices.iter() {
+            if reachable.contains(&block_idx) {
+                continue;
+            }
+            let offsets = block.offset..block.offset + block.instructions.len();
+            for facts in reaching.values() {
+                assert!(facts.before.ones().all(|def| !offsets.contains(&def)));
+            }
+        }
+    }
+}
+
+fn main() -> std::io::Result<()> {
+    let args = Args::parse();
+    let mut reader: Box<dyn Read> = if let Some(ref f) = args.f {
+        Box::new(BufReader::new(std::fs::File::open(f)?))
+    } else {
+        Box::new(BufReader::new(std::io::stdin()))
+    };
+    let mut buf = String::new();
+    assert!(reader.read_to_string(&mut buf)? > 0);
+
+    check(serde_json::from_str(&buf).unwrap());
+    check(serde_json::from_str(DEAD_PREDECESSOR).unwrap());
+    eprintln!("passed!");
+    Ok(())
+}
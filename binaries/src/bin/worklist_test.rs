@@ -0,0 +1,76 @@
+use bril::ir::FunctionIdx;
+use bril_analysis::{
+    SolveStats, Worklist,
+    analysis::{
+        liveness, liveness_para_with_stats, liveness_with_stats, reaching_def,
+        reaching_def_para_with_stats, reaching_def_with_stats,
+    },
+};
+use bril_cfg::build_cfg;
+use bril_rs::Program;
+use clap::Parser;
+use std::io::{BufReader, Read};
+
+/// Every solved block is transferred at least once, and every block taken
+/// off the worklist exactly once.
+fn check_stats(stats: &SolveStats, blocks: usize) {
+    assert_eq!(stats.iterations, stats.transfers);
+    assert!(stats.transfers >= blocks);
+    assert_eq!(
+        stats
+            .component_timings
+            .iter()
+            .map(|(size, _)| size)
+            .sum::<usize>(),
+        blocks
+    );
+}
+
+#[derive(Parser)]
+struct Args {
+    #[arg(short)]
+    f: Option<String>,
+}
+
+fn main() -> std::io::Result<()> {
+    let args = Args::parse();
+    let mut reader: Box<dyn Read> = if let Some(ref f) = args.f {
+        Box::new(BufReader::new(std::fs::File::open(f)?))
+    } else {
+        Box::new(BufReader::new(std::io::stdin()))
+    };
+    let mut buf = String::new();
+    assert!(reader.read_to_string(&mut buf)? > 0);
+
+    let bril_prog: Program = serde_json::from_str(&buf).unwrap();
+    let prog = bril::shim::flattened_program_repr(bril_prog);
+
+    for idx in 0..prog.functions().count() {
+        let cfg = build_cfg(&prog.get_function(FunctionIdx(idx as u32)));
+        let live = liveness(&cfg);
+        let reaching = reaching_def(&cfg);
+        for worklist in [
+            Worklist::Fifo,
+            Worklist::Lifo,
+            Worklist::Priority,
+            Worklist::RoundRobin,
+        ] {
+            let (solution, stats) = liveness_with_stats(&cfg, worklist);
+            assert_eq!(solution, live, "{worklist:?}");
+            check_stats(&stats, solution.len());
+            let (solution, stats) = reaching_def_with_stats(&cfg, worklist);
+            assert_eq!(solution, reaching, "{worklist:?}");
+            check_stats(&stats, solution.len());
+
+            let (solution, stats) = liveness_para_with_stats(&cfg, worklist, 4);
+            check_stats(&stats, solution.len());
+            assert_eq!(solution, live, "{worklist:?}");
+            let (solution, stats) =
+                reaching_def_para_with_stats(&cfg, worklist, 4);
+            check_stats(&stats, solution.len());
+            assert_eq!(solution, reaching, "{worklist:?}");
+        }
+    }
+    eprintln!("passed!");
+    Ok(())
+}
@@ -1,23 +1,66 @@
+mod abstract_interp;
+mod available_copies;
+mod const_prop;
+mod def_use;
+mod interval;
+mod lazy_code_motion;
 mod liveness;
+mod parity;
 mod reaching_def;
+mod sign;
+mod uninitialized;
 mod prelude {
-    pub(crate) use super::InstructionExt;
-    pub(crate) use crate::{Direction, parallel, sequential};
-    pub(crate) use bril::builder::BasicBlockIdx;
+    pub(crate) use super::{InstructionExt, gen_kill_transfer};
+    pub(crate) use crate::{
+        BlockFacts, Direction, SolveOptions, SolveStats, Worklist,
+        bitset::BitSet, gen_kill::GenKill, instr::InstrFacts, parallel,
+        sequential,
+    };
+    pub(crate) use bril::{builder::BasicBlockIdx, ir::Instruction};
     pub(crate) use bril_cfg::Cfg;
-    pub(crate) use dashmap::DashMap;
     pub(crate) use fixedbitset::FixedBitSet;
     pub(crate) use rayon::prelude::*;
     pub(crate) use slotmap::SecondaryMap;
 }
-use bril::ir::{Instruction, Variable};
+use crate::bitset::BitSet;
+use bril::{
+    builder::BasicBlockIdx,
+    ir::{Instruction, Variable},
+};
+use slotmap::SecondaryMap;
 
+pub use abstract_interp::*;
+pub use available_copies::*;
+pub use const_prop::*;
+pub use def_use::*;
+pub use interval::*;
+pub use lazy_code_motion::*;
 pub use liveness::*;
+pub use parity::*;
 pub use reaching_def::*;
+pub use sign::*;
+pub use uninitialized::*;
 
-pub(crate) trait InstructionExt {
+/// Transfer `(in − kill) ∪ gen` through every block, for the gen/kill
+/// problems solved over sets other than those of [`crate::gen_kill::GenKill`].
+pub(crate) fn gen_kill_transfer<S: BitSet>(
+    kill_sets: &SecondaryMap<BasicBlockIdx, S>,
+    gen_sets: &SecondaryMap<BasicBlockIdx, S>,
+) -> impl Fn(BasicBlockIdx, S) -> S + Sync {
+    move |block_idx, mut merged_in| {
+        merged_in.difference_with(&kill_sets[block_idx]);
+        merged_in.union_with(&gen_sets[block_idx]);
+        merged_in
+    }
+}
+
+pub trait InstructionExt {
     fn dest(&self) -> Option<Variable>;
+    /// Same as [`InstructionExt::dest`], for renaming it in place.
+    fn dest_mut(&mut self) -> Option<&mut Variable>;
     fn operands(&self) -> Vec<Variable>;
+    /// Same as [`InstructionExt::operands`], for rewriting them in place.
+    fn operands_mut(&mut self) -> Vec<&mut Variable>;
 }
 
 impl InstructionExt for Instruction {
@@ -42,6 +85,27 @@ impl InstructionExt for Instruction {
         }
     }
 
+    fn dest_mut(&mut self) -> Option<&mut Variable> {
+        match self {
+            Instruction::Add(dest, ..)
+            | Instruction::Sub(dest, ..)
+            | Instruction::Mul(dest, ..)
+            | Instruction::Div(dest, ..)
+            | Instruction::Eq(dest, ..)
+            | Instruction::Lt(dest, ..)
+            | Instruction::Gt(dest, ..)
+            | Instruction::Le(dest, ..)
+            | Instruction::Ge(dest, ..)
+            | Instruction::Not(dest, ..)
+            | Instruction::And(dest, ..)
+            | Instruction::Or(dest, ..)
+            | Instruction::Const(dest, ..)
+            | Instruction::Id(dest, ..) => Some(dest),
+            Instruction::Call(dest, ..) => dest.as_mut(),
+            _ => None,
+        }
+    }
+
     fn operands(&self) -> Vec<Variable> {
         match self {
             Instruction::Add(_, arg0, arg1)
@@ -66,4 +130,29 @@ impl InstructionExt for Instruction {
             _ => vec![],
         }
     }
+
+    fn operands_mut(&mut self) -> Vec<&mut Variable> {
+        match self {
+            Instruction::Add(_, arg0, arg1)
+            | Instruction::Sub(_, arg0, arg1)
+            | Instruction::Mul(_, arg0, arg1)
+            | Instruction::Div(_, arg0, arg1)
+            | Instruction::Eq(_, arg0, arg1)
+            | Instruction::Lt(_, arg0, arg1)
+            | Instruction::Gt(_, arg0, arg1)
+            | Instruction::Le(_, arg0, arg1)
+            | Instruction::Ge(_, arg0, arg1)
+            | Instruction::And(_, arg0, arg1)
+            | Instruction::Or(_, arg0, arg1) => vec![arg0, arg1],
+            Instruction::Not(.., arg0) | Instruction::Id(.., arg0) => {
+                vec![arg0]
+            }
+            Instruction::Call(.., args) | Instruction::Print(args) => {
+                args.iter_mut().collect()
+            }
+            Instruction::Br(arg0, ..) => vec![arg0],
+            Instruction::Ret(ret) => ret.iter_mut().collect(),
+            _ => vec![],
+        }
+    }
 }
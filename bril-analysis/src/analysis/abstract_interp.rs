@@ -0,0 +1,393 @@
+use super::prelude::*;
+use crate::{EdgeTransfers, Widening};
+use bril::ir::{Type, Value, Variable};
+use bril_cfg::Exit;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// upper bound on descending passes refining a widened solution
+const NARROWING_PASSES: usize = 3;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Comparison {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Comparison {
+    /// The comparison holding whenever this one does not.
+    pub fn negate(self) -> Self {
+        match self {
+            Self::Eq => Self::Ne,
+            Self::Ne => Self::Eq,
+            Self::Lt => Self::Ge,
+            Self::Le => Self::Gt,
+            Self::Gt => Self::Le,
+            Self::Ge => Self::Lt,
+        }
+    }
+
+    /// The comparison holding with both sides swapped.
+    pub fn flip(self) -> Self {
+        match self {
+            Self::Eq => Self::Eq,
+            Self::Ne => Self::Ne,
+            Self::Lt => Self::Gt,
+            Self::Le => Self::Ge,
+            Self::Gt => Self::Lt,
+            Self::Ge => Self::Le,
+        }
+    }
+
+    fn of(instruction: &Instruction) -> Option<(Self, Variable, Variable)> {
+        match instruction {
+            Instruction::Eq(_, lhs, rhs) => Some((Self::Eq, *lhs, *rhs)),
+            Instruction::Lt(_, lhs, rhs) => Some((Self::Lt, *lhs, *rhs)),
+            Instruction::Le(_, lhs, rhs) => Some((Self::Le, *lhs, *rhs)),
+            Instruction::Gt(_, lhs, rhs) => Some((Self::Gt, *lhs, *rhs)),
+            Instruction::Ge(_, lhs, rhs) => Some((Self::Ge, *lhs, *rhs)),
+            _ => None,
+        }
+    }
+}
+
+/// An abstraction of the set of values an `int` variable may hold.
+pub trait AbstractValue: Clone + PartialEq {
+    /// any value
+    fn top() -> Self;
+    fn constant(value: i64) -> Self;
+    fn may_equal(&self, value: i64) -> bool;
+    fn join(&self, other: &Self) -> Self;
+    /// Must over-approximate [`AbstractValue::join`] such that any ascending
+    /// chain built from it is finite. Lattices of finite height can keep the
+    /// default.
+    fn widen(&self, next: &Self) -> Self {
+        self.join(next)
+    }
+    /// Must return a value between `next` and `self` such that any
+    /// descending chain built from it is finite.
+    fn narrow(&self, next: &Self) -> Self {
+        next.clone()
+    }
+    fn add(&self, other: &Self) -> Self;
+    fn sub(&self, other: &Self) -> Self;
+    fn mul(&self, other: &Self) -> Self;
+    /// Only needs to account for non-trapping divisions.
+    fn div(&self, other: &Self) -> Self;
+    /// Restricts both sides to the values for which `lhs comparison rhs`
+    /// holds, or returns `None` if there are none. Defaults to learning
+    /// nothing.
+    fn assume(
+        comparison: Comparison,
+        lhs: &Self,
+        rhs: &Self,
+    ) -> Option<(Self, Self)> {
+        let _ = comparison;
+        Some((lhs.clone(), rhs.clone()))
+    }
+}
+
+/// Abstract state of the `int` variables of a function at a program point,
+/// keyed by variable numbering. Variables without an entry may hold any
+/// value.
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub enum AbstractEnv<V> {
+    /// no execution reaches the program point
+    #[default]
+    Unreachable,
+    Reachable(BTreeMap<u32, V>),
+}
+
+impl<V: AbstractValue> AbstractEnv<V> {
+    /// Abstract value of `variable`, or `None` if the program point is
+    /// unreachable.
+    pub fn get(&self, variable: Variable) -> Option<V> {
+        match self {
+            Self::Unreachable => None,
+            Self::Reachable(values) => {
+                Some(values.get(&variable.0).cloned().unwrap_or_else(V::top))
+            }
+        }
+    }
+
+    pub fn join(self, other: &Self) -> Self {
+        self.combine(other, V::join)
+    }
+
+    pub fn widen(&self, next: Self) -> Self {
+        self.clone().combine(&next, V::widen)
+    }
+
+    pub fn narrow(&self, next: Self) -> Self {
+        match (self, next) {
+            (Self::Reachable(values), Self::Reachable(next_values)) => {
+                Self::Reachable(
+                    next_values
+                        .into_iter()
+                        .map(|(variable, next)| {
+                            let narrowed = values
+                                .get(&variable)
+                                .cloned()
+                                .unwrap_or_else(V::top)
+                                .narrow(&next);
+                            (variable, narrowed)
+                        })
+                        .collect(),
+                )
+            }
+            (_, next) => next,
+        }
+    }
+
+    pub fn transfer(self, instruction: &Instruction) -> Self {
+        let Self::Reachable(mut values) = self else {
+            return self;
+        };
+        let get = |values: &BTreeMap<u32, V>, variable: &Variable| {
+            values.get(&variable.0).cloned().unwrap_or_else(V::top)
+        };
+        let value = match instruction {
+            Instruction::Const(_, Value::Int(constant)) => {
+                Some(V::constant(*constant))
+            }
+            Instruction::Add(_, arg0, arg1) => {
+                Some(get(&values, arg0).add(&get(&values, arg1)))
+            }
+            Instruction::Sub(_, arg0, arg1) => {
+                Some(get(&values, arg0).sub(&get(&values, arg1)))
+            }
+            Instruction::Mul(_, arg0, arg1) => {
+                Some(get(&values, arg0).mul(&get(&values, arg1)))
+            }
+            Instruction::Div(_, arg0, arg1) => {
+                Some(get(&values, arg0).div(&get(&values, arg1)))
+            }
+            Instruction::Id(dest, arg0) if dest.1 == Type::Int => {
+                Some(get(&values, arg0))
+            }
+            _ => None,
+        };
+        if let Some(dest) = instruction.dest() {
+            match value {
+                Some(value) if value != V::top() => {
+                    values.insert(dest.0, value);
+                }
+                _ => {
+                    values.remove(&dest.0);
+                }
+            }
+        }
+        Self::Reachable(values)
+    }
+
+    /// Restricts the state to executions where `lhs comparison rhs` holds.
+    pub fn assume(
+        self,
+        comparison: Comparison,
+        lhs: Variable,
+        rhs: Variable,
+    ) -> Self {
+        let Self::Reachable(mut values) = self else {
+            return self;
+        };
+        let get = |variable: Variable| {
+            values.get(&variable.0).cloned().unwrap_or_else(V::top)
+        };
+        let Some((lhs_value, rhs_value)) =
+            V::assume(comparison, &get(lhs), &get(rhs))
+        else {
+            return Self::Unreachable;
+        };
+        for (variable, value) in [(lhs, lhs_value), (rhs, rhs_value)] {
+            if value == V::top() {
+                values.remove(&variable.0);
+            } else {
+                values.insert(variable.0, value);
+            }
+        }
+        Self::Reachable(values)
+    }
+
+    /// Pointwise combination where a variable missing on either side stays
+    /// unconstrained.
+    fn combine(self, other: &Self, f: impl Fn(&V, &V) -> V) -> Self {
+        match (self, other) {
+            (Self::Unreachable, other) => other.clone(),
+            (this, Self::Unreachable) => this,
+            (Self::Reachable(values), Self::Reachable(other_values)) => {
+                Self::Reachable(
+                    values
+                        .into_iter()
+                        .filter_map(|(variable, value)| {
+                            let combined =
+                                f(&value, other_values.get(&variable)?);
+                            (combined != V::top())
+                                .then_some((variable, combined))
+                        })
+                        .collect(),
+                )
+            }
+        }
+    }
+}
+
+/// What is known to hold whenever control flows along a conditional edge.
+pub struct BranchCondition {
+    pub condition: Variable,
+    /// value of `condition` along the edge
+    pub outcome: bool,
+    /// comparison `condition` was computed by, already negated if the edge
+    /// is taken when it is false
+    pub comparison: Option<(Comparison, Variable, Variable)>,
+}
+
+/// Conditions of the branches of a function, keyed by `(from, to)` edges.
+/// Branches with both targets being the same block are left out, since
+/// nothing is learned from taking them.
+pub fn branch_conditions(
+    cfg: &Cfg,
+) -> HashMap<(BasicBlockIdx, BasicBlockIdx), BranchCondition> {
+    let mut conditions = HashMap::new();
+    for (block_idx, block) in cfg.vertices.iter() {
+        let Exit::Conditional { if_true, if_false } = cfg.edges[block_idx]
+        else {
+            continue;
+        };
+        let Some((Instruction::Br(condition, ..), rest)) =
+            block.instructions.split_last()
+        else {
+            continue;
+        };
+        if if_true == if_false {
+            continue;
+        }
+
+        // operands must still hold the compared values when branching
+        let mut redefined = HashSet::new();
+        let mut comparison = None;
+        for instruction in rest.iter().rev() {
+            let Some(dest) = instruction.dest() else {
+                continue;
+            };
+            if dest.0 == condition.0 {
+                comparison =
+                    Comparison::of(instruction).filter(|(_, lhs, rhs)| {
+                        !redefined.contains(&lhs.0)
+                            && !redefined.contains(&rhs.0)
+                    });
+                break;
+            }
+            redefined.insert(dest.0);
+        }
+
+        for (target, outcome) in [(if_true, true), (if_false, false)] {
+            conditions.insert(
+                (block_idx, target),
+                BranchCondition {
+                    condition: *condition,
+                    outcome,
+                    comparison: comparison.map(|(comparison, lhs, rhs)| {
+                        if outcome {
+                            (comparison, lhs, rhs)
+                        } else {
+                            (comparison.negate(), lhs, rhs)
+                        }
+                    }),
+                },
+            );
+        }
+    }
+    conditions
+}
+
+/// Forward abstract interpretation of the `int` variables of a function over
+/// the value domain `V`, widening at the heads of loops. Facts flowing along
+/// the edges of a branch on a comparison are refined by its outcome.
+pub fn abstract_interp<V: AbstractValue>(
+    cfg: &Cfg,
+) -> SecondaryMap<BasicBlockIdx, BlockFacts<AbstractEnv<V>>> {
+    let mut edge_transfers = EdgeTransfers::new();
+    for (edge, branch) in branch_conditions(cfg) {
+        if let Some((comparison, lhs, rhs)) = branch.comparison {
+            edge_transfers.insert(
+                edge,
+                Box::new(move |env: &AbstractEnv<V>| {
+                    env.clone().assume(comparison, lhs, rhs)
+                }),
+            );
+        }
+    }
+
+    // function parameters may hold any value
+    let entry_inputs =
+        HashMap::from([(cfg.entry, AbstractEnv::Reachable(BTreeMap::new()))]);
+    sequential::solve_dataflow(
+        cfg,
+        &(),
+        Direction::Forward,
+        entry_inputs,
+        |in1, in2| in1.join(in2),
+        |block_idx, merged_in| {
+            cfg.vertices[block_idx]
+                .instructions
+                .iter()
+                .fold(merged_in, AbstractEnv::transfer)
+        },
+        SolveOptions {
+            edge_transfers: Some(&edge_transfers),
+            widening: Some(Widening {
+                widen: &|previous, next| previous.widen(next),
+                narrow: &|previous, next| previous.narrow(next),
+                narrowing_passes: NARROWING_PASSES,
+            }),
+            ..Default::default()
+        },
+    )
+}
+
+/// Instruction-level view of the solution of [`abstract_interp`].
+pub type AbstractInstrFacts<'a, 'program, V> = InstrFacts<
+    'a,
+    'program,
+    fn(usize, &Instruction, AbstractEnv<V>) -> AbstractEnv<V>,
+    AbstractEnv<V>,
+>;
+
+/// Instruction-level abstract states on top of the solution of
+/// [`abstract_interp`].
+pub fn abstract_interp_instr<'a, 'program, V: AbstractValue>(
+    cfg: &'a Cfg<'program>,
+    blocks: &'a SecondaryMap<BasicBlockIdx, BlockFacts<AbstractEnv<V>>>,
+) -> AbstractInstrFacts<'a, 'program, V> {
+    InstrFacts::new(cfg, Direction::Forward, blocks, |_, instruction, env| {
+        env.transfer(instruction)
+    })
+}
+
+/// Offsets of the `div` instructions, relative to function's instruction
+/// buffer, whose divisor is proven to never be zero.
+pub fn nonzero_divisors<V: AbstractValue>(
+    cfg: &Cfg,
+    blocks: &SecondaryMap<BasicBlockIdx, BlockFacts<AbstractEnv<V>>>,
+) -> Vec<usize> {
+    let instr_facts = abstract_interp_instr(cfg, blocks);
+    let mut offsets = vec![];
+    for (block_idx, block) in cfg.vertices.iter() {
+        let Some(points) = instr_facts.points(block_idx) else {
+            continue;
+        };
+        for (i, (instruction, env)) in
+            block.instructions.iter().zip(points).enumerate()
+        {
+            if let Instruction::Div(_, _, divisor) = instruction
+                && env.get(*divisor).is_some_and(|value| !value.may_equal(0))
+            {
+                offsets.push(block.offset + i);
+            }
+        }
+    }
+    offsets
+}
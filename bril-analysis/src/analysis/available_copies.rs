@@ -0,0 +1,127 @@
+use super::{prelude::*, reaching_def::total_instr_num};
+use bril::ir::Variable;
+use std::collections::HashMap;
+
+/// ones in the returned bitsets should be interpreted as offset of an `id`
+/// instruction relative to function's instruction buffer. A copy is
+/// available at a program point if it executed on every path leading there,
+/// and neither its destination nor its source was redefined since.
+pub fn available_copies(cfg: &Cfg) -> SecondaryMap<BasicBlockIdx, BlockFacts> {
+    let total_instr_num = total_instr_num(cfg);
+    let copies = find_copies(cfg);
+    let mentions = find_mentions(cfg, &copies);
+    let mut all_copies = FixedBitSet::with_capacity(total_instr_num);
+    all_copies.extend(copies.keys().copied());
+
+    // solved as the may-analysis of unavailable copies, so that blocks not
+    // visited yet start out with every copy available
+    let unavailable = sequential::solve_dataflow(
+        cfg,
+        &(),
+        Direction::Forward,
+        HashMap::from([(cfg.entry, all_copies.clone())]),
+        |mut in1, in2| {
+            in1.union_with(in2);
+            in1
+        },
+        |block_idx, mut merged_in| {
+            let block = &cfg.vertices[block_idx];
+            merged_in.grow(total_instr_num);
+            for (i, instruction) in block.instructions.iter().enumerate() {
+                if let Some(dest) = instruction.dest() {
+                    if let Some(mentions) = mentions.get(&dest.0) {
+                        merged_in.union_with(mentions);
+                    }
+                    if copies.contains_key(&(block.offset + i)) {
+                        merged_in.set(block.offset + i, false);
+                    }
+                }
+            }
+            merged_in
+        },
+        SolveOptions::default(),
+    );
+
+    unavailable
+        .into_iter()
+        .map(|(block_idx, facts)| {
+            let available = |unavailable: FixedBitSet| {
+                let mut available = all_copies.clone();
+                available.difference_with(&unavailable);
+                available
+            };
+            let facts = BlockFacts {
+                before: available(facts.before),
+                after: available(facts.after),
+            };
+            (block_idx, facts)
+        })
+        .collect()
+}
+
+/// Instruction-level available copies on top of the solution of
+/// [`available_copies`].
+pub fn available_copies_instr<'a, 'program>(
+    cfg: &'a Cfg<'program>,
+    blocks: &'a SecondaryMap<BasicBlockIdx, BlockFacts>,
+) -> InstrFacts<
+    'a,
+    'program,
+    impl Fn(usize, &Instruction, FixedBitSet) -> FixedBitSet,
+> {
+    let total_instr_num = total_instr_num(cfg);
+    let copies = find_copies(cfg);
+    let mentions = find_mentions(cfg, &copies);
+    InstrFacts::new(
+        cfg,
+        Direction::Forward,
+        blocks,
+        move |offset, instruction, mut available| {
+            if let Some(dest) = instruction.dest() {
+                available.grow(total_instr_num);
+                if let Some(mentions) = mentions.get(&dest.0) {
+                    available.difference_with(mentions);
+                }
+                if copies.contains_key(&offset) {
+                    available.insert(offset);
+                }
+            }
+            available
+        },
+    )
+}
+
+/// maps the offset of every copy to its destination and source, leaving out
+/// copies of a variable to itself
+pub fn find_copies(cfg: &Cfg) -> HashMap<usize, (Variable, Variable)> {
+    let mut copies = HashMap::new();
+    for block in cfg.vertices.values() {
+        for (i, instruction) in block.instructions.iter().enumerate() {
+            if let Instruction::Id(dest, src) = instruction
+                && dest.0 != src.0
+            {
+                copies.insert(block.offset + i, (*dest, *src));
+            }
+        }
+    }
+    copies
+}
+
+/// maps every variable to the offsets of the copies it is the destination or
+/// the source of
+fn find_mentions(
+    cfg: &Cfg,
+    copies: &HashMap<usize, (Variable, Variable)>,
+) -> HashMap<u32, FixedBitSet> {
+    let total_instr_num = total_instr_num(cfg);
+    let mut mentions: HashMap<u32, FixedBitSet> = HashMap::new();
+    for (&offset, (dest, src)) in copies {
+        for variable in [dest, src] {
+            mentions
+                .entry(variable.0)
+                .or_insert(FixedBitSet::with_capacity(total_instr_num))
+                .insert(offset);
+        }
+    }
+    mentions
+}
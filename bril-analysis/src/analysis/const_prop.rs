@@ -0,0 +1,225 @@
+use super::{abstract_interp::*, prelude::*};
+use crate::EdgeTransfers;
+use bril::ir::{FunctionIdx, Value, Variable};
+use std::collections::{BTreeMap, HashMap};
+
+/// Variables of a function known to hold a constant at a program point,
+/// keyed by variable numbering.
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub enum Constants {
+    /// no execution reaches the program point
+    #[default]
+    Unreachable,
+    Reachable(BTreeMap<u32, Value>),
+}
+
+impl Constants {
+    /// Constant held by `variable`, or `None` if it is not known to be
+    /// constant or the program point is unreachable.
+    pub fn get(&self, variable: Variable) -> Option<&Value> {
+        match self {
+            Self::Unreachable => None,
+            Self::Reachable(values) => values.get(&variable.0),
+        }
+    }
+
+    pub fn is_reachable(&self) -> bool {
+        matches!(self, Self::Reachable(_))
+    }
+
+    /// Keeps the constants both sides agree on.
+    pub fn join(self, other: &Self) -> Self {
+        match (self, other) {
+            (Self::Unreachable, other) => other.clone(),
+            (this, Self::Unreachable) => this,
+            (Self::Reachable(mut values), Self::Reachable(other_values)) => {
+                values.retain(|variable, value| {
+                    other_values.get(variable) == Some(value)
+                });
+                Self::Reachable(values)
+            }
+        }
+    }
+
+    /// Value `instruction` computes given the constants before it, or `None`
+    /// if it is not a constant. Divisions by zero are never folded.
+    pub fn evaluate(&self, instruction: &Instruction) -> Option<Value> {
+        let int = |variable: &Variable| match self.get(*variable) {
+            Some(Value::Int(value)) => Some(*value),
+            _ => None,
+        };
+        let bool = |variable: &Variable| match self.get(*variable) {
+            Some(Value::Bool(value)) => Some(*value),
+            _ => None,
+        };
+        let ints = |arg0, arg1| Some((int(arg0)?, int(arg1)?));
+        match instruction {
+            Instruction::Const(_, value) => Some(value.clone()),
+            Instruction::Id(_, arg0) => self.get(*arg0).cloned(),
+            Instruction::Add(_, arg0, arg1) => ints(arg0, arg1)
+                .map(|(lhs, rhs)| Value::Int(lhs.wrapping_add(rhs))),
+            Instruction::Sub(_, arg0, arg1) => ints(arg0, arg1)
+                .map(|(lhs, rhs)| Value::Int(lhs.wrapping_sub(rhs))),
+            Instruction::Mul(_, arg0, arg1) => {
+                if int(arg0) == Some(0) || int(arg1) == Some(0) {
+                    return Some(Value::Int(0));
+                }
+                ints(arg0, arg1)
+                    .map(|(lhs, rhs)| Value::Int(lhs.wrapping_mul(rhs)))
+            }
+            Instruction::Div(_, arg0, arg1) => {
+                let (lhs, rhs) = ints(arg0, arg1)?;
+                (rhs != 0).then(|| Value::Int(lhs.wrapping_div(rhs)))
+            }
+            Instruction::Eq(_, arg0, arg1) => {
+                ints(arg0, arg1).map(|(lhs, rhs)| Value::Bool(lhs == rhs))
+            }
+            Instruction::Lt(_, arg0, arg1) => {
+                ints(arg0, arg1).map(|(lhs, rhs)| Value::Bool(lhs < rhs))
+            }
+            Instruction::Gt(_, arg0, arg1) => {
+                ints(arg0, arg1).map(|(lhs, rhs)| Value::Bool(lhs > rhs))
+            }
+            Instruction::Le(_, arg0, arg1) => {
+                ints(arg0, arg1).map(|(lhs, rhs)| Value::Bool(lhs <= rhs))
+            }
+            Instruction::Ge(_, arg0, arg1) => {
+                ints(arg0, arg1).map(|(lhs, rhs)| Value::Bool(lhs >= rhs))
+            }
+            Instruction::Not(_, arg0) => {
+                bool(arg0).map(|value| Value::Bool(!value))
+            }
+            Instruction::And(_, arg0, arg1) => match (bool(arg0), bool(arg1)) {
+                (Some(false), _) | (_, Some(false)) => Some(Value::Bool(false)),
+                (Some(true), Some(true)) => Some(Value::Bool(true)),
+                _ => None,
+            },
+            Instruction::Or(_, arg0, arg1) => match (bool(arg0), bool(arg1)) {
+                (Some(true), _) | (_, Some(true)) => Some(Value::Bool(true)),
+                (Some(false), Some(false)) => Some(Value::Bool(false)),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    pub fn transfer(self, instruction: &Instruction) -> Self {
+        self.transfer_with(instruction, |_| None)
+    }
+
+    /// Same as [`Constants::transfer`], where calls return the constant
+    /// `returns` knows the callee to return.
+    pub fn transfer_with(
+        self,
+        instruction: &Instruction,
+        returns: impl Fn(FunctionIdx) -> Option<Value>,
+    ) -> Self {
+        let value = match instruction {
+            Instruction::Call(_, callee, _) => returns(*callee),
+            _ => self.evaluate(instruction),
+        };
+        let Self::Reachable(mut values) = self else {
+            return self;
+        };
+        if let Some(dest) = instruction.dest() {
+            match value {
+                Some(value) => {
+                    values.insert(dest.0, value);
+                }
+                None => {
+                    values.remove(&dest.0);
+                }
+            }
+        }
+        Self::Reachable(values)
+    }
+
+    /// Restricts the constants to executions taking a branch on `branch`.
+    pub fn assume(self, branch: &BranchCondition) -> Self {
+        let Self::Reachable(mut values) = self else {
+            return self;
+        };
+        let outcome = Value::Bool(branch.outcome);
+        match values.insert(branch.condition.0, outcome.clone()) {
+            // the edge is never taken
+            Some(previous) if previous != outcome => return Self::Unreachable,
+            _ => {}
+        }
+        if let Some((Comparison::Eq, lhs, rhs)) = branch.comparison {
+            if let Some(value) = values.get(&lhs.0).cloned() {
+                values.insert(rhs.0, value);
+            } else if let Some(value) = values.get(&rhs.0).cloned() {
+                values.insert(lhs.0, value);
+            }
+        }
+        Self::Reachable(values)
+    }
+}
+
+/// Conditional constant propagation: facts flowing along the edges of a
+/// branch know the outcome of its condition, and edges a known condition
+/// never takes propagate nothing.
+pub fn const_prop(
+    cfg: &Cfg,
+) -> SecondaryMap<BasicBlockIdx, BlockFacts<Constants>> {
+    // function parameters are not constant
+    const_prop_with(cfg, Constants::Reachable(BTreeMap::new()), |_| None)
+}
+
+/// Same as [`const_prop`], starting from the constants `entry` knows the
+/// parameters to hold, and with calls returning the constant `returns` knows
+/// the callee to return.
+pub fn const_prop_with(
+    cfg: &Cfg,
+    entry: Constants,
+    returns: impl Fn(FunctionIdx) -> Option<Value>,
+) -> SecondaryMap<BasicBlockIdx, BlockFacts<Constants>> {
+    let mut edge_transfers = EdgeTransfers::new();
+    for (edge, branch) in branch_conditions(cfg) {
+        edge_transfers.insert(
+            edge,
+            Box::new(move |constants: &Constants| {
+                constants.clone().assume(&branch)
+            }),
+        );
+    }
+
+    let entry_inputs = HashMap::from([(cfg.entry, entry)]);
+    sequential::solve_dataflow(
+        cfg,
+        &(),
+        Direction::Forward,
+        entry_inputs,
+        |in1, in2| in1.join(in2),
+        |block_idx, merged_in| {
+            cfg.vertices[block_idx].instructions.iter().fold(
+                merged_in,
+                |constants, instruction| {
+                    constants.transfer_with(instruction, &returns)
+                },
+            )
+        },
+        SolveOptions {
+            edge_transfers: Some(&edge_transfers),
+            ..Default::default()
+        },
+    )
+}
+
+/// Instruction-level view of the solution of [`const_prop`].
+pub type ConstInstrFacts<'a, 'program> = InstrFacts<
+    'a,
+    'program,
+    fn(usize, &Instruction, Constants) -> Constants,
+    Constants,
+>;
+
+/// Instruction-level constants on top of the solution of [`const_prop`].
+pub fn const_prop_instr<'a, 'program>(
+    cfg: &'a Cfg<'program>,
+    blocks: &'a SecondaryMap<BasicBlockIdx, BlockFacts<Constants>>,
+) -> ConstInstrFacts<'a, 'program> {
+    InstrFacts::new(cfg, Direction::Forward, blocks, |_, instruction, env| {
+        env.transfer(instruction)
+    })
+}
@@ -0,0 +1,82 @@
+use super::{
+    prelude::*,
+    reaching_def::{
+        find_definitions, reaching_def, reaching_def_instr, total_instr_num,
+    },
+};
+
+/// Def-use and use-def chains of a function, derived from [`reaching_def`].
+///
+/// Instructions are identified by their offset relative to function's
+/// instruction buffer, same as in the reaching definitions bitsets. Operands
+/// of an instruction are numbered in the order they appear in it. Function
+/// parameters are not tracked, so an operand which is only reached by a
+/// parameter has no defining instructions.
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct DefUseChains {
+    /// for every instruction, the reaching definitions of each operand
+    use_def: Vec<Vec<Vec<usize>>>,
+    /// for every defining instruction, the instructions using it
+    def_use: Vec<Vec<usize>>,
+}
+
+impl DefUseChains {
+    pub fn new(cfg: &Cfg) -> Self {
+        let total_instr_num = total_instr_num(cfg);
+        let definitions = find_definitions::<FixedBitSet>(cfg);
+        let blocks = reaching_def(cfg);
+        let instr_facts = reaching_def_instr(cfg, &blocks);
+
+        let mut chains = Self {
+            use_def: vec![vec![]; total_instr_num],
+            def_use: vec![vec![]; total_instr_num],
+        };
+        for (block_idx, block) in cfg.vertices.iter() {
+            // unreachable blocks are not part of the dataflow solution
+            let Some(points) = instr_facts.points(block_idx) else {
+                continue;
+            };
+            for (i, (instruction, reaching)) in
+                block.instructions.iter().zip(points).enumerate()
+            {
+                let offset = block.offset + i;
+                let operand_defs: Vec<Vec<usize>> = instruction
+                    .operands()
+                    .into_iter()
+                    .map(|operand| {
+                        definitions
+                            .get(&operand.0)
+                            .map(|defs| defs.intersection(&reaching).collect())
+                            .unwrap_or_default()
+                    })
+                    .collect();
+                for &def in operand_defs.iter().flatten() {
+                    if chains.def_use[def].last() != Some(&offset) {
+                        chains.def_use[def].push(offset);
+                    }
+                }
+                chains.use_def[offset] = operand_defs;
+            }
+        }
+        chains
+    }
+
+    /// Reaching definitions of the `operand`-th operand of the instruction at
+    /// `offset`.
+    pub fn reaching_defs(&self, offset: usize, operand: usize) -> &[usize] {
+        self.use_def
+            .get(offset)
+            .and_then(|operands| operands.get(operand))
+            .map_or(&[], Vec::as_slice)
+    }
+
+    /// Reaching definitions of every operand of the instruction at `offset`.
+    pub fn operand_defs(&self, offset: usize) -> &[Vec<usize>] {
+        self.use_def.get(offset).map_or(&[], Vec::as_slice)
+    }
+
+    /// Instructions using the value defined by the instruction at `def`.
+    pub fn uses(&self, def: usize) -> &[usize] {
+        self.def_use.get(def).map_or(&[], Vec::as_slice)
+    }
+}
@@ -0,0 +1,202 @@
+use super::{abstract_interp::*, prelude::*};
+use std::fmt;
+
+/// A closed range of `int` values. The bounds of `int` double as the
+/// infinities, which is exact since values wrap around on overflow.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Interval {
+    pub lo: i64,
+    pub hi: i64,
+}
+
+impl Interval {
+    pub const TOP: Interval = Interval {
+        lo: i64::MIN,
+        hi: i64::MAX,
+    };
+
+    pub fn contains(&self, value: i64) -> bool {
+        self.lo <= value && value <= self.hi
+    }
+
+    /// The smallest interval containing all of `bounds`, or top if any of
+    /// them overflowed.
+    fn from_bounds(bounds: impl IntoIterator<Item = i128>) -> Self {
+        let (lo, hi) = bounds
+            .into_iter()
+            .fold((i128::MAX, i128::MIN), |(lo, hi), bound| {
+                (lo.min(bound), hi.max(bound))
+            });
+        match (i64::try_from(lo), i64::try_from(hi)) {
+            (Ok(lo), Ok(hi)) => Self { lo, hi },
+            _ => Self::TOP,
+        }
+    }
+
+    fn intersect(&self, other: &Self) -> Option<Self> {
+        let lo = self.lo.max(other.lo);
+        let hi = self.hi.min(other.hi);
+        (lo <= hi).then_some(Self { lo, hi })
+    }
+
+    fn corners(&self, other: &Self, f: impl Fn(i128, i128) -> i128) -> Self {
+        let (lo0, hi0) = (self.lo as i128, self.hi as i128);
+        let (lo1, hi1) = (other.lo as i128, other.hi as i128);
+        Self::from_bounds([f(lo0, lo1), f(lo0, hi1), f(hi0, lo1), f(hi0, hi1)])
+    }
+}
+
+impl fmt::Display for Interval {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.lo {
+            i64::MIN => formatter.write_str("[-inf, ")?,
+            lo => write!(formatter, "[{lo}, ")?,
+        }
+        match self.hi {
+            i64::MAX => formatter.write_str("+inf]"),
+            hi => write!(formatter, "{hi}]"),
+        }
+    }
+}
+
+impl AbstractValue for Interval {
+    fn top() -> Self {
+        Self::TOP
+    }
+
+    fn constant(value: i64) -> Self {
+        Self {
+            lo: value,
+            hi: value,
+        }
+    }
+
+    fn may_equal(&self, value: i64) -> bool {
+        self.contains(value)
+    }
+
+    fn join(&self, other: &Self) -> Self {
+        Self {
+            lo: self.lo.min(other.lo),
+            hi: self.hi.max(other.hi),
+        }
+    }
+
+    fn widen(&self, next: &Self) -> Self {
+        Self {
+            lo: if next.lo < self.lo { i64::MIN } else { self.lo },
+            hi: if next.hi > self.hi { i64::MAX } else { self.hi },
+        }
+    }
+
+    fn narrow(&self, next: &Self) -> Self {
+        Self {
+            lo: if self.lo == i64::MIN {
+                next.lo
+            } else {
+                self.lo
+            },
+            hi: if self.hi == i64::MAX {
+                next.hi
+            } else {
+                self.hi
+            },
+        }
+    }
+
+    fn add(&self, other: &Self) -> Self {
+        Self::from_bounds([
+            self.lo as i128 + other.lo as i128,
+            self.hi as i128 + other.hi as i128,
+        ])
+    }
+
+    fn sub(&self, other: &Self) -> Self {
+        Self::from_bounds([
+            self.lo as i128 - other.hi as i128,
+            self.hi as i128 - other.lo as i128,
+        ])
+    }
+
+    fn mul(&self, other: &Self) -> Self {
+        self.corners(other, |lhs, rhs| lhs * rhs)
+    }
+
+    fn div(&self, other: &Self) -> Self {
+        // the quotient is monotone in both operands as long as the divisor
+        // keeps its sign, so split the divisor around zero
+        let negative = (other.lo < 0).then(|| Self {
+            lo: other.lo,
+            hi: other.hi.min(-1),
+        });
+        let positive = (other.hi > 0).then(|| Self {
+            lo: other.lo.max(1),
+            hi: other.hi,
+        });
+        [negative, positive]
+            .into_iter()
+            .flatten()
+            .map(|divisor| self.corners(&divisor, |lhs, rhs| lhs / rhs))
+            .reduce(|acc, quotient| acc.join(&quotient))
+            // always traps
+            .unwrap_or(Self::TOP)
+    }
+
+    fn assume(
+        comparison: Comparison,
+        lhs: &Self,
+        rhs: &Self,
+    ) -> Option<(Self, Self)> {
+        // bounds one past the other side, empty if there is no such value
+        let below = |value: i64| {
+            value.checked_sub(1).map(|hi| Self { lo: i64::MIN, hi })
+        };
+        let above = |value: i64| {
+            value.checked_add(1).map(|lo| Self { lo, hi: i64::MAX })
+        };
+        let at_most = |hi: i64| Self { lo: i64::MIN, hi };
+        let at_least = |lo: i64| Self { lo, hi: i64::MAX };
+        match comparison {
+            Comparison::Eq => {
+                let both = lhs.intersect(rhs)?;
+                Some((both, both))
+            }
+            Comparison::Ne => {
+                // only a singleton can cut off the bound of the other side
+                let exclude = |this: &Self, other: &Self| {
+                    if other.lo != other.hi {
+                        Some(*this)
+                    } else if this.lo == other.lo {
+                        above(this.lo)?.intersect(this)
+                    } else if this.hi == other.lo {
+                        below(this.hi)?.intersect(this)
+                    } else {
+                        Some(*this)
+                    }
+                };
+                Some((exclude(lhs, rhs)?, exclude(rhs, lhs)?))
+            }
+            Comparison::Lt => Some((
+                lhs.intersect(&below(rhs.hi)?)?,
+                rhs.intersect(&above(lhs.lo)?)?,
+            )),
+            Comparison::Le => Some((
+                lhs.intersect(&at_most(rhs.hi))?,
+                rhs.intersect(&at_least(lhs.lo))?,
+            )),
+            Comparison::Gt | Comparison::Ge => {
+                let (rhs, lhs) = Self::assume(comparison.flip(), rhs, lhs)?;
+                Some((lhs, rhs))
+            }
+        }
+    }
+}
+
+pub type IntervalEnv = AbstractEnv<Interval>;
+
+/// Ranges of the `int` variables at the boundaries of every block.
+pub fn intervals(
+    cfg: &Cfg,
+) -> SecondaryMap<BasicBlockIdx, BlockFacts<IntervalEnv>> {
+    abstract_interp(cfg)
+}
@@ -0,0 +1,281 @@
+use super::prelude::*;
+use bril::ir::Variable;
+use bril_cfg::Exit;
+use std::{
+    collections::HashMap,
+    mem::{self, Discriminant},
+};
+
+/// Placement of the expressions of a function computed by lazy code motion.
+/// The CFG is expected to have every edge into a block with several
+/// predecessors split, and an entry without predecessors.
+pub struct LazyCodeMotion {
+    /// a computation of every expression, indexed by the bits of the sets
+    pub expressions: Vec<Instruction>,
+    keys: HashMap<(Discriminant<Instruction>, Vec<Variable>), usize>,
+    /// expressions to compute at the start of every block
+    pub insert: SecondaryMap<BasicBlockIdx, FixedBitSet>,
+    /// expressions whose first computation in every block is replaced with
+    /// the result of an inserted one
+    pub replace: SecondaryMap<BasicBlockIdx, FixedBitSet>,
+}
+
+impl LazyCodeMotion {
+    /// The expression `instruction` computes, if it may be moved at all.
+    pub fn expression(&self, instruction: &Instruction) -> Option<usize> {
+        self.keys.get(&key(instruction)?).copied()
+    }
+}
+
+pub fn lazy_code_motion(cfg: &Cfg) -> LazyCodeMotion {
+    solve(cfg, None)
+}
+
+/// Same as [`lazy_code_motion`], solving each of the four problems with the
+/// parallel solver.
+pub fn lazy_code_motion_para(cfg: &Cfg, num_threads: usize) -> LazyCodeMotion {
+    solve(cfg, Some(num_threads))
+}
+
+/// Pure operations that cannot trap, keyed by operator and operands, sorted
+/// for commutative ones.
+fn key(
+    instruction: &Instruction,
+) -> Option<(Discriminant<Instruction>, Vec<Variable>)> {
+    let mut operands = instruction.operands();
+    match instruction {
+        Instruction::Add(..)
+        | Instruction::Mul(..)
+        | Instruction::Eq(..)
+        | Instruction::And(..)
+        | Instruction::Or(..) => operands.sort_unstable(),
+        Instruction::Sub(..)
+        | Instruction::Lt(..)
+        | Instruction::Gt(..)
+        | Instruction::Le(..)
+        | Instruction::Ge(..)
+        | Instruction::Not(..) => {}
+        _ => return None,
+    }
+    Some((mem::discriminant(instruction), operands))
+}
+
+fn solve(cfg: &Cfg, num_threads: Option<usize>) -> LazyCodeMotion {
+    let mut expressions = vec![];
+    let mut keys = HashMap::new();
+    for block in cfg.vertices.values() {
+        for instruction in block.instructions {
+            if let Some(key) = key(instruction) {
+                keys.entry(key).or_insert_with(|| {
+                    expressions.push(instruction.clone());
+                    expressions.len() - 1
+                });
+            }
+        }
+    }
+    let total_expr_num = expressions.len();
+    let empty = FixedBitSet::with_capacity(total_expr_num);
+    let complement = |mut set: FixedBitSet| {
+        set.grow(total_expr_num);
+        set.toggle_range(..);
+        set
+    };
+
+    // upward-exposed computations and expressions with a redefined operand
+    let mut uses = SecondaryMap::new();
+    let mut kills = SecondaryMap::new();
+    for (block_idx, block) in &cfg.vertices {
+        let (mut used, mut killed) = (empty.clone(), empty.clone());
+        for instruction in block.instructions {
+            if let Some(key) = key(instruction)
+                && !killed.contains(keys[&key])
+            {
+                used.insert(keys[&key]);
+            }
+            if let Some(dest) = instruction.dest() {
+                for (key, &expression) in &keys {
+                    if key.1.iter().any(|operand| operand.0 == dest.0) {
+                        killed.insert(expression);
+                    }
+                }
+            }
+        }
+        uses.insert(block_idx, used);
+        kills.insert(block_idx, killed);
+    }
+    let is_exit = |block_idx| matches!(cfg.edges[block_idx], Exit::Return);
+    let successors = |block_idx| {
+        let mut successors = cfg.successors(block_idx);
+        successors.dedup();
+        successors
+    };
+    let solve_outputs =
+        |direction, transfer| solve_with(cfg, direction, transfer, num_threads);
+
+    // must-problems are solved as the may-problems of their complements, so
+    // that the solver can start from empty sets. Boundaries are set in the
+    // transfer functions since the parallel solver only knows about one.
+    let not_anticipated = solve_outputs(
+        Direction::Backward,
+        Box::new(|block_idx, mut out| {
+            out.grow(total_expr_num);
+            if is_exit(block_idx) {
+                out.insert_range(..);
+            }
+            out.union_with(&kills[block_idx]);
+            out.difference_with(&uses[block_idx]);
+            out
+        }),
+    );
+    let anticipated_in: SecondaryMap<_, _> = not_anticipated
+        .iter()
+        .map(|(block_idx, set)| (block_idx, complement(set.clone())))
+        .collect();
+
+    let not_available = solve_outputs(
+        Direction::Forward,
+        Box::new(|block_idx, mut merged_in| {
+            merged_in.grow(total_expr_num);
+            if block_idx == cfg.entry {
+                merged_in.insert_range(..);
+            }
+            merged_in.difference_with(&anticipated_in[block_idx]);
+            merged_in.union_with(&kills[block_idx]);
+            merged_in
+        }),
+    );
+    let forward_input = |block_idx, outputs: &SecondaryMap<_, FixedBitSet>| {
+        let mut input = empty.clone();
+        if block_idx == cfg.entry {
+            return input;
+        }
+        input.toggle_range(..);
+        // blocks unreachable from the entry are never solved
+        for predecessor in cfg.predecessors(block_idx) {
+            if let Some(output) = outputs.get(predecessor) {
+                input.difference_with(output);
+            }
+        }
+        input
+    };
+    let earliest: SecondaryMap<_, _> = anticipated_in
+        .iter()
+        .map(|(block_idx, anticipated)| {
+            let mut earliest = anticipated.clone();
+            earliest.difference_with(&forward_input(block_idx, &not_available));
+            (block_idx, earliest)
+        })
+        .collect();
+
+    let not_postponable = solve_outputs(
+        Direction::Forward,
+        Box::new(|block_idx, mut merged_in| {
+            merged_in.grow(total_expr_num);
+            if block_idx == cfg.entry {
+                merged_in.insert_range(..);
+            }
+            merged_in.difference_with(&earliest[block_idx]);
+            merged_in.union_with(&uses[block_idx]);
+            merged_in
+        }),
+    );
+    let placeable: SecondaryMap<_, _> = earliest
+        .iter()
+        .map(|(block_idx, earliest)| {
+            let mut placeable = forward_input(block_idx, &not_postponable);
+            placeable.union_with(earliest);
+            (block_idx, placeable)
+        })
+        .collect();
+    let latest: SecondaryMap<_, _> = placeable
+        .iter()
+        .map(|(block_idx, here)| {
+            let mut everywhere_after = complement(empty.clone());
+            for successor in successors(block_idx) {
+                everywhere_after.intersect_with(&placeable[successor]);
+            }
+            let mut latest = complement(everywhere_after);
+            latest.union_with(&uses[block_idx]);
+            latest.intersect_with(here);
+            (block_idx, latest)
+        })
+        .collect();
+
+    let used_in = solve_outputs(
+        Direction::Backward,
+        Box::new(|block_idx, mut out| {
+            out.grow(total_expr_num);
+            out.union_with(&uses[block_idx]);
+            out.difference_with(&latest[block_idx]);
+            out
+        }),
+    );
+    let mut insert = SecondaryMap::new();
+    let mut replace = SecondaryMap::new();
+    for (block_idx, latest) in &latest {
+        let mut used_out = empty.clone();
+        for successor in successors(block_idx) {
+            used_out.union_with(&used_in[successor]);
+        }
+        let mut inserted = latest.clone();
+        inserted.intersect_with(&used_out);
+        let mut replaced = complement(latest.clone());
+        replaced.union_with(&used_out);
+        replaced.intersect_with(&uses[block_idx]);
+        insert.insert(block_idx, inserted);
+        replace.insert(block_idx, replaced);
+    }
+
+    LazyCodeMotion {
+        expressions,
+        keys,
+        insert,
+        replace,
+    }
+}
+
+type Transfer<'t> =
+    Box<dyn Fn(BasicBlockIdx, FixedBitSet) -> FixedBitSet + Sync + 't>;
+
+/// Solves a union problem, returning the output of every reachable block.
+fn solve_with(
+    cfg: &Cfg,
+    direction: Direction,
+    transfer: Transfer,
+    num_threads: Option<usize>,
+) -> SecondaryMap<BasicBlockIdx, FixedBitSet> {
+    let merge = |mut in1: FixedBitSet, in2: &FixedBitSet| {
+        in1.union_with(in2);
+        in1
+    };
+    let output = |facts: BlockFacts| match direction {
+        Direction::Forward => facts.after,
+        Direction::Backward => facts.before,
+    };
+    match num_threads {
+        None => sequential::solve_dataflow(
+            cfg,
+            &(),
+            direction,
+            HashMap::new(),
+            merge,
+            transfer,
+            SolveOptions::default(),
+        )
+        .into_iter()
+        .map(|(block_idx, facts)| (block_idx, output(facts)))
+        .collect(),
+        Some(num_threads) => parallel::solve_dataflow(
+            cfg,
+            direction,
+            FixedBitSet::new(),
+            merge,
+            transfer,
+            num_threads,
+            SolveOptions::default(),
+        )
+        .into_iter()
+        .map(|(block_idx, facts)| (block_idx, output(facts)))
+        .collect(),
+    }
+}
@@ -1,10 +1,55 @@
 use super::prelude::*;
+use crate::Previous;
+use bril::ir::Variable;
 use std::collections::{HashMap, HashSet};
 
-/// ones of the returned bitset should be interpreted as the numbering of live
+/// ones of the returned bitsets should be interpreted as the numbering of live
 /// variable variables are zero-indexed per function
-pub fn liveness(cfg: &Cfg) -> SecondaryMap<BasicBlockIdx, FixedBitSet> {
-    let (kill_set, gen_set) = (find_kill_set(cfg), find_gen_set(cfg));
+pub fn liveness(cfg: &Cfg) -> SecondaryMap<BasicBlockIdx, BlockFacts> {
+    liveness_with_options(cfg, SolveOptions::default())
+}
+
+/// Same as [`liveness`], taking the variables every instruction reads from
+/// `operands`, e.g. [`crate::summary::Summaries::operands`] to leave out the
+/// arguments callees never read.
+pub fn liveness_with(
+    cfg: &Cfg,
+    operands: impl Fn(&Instruction) -> Vec<Variable>,
+) -> SecondaryMap<BasicBlockIdx, BlockFacts> {
+    let problem = GenKill::new(
+        Direction::Backward,
+        &find_gen_set(cfg, operands),
+        &find_kill_set(cfg),
+    );
+    sequential::solve_gen_kill(
+        cfg,
+        &(),
+        &problem,
+        &HashMap::new(),
+        SolveOptions::default(),
+    )
+}
+
+/// Same as [`liveness`], solved as configured by `options`.
+pub fn liveness_with_options(
+    cfg: &Cfg,
+    options: SolveOptions,
+) -> SecondaryMap<BasicBlockIdx, BlockFacts> {
+    sequential::solve_gen_kill(
+        cfg,
+        &(),
+        &liveness_gen_kill(cfg),
+        &HashMap::new(),
+        options,
+    )
+}
+
+/// Same as [`liveness`], with the sets of live variables represented as `S`.
+pub fn liveness_in<S: BitSet>(
+    cfg: &Cfg,
+) -> SecondaryMap<BasicBlockIdx, BlockFacts<S>> {
+    let (kill_set, gen_set) =
+        (find_kill_set(cfg), find_gen_set(cfg, Instruction::operands));
     sequential::solve_dataflow(
         cfg,
         &(),
@@ -14,43 +59,188 @@ pub fn liveness(cfg: &Cfg) -> SecondaryMap<BasicBlockIdx, FixedBitSet> {
             in1.union_with(in2);
             in1
         },
-        |block_idx, mut merged_in| {
-            merged_in.difference_with(&kill_set[block_idx]);
-            merged_in.union_with(&gen_set[block_idx]);
-            merged_in
-        },
+        gen_kill_transfer(&kill_set, &gen_set),
+        SolveOptions::default(),
     )
 }
 
 pub fn liveness_para(
     cfg: &Cfg,
     num_threads: usize,
-) -> DashMap<BasicBlockIdx, FixedBitSet> {
+) -> SecondaryMap<BasicBlockIdx, BlockFacts> {
+    liveness_para_with_options(cfg, num_threads, SolveOptions::default())
+}
+
+/// Same as [`liveness_para`], solved as configured by `options`.
+pub fn liveness_para_with_options(
+    cfg: &Cfg,
+    num_threads: usize,
+    options: SolveOptions,
+) -> SecondaryMap<BasicBlockIdx, BlockFacts> {
+    let problem = GenKill::new(
+        Direction::Backward,
+        &find_gen_set_para(cfg),
+        &find_kill_set_para(cfg),
+    );
+    parallel::solve_gen_kill(cfg, &problem, num_threads, options)
+}
+
+/// Same as [`liveness_para`], with the sets of live variables represented
+/// as `S`.
+pub fn liveness_para_in<S: BitSet>(
+    cfg: &Cfg,
+    num_threads: usize,
+) -> SecondaryMap<BasicBlockIdx, BlockFacts<S>> {
     let (kill_set, gen_set) = (find_kill_set_para(cfg), find_gen_set_para(cfg));
     parallel::solve_dataflow(
         cfg,
         Direction::Backward,
-        FixedBitSet::new(),
+        S::default(),
         |mut in1, in2| {
             in1.union_with(in2);
             in1
         },
-        |block_idx, mut merged_in| {
-            merged_in.difference_with(&kill_set.get(&block_idx).unwrap());
-            merged_in.union_with(&gen_set.get(&block_idx).unwrap());
-            merged_in
+        gen_kill_transfer(&kill_set, &gen_set),
+        num_threads,
+        SolveOptions::default(),
+    )
+}
+
+/// Same as [`liveness`], solved with `worklist`.
+pub fn liveness_with_stats(
+    cfg: &Cfg,
+    worklist: Worklist,
+) -> (SecondaryMap<BasicBlockIdx, BlockFacts>, SolveStats) {
+    let mut stats = SolveStats::default();
+    let solution = liveness_with_options(
+        cfg,
+        SolveOptions {
+            worklist,
+            stats: Some(&mut stats),
+            ..Default::default()
+        },
+    );
+    (solution, stats)
+}
+
+/// Same as [`liveness_para`], solving every component with `worklist`.
+pub fn liveness_para_with_stats(
+    cfg: &Cfg,
+    worklist: Worklist,
+    num_threads: usize,
+) -> (SecondaryMap<BasicBlockIdx, BlockFacts>, SolveStats) {
+    let mut stats = SolveStats::default();
+    let solution = liveness_para_with_options(
+        cfg,
+        num_threads,
+        SolveOptions {
+            worklist,
+            stats: Some(&mut stats),
+            ..Default::default()
+        },
+    );
+    (solution, stats)
+}
+
+/// Same as [`liveness`], re-solving `previous` after the instructions of
+/// the `changed` blocks were rewritten in place.
+pub fn liveness_incremental(
+    cfg: &Cfg,
+    previous: SecondaryMap<BasicBlockIdx, BlockFacts>,
+    changed: &HashSet<BasicBlockIdx>,
+) -> SecondaryMap<BasicBlockIdx, BlockFacts> {
+    liveness_with_options(
+        cfg,
+        SolveOptions {
+            previous: Some(Previous {
+                solution: previous,
+                changed,
+            }),
+            ..Default::default()
         },
+    )
+}
+
+/// Same as [`liveness_para`], re-solving `previous` after the instructions
+/// of the `changed` blocks were rewritten in place.
+pub fn liveness_para_incremental(
+    cfg: &Cfg,
+    previous: SecondaryMap<BasicBlockIdx, BlockFacts>,
+    changed: &HashSet<BasicBlockIdx>,
+    num_threads: usize,
+) -> SecondaryMap<BasicBlockIdx, BlockFacts> {
+    liveness_para_with_options(
+        cfg,
         num_threads,
+        SolveOptions {
+            previous: Some(Previous {
+                solution: previous,
+                changed,
+            }),
+            ..Default::default()
+        },
     )
 }
 
-fn find_kill_set(cfg: &Cfg) -> SecondaryMap<BasicBlockIdx, FixedBitSet> {
+/// Liveness as a gen/kill problem, for [`sequential::solve_gen_kill`] and
+/// [`parallel::solve_gen_kill`], which [`liveness`] solves. Every set is as
+/// long as the largest.
+pub fn liveness_gen_kill(cfg: &Cfg) -> GenKill {
+    GenKill::new(
+        Direction::Backward,
+        &find_gen_set(cfg, Instruction::operands),
+        &find_kill_set(cfg),
+    )
+}
+
+/// Instruction-level liveness on top of the solution of [`liveness`].
+pub fn liveness_instr<'a, 'program>(
+    cfg: &'a Cfg<'program>,
+    blocks: &'a SecondaryMap<BasicBlockIdx, BlockFacts>,
+) -> InstrFacts<
+    'a,
+    'program,
+    impl Fn(usize, &Instruction, FixedBitSet) -> FixedBitSet,
+> {
+    liveness_instr_with(cfg, blocks, Instruction::operands)
+}
+
+/// Instruction-level liveness on top of the solution of [`liveness_with`],
+/// given the same `operands`.
+pub fn liveness_instr_with<'a, 'program>(
+    cfg: &'a Cfg<'program>,
+    blocks: &'a SecondaryMap<BasicBlockIdx, BlockFacts>,
+    operands: impl Fn(&Instruction) -> Vec<Variable>,
+) -> InstrFacts<
+    'a,
+    'program,
+    impl Fn(usize, &Instruction, FixedBitSet) -> FixedBitSet,
+> {
+    InstrFacts::new(
+        cfg,
+        Direction::Backward,
+        blocks,
+        move |_, instruction, mut live| {
+            if let Some(dest) = instruction.dest()
+                && (dest.0 as usize) < live.len()
+            {
+                live.remove(dest.0 as usize);
+            }
+            for operand in operands(instruction) {
+                live.grow_and_insert(operand.0 as usize);
+            }
+            live
+        },
+    )
+}
+
+fn find_kill_set<S: BitSet>(cfg: &Cfg) -> SecondaryMap<BasicBlockIdx, S> {
     let mut kill_set = SecondaryMap::new();
     for (idx, block) in cfg.vertices.iter() {
-        let mut able_to_kill = FixedBitSet::new();
+        let mut able_to_kill = S::default();
         for instruction in block.instructions.iter().rev() {
             if let Some(dest) = instruction.dest() {
-                able_to_kill.grow_and_insert(dest.0 as usize);
+                able_to_kill.insert(dest.0 as usize);
             }
         }
         kill_set.insert(idx, able_to_kill);
@@ -58,15 +248,18 @@ fn find_kill_set(cfg: &Cfg) -> SecondaryMap<BasicBlockIdx, FixedBitSet> {
     kill_set
 }
 
-fn find_gen_set(cfg: &Cfg) -> SecondaryMap<BasicBlockIdx, FixedBitSet> {
+fn find_gen_set<S: BitSet>(
+    cfg: &Cfg,
+    operands: impl Fn(&Instruction) -> Vec<Variable>,
+) -> SecondaryMap<BasicBlockIdx, S> {
     let mut gen_set = SecondaryMap::new();
     for (idx, block) in cfg.vertices.iter() {
-        let mut generated = FixedBitSet::new();
+        let mut generated = S::default();
         let mut local_defs = HashSet::new();
         for instruction in block.instructions {
-            for operand in instruction.operands() {
+            for operand in operands(instruction) {
                 if !local_defs.contains(&operand.0) {
-                    generated.grow_and_insert(operand.0 as usize);
+                    generated.insert(operand.0 as usize);
                 }
             }
             if let Some(dest) = instruction.dest() {
@@ -78,36 +271,44 @@ fn find_gen_set(cfg: &Cfg) -> SecondaryMap<BasicBlockIdx, FixedBitSet> {
     gen_set
 }
 
-fn find_kill_set_para(cfg: &Cfg) -> DashMap<BasicBlockIdx, FixedBitSet> {
-    let kill_set = DashMap::new();
-    cfg.vertices.iter().par_bridge().for_each(|(idx, block)| {
-        let mut able_to_kill = FixedBitSet::new();
-        for instruction in block.instructions.iter().rev() {
-            if let Some(dest) = instruction.dest() {
-                able_to_kill.grow_and_insert(dest.0 as usize);
+fn find_kill_set_para<S: BitSet>(cfg: &Cfg) -> SecondaryMap<BasicBlockIdx, S> {
+    let kill_set: Vec<_> = cfg
+        .vertices
+        .iter()
+        .par_bridge()
+        .map(|(idx, block)| {
+            let mut able_to_kill = S::default();
+            for instruction in block.instructions.iter().rev() {
+                if let Some(dest) = instruction.dest() {
+                    able_to_kill.insert(dest.0 as usize);
+                }
             }
-        }
-        kill_set.insert(idx, able_to_kill);
-    });
-    kill_set
+            (idx, able_to_kill)
+        })
+        .collect();
+    kill_set.into_iter().collect()
 }
 
-fn find_gen_set_para(cfg: &Cfg) -> DashMap<BasicBlockIdx, FixedBitSet> {
-    let gen_set = DashMap::new();
-    cfg.vertices.iter().par_bridge().for_each(|(idx, block)| {
-        let mut generated = FixedBitSet::new();
-        let mut local_defs = HashSet::new();
-        for instruction in block.instructions {
-            for operand in instruction.operands() {
-                if !local_defs.contains(&operand.0) {
-                    generated.grow_and_insert(operand.0 as usize);
+fn find_gen_set_para<S: BitSet>(cfg: &Cfg) -> SecondaryMap<BasicBlockIdx, S> {
+    let gen_set: Vec<_> = cfg
+        .vertices
+        .iter()
+        .par_bridge()
+        .map(|(idx, block)| {
+            let mut generated = S::default();
+            let mut local_defs = HashSet::new();
+            for instruction in block.instructions {
+                for operand in instruction.operands() {
+                    if !local_defs.contains(&operand.0) {
+                        generated.insert(operand.0 as usize);
+                    }
+                }
+                if let Some(dest) = instruction.dest() {
+                    local_defs.insert(dest.0);
                 }
             }
-            if let Some(dest) = instruction.dest() {
-                local_defs.insert(dest.0);
-            }
-        }
-        gen_set.insert(idx, generated);
-    });
-    gen_set
+            (idx, generated)
+        })
+        .collect();
+    gen_set.into_iter().collect()
 }
@@ -0,0 +1,102 @@
+use super::{abstract_interp::*, prelude::*};
+use std::fmt;
+
+/// The set of parities an `int` value may have. Wrapping around on overflow
+/// preserves parity, so addition, subtraction and multiplication are exact.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Parity(u8);
+
+impl Parity {
+    pub const EVEN: Parity = Parity(1);
+    pub const ODD: Parity = Parity(2);
+    pub const TOP: Parity = Parity(3);
+
+    pub fn contains(&self, other: Parity) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    fn opposite(self) -> Parity {
+        Parity((self.0 & 1) << 1 | (self.0 & 2) >> 1)
+    }
+}
+
+impl fmt::Display for Parity {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str(match *self {
+            Self::EVEN => "even",
+            Self::ODD => "odd",
+            _ => "any",
+        })
+    }
+}
+
+impl AbstractValue for Parity {
+    fn top() -> Self {
+        Self::TOP
+    }
+
+    fn constant(value: i64) -> Self {
+        if value % 2 == 0 {
+            Self::EVEN
+        } else {
+            Self::ODD
+        }
+    }
+
+    fn may_equal(&self, value: i64) -> bool {
+        self.contains(Self::constant(value))
+    }
+
+    fn join(&self, other: &Self) -> Self {
+        Parity(self.0 | other.0)
+    }
+
+    fn add(&self, other: &Self) -> Self {
+        match *other {
+            Self::EVEN => *self,
+            Self::ODD => self.opposite(),
+            _ => Self::TOP,
+        }
+    }
+
+    fn sub(&self, other: &Self) -> Self {
+        self.add(other)
+    }
+
+    fn mul(&self, other: &Self) -> Self {
+        if *self == Self::EVEN || *other == Self::EVEN {
+            Self::EVEN
+        } else if *self == Self::ODD && *other == Self::ODD {
+            Self::ODD
+        } else {
+            Self::TOP
+        }
+    }
+
+    fn div(&self, _: &Self) -> Self {
+        Self::TOP
+    }
+
+    fn assume(
+        comparison: Comparison,
+        lhs: &Self,
+        rhs: &Self,
+    ) -> Option<(Self, Self)> {
+        match comparison {
+            Comparison::Eq => {
+                let both = Parity(lhs.0 & rhs.0);
+                (both.0 != 0).then_some((both, both))
+            }
+            _ => Some((*lhs, *rhs)),
+        }
+    }
+}
+
+pub type ParityEnv = AbstractEnv<Parity>;
+
+/// Parities of the `int` variables at the boundaries of every block.
+pub fn parities(
+    cfg: &Cfg,
+) -> SecondaryMap<BasicBlockIdx, BlockFacts<ParityEnv>> {
+    abstract_interp(cfg)
+}
@@ -1,77 +1,268 @@
 use super::prelude::*;
-use std::collections::HashMap;
+use crate::Previous;
+use std::collections::{HashMap, HashSet};
 
-/// ones in the returned bitset should be interpreted as offset of the
+/// ones in the returned bitsets should be interpreted as offset of the
 /// instruction that defines the reaching definition relative to function's
 /// instruction buffer
-pub fn reaching_def(cfg: &Cfg) -> SecondaryMap<BasicBlockIdx, FixedBitSet> {
-    let (kill_set, gen_set) = (find_kill_set(cfg), find_gen_set(cfg));
+pub fn reaching_def(cfg: &Cfg) -> SecondaryMap<BasicBlockIdx, BlockFacts> {
+    reaching_def_with_options(cfg, SolveOptions::default())
+}
+
+/// Same as [`reaching_def`], solved as configured by `options`. Facts hold
+/// instruction offsets, so the changed blocks of a previous solution must
+/// keep their instruction count.
+pub fn reaching_def_with_options(
+    cfg: &Cfg,
+    options: SolveOptions,
+) -> SecondaryMap<BasicBlockIdx, BlockFacts> {
+    debug_assert!(
+        options.previous.as_ref().is_none_or(|previous| {
+            keeps_instruction_count(cfg, &previous.solution)
+        }),
+        "changed blocks must keep their instruction count"
+    );
     // function parameters are not tracked
+    sequential::solve_gen_kill(
+        cfg,
+        &(),
+        &reaching_def_gen_kill(cfg),
+        &HashMap::new(),
+        options,
+    )
+}
+
+/// Same as [`reaching_def`], with the sets of definitions represented as
+/// `S`. Sparse representations only store the definitions that reach,
+/// rather than a bit for every instruction of the function.
+pub fn reaching_def_in<S: BitSet>(
+    cfg: &Cfg,
+) -> SecondaryMap<BasicBlockIdx, BlockFacts<S>> {
+    let (kill_set, gen_set) = (find_kill_set(cfg), find_gen_set(cfg));
     sequential::solve_dataflow(
         cfg,
         &(),
         Direction::Forward,
         HashMap::new(),
         |mut in1, in2| {
-            in1.grow(in2.len());
             in1.union_with(in2);
             in1
         },
-        |block_idx, mut merged_in| {
-            merged_in.difference_with(&kill_set[block_idx]);
-            merged_in.union_with(&gen_set[block_idx]);
-            merged_in
-        },
+        gen_kill_transfer(&kill_set, &gen_set),
+        SolveOptions::default(),
     )
 }
 
 pub fn reaching_def_para(
     cfg: &Cfg,
     num_threads: usize,
-) -> DashMap<BasicBlockIdx, FixedBitSet> {
+) -> SecondaryMap<BasicBlockIdx, BlockFacts> {
+    reaching_def_para_with_options(cfg, num_threads, SolveOptions::default())
+}
+
+/// Same as [`reaching_def_para`], solved as configured by `options`, under
+/// the same conditions as [`reaching_def_with_options`].
+pub fn reaching_def_para_with_options(
+    cfg: &Cfg,
+    num_threads: usize,
+    options: SolveOptions,
+) -> SecondaryMap<BasicBlockIdx, BlockFacts> {
+    debug_assert!(
+        options.previous.as_ref().is_none_or(|previous| {
+            keeps_instruction_count(cfg, &previous.solution)
+        }),
+        "changed blocks must keep their instruction count"
+    );
+    let problem = GenKill::new(
+        Direction::Forward,
+        &find_gen_set_para(cfg),
+        &find_kill_set_para(cfg),
+    );
+    parallel::solve_gen_kill(cfg, &problem, num_threads, options)
+}
+
+/// Same as [`reaching_def_para`], with the sets of definitions represented
+/// as `S`.
+pub fn reaching_def_para_in<S: BitSet>(
+    cfg: &Cfg,
+    num_threads: usize,
+) -> SecondaryMap<BasicBlockIdx, BlockFacts<S>> {
     let (kill_set, gen_set) = (find_kill_set_para(cfg), find_gen_set_para(cfg));
-    // function parameters are not tracked
     parallel::solve_dataflow(
         cfg,
         Direction::Forward,
-        FixedBitSet::new(),
+        S::default(),
         |mut in1, in2| {
             in1.union_with(in2);
             in1
         },
-        |block_idx, mut merged_in| {
-            merged_in.difference_with(&kill_set.get(&block_idx).unwrap());
-            merged_in.union_with(&gen_set.get(&block_idx).unwrap());
-            merged_in
+        gen_kill_transfer(&kill_set, &gen_set),
+        num_threads,
+        SolveOptions::default(),
+    )
+}
+
+/// Same as [`reaching_def`], solved with `worklist`.
+pub fn reaching_def_with_stats(
+    cfg: &Cfg,
+    worklist: Worklist,
+) -> (SecondaryMap<BasicBlockIdx, BlockFacts>, SolveStats) {
+    let mut stats = SolveStats::default();
+    let solution = reaching_def_with_options(
+        cfg,
+        SolveOptions {
+            worklist,
+            stats: Some(&mut stats),
+            ..Default::default()
+        },
+    );
+    (solution, stats)
+}
+
+/// Same as [`reaching_def_para`], solving every component with `worklist`.
+pub fn reaching_def_para_with_stats(
+    cfg: &Cfg,
+    worklist: Worklist,
+    num_threads: usize,
+) -> (SecondaryMap<BasicBlockIdx, BlockFacts>, SolveStats) {
+    let mut stats = SolveStats::default();
+    let solution = reaching_def_para_with_options(
+        cfg,
+        num_threads,
+        SolveOptions {
+            worklist,
+            stats: Some(&mut stats),
+            ..Default::default()
+        },
+    );
+    (solution, stats)
+}
+
+/// Same as [`reaching_def`], re-solving `previous` after the instructions of
+/// the `changed` blocks were rewritten in place. The definitions of changed
+/// blocks only reach the blocks downstream of them, which are solved again
+/// anyway, so the kill sets of the other blocks cannot have changed in a way
+/// that matters. Facts hold instruction offsets, so the changed blocks must
+/// keep their instruction count for the other facts to stay valid.
+pub fn reaching_def_incremental(
+    cfg: &Cfg,
+    previous: SecondaryMap<BasicBlockIdx, BlockFacts>,
+    changed: &HashSet<BasicBlockIdx>,
+) -> SecondaryMap<BasicBlockIdx, BlockFacts> {
+    reaching_def_with_options(
+        cfg,
+        SolveOptions {
+            previous: Some(Previous {
+                solution: previous,
+                changed,
+            }),
+            ..Default::default()
         },
+    )
+}
+
+/// Same as [`reaching_def_para`], re-solving `previous` after the
+/// instructions of the `changed` blocks were rewritten in place, under the
+/// same conditions as [`reaching_def_incremental`].
+pub fn reaching_def_para_incremental(
+    cfg: &Cfg,
+    previous: SecondaryMap<BasicBlockIdx, BlockFacts>,
+    changed: &HashSet<BasicBlockIdx>,
+    num_threads: usize,
+) -> SecondaryMap<BasicBlockIdx, BlockFacts> {
+    reaching_def_para_with_options(
+        cfg,
         num_threads,
+        SolveOptions {
+            previous: Some(Previous {
+                solution: previous,
+                changed,
+            }),
+            ..Default::default()
+        },
     )
 }
 
-fn find_kill_set(cfg: &Cfg) -> SecondaryMap<BasicBlockIdx, FixedBitSet> {
-    let total_instr_num = cfg
-        .vertices
-        .values()
-        .map(|v| v.offset + v.instructions.len())
-        .max()
-        .unwrap_or(0);
+/// Reaching definitions as a gen/kill problem, for
+/// [`sequential::solve_gen_kill`] and [`parallel::solve_gen_kill`], which
+/// [`reaching_def`] solves.
+pub fn reaching_def_gen_kill(cfg: &Cfg) -> GenKill {
+    GenKill::new(Direction::Forward, &find_gen_set(cfg), &find_kill_set(cfg))
+}
+
+/// Instruction-level reaching definitions on top of the solution of
+/// [`reaching_def`].
+pub fn reaching_def_instr<'a, 'program>(
+    cfg: &'a Cfg<'program>,
+    blocks: &'a SecondaryMap<BasicBlockIdx, BlockFacts>,
+) -> InstrFacts<
+    'a,
+    'program,
+    impl Fn(usize, &Instruction, FixedBitSet) -> FixedBitSet,
+> {
+    let total_instr_num = total_instr_num(cfg);
+    let definitions = find_definitions::<FixedBitSet>(cfg);
+    InstrFacts::new(
+        cfg,
+        Direction::Forward,
+        blocks,
+        move |offset, instruction, mut reaching| {
+            if let Some(dest) = instruction.dest() {
+                reaching.grow(total_instr_num);
+                reaching.difference_with(&definitions[&dest.0]);
+                reaching.insert(offset);
+            }
+            reaching
+        },
+    )
+}
 
-    let mut universe: HashMap<u32, FixedBitSet> = HashMap::new();
+/// maps every variable to the offsets of all instructions defining it
+pub(crate) fn find_definitions<S: BitSet>(cfg: &Cfg) -> HashMap<u32, S> {
+    let total_instr_num = total_instr_num(cfg);
+    let mut universe: HashMap<u32, S> = HashMap::new();
     for block in cfg.vertices.values() {
         for (i, instruction) in block.instructions.iter().enumerate() {
             if let Some(dest) = instruction.dest() {
                 universe
                     .entry(dest.0)
-                    .or_insert(FixedBitSet::with_capacity(total_instr_num))
+                    .or_insert_with(|| S::with_capacity(total_instr_num))
                     .insert(block.offset + i);
             }
         }
     }
+    universe
+}
+
+/// Whether every fact of `previous` is as long as the function, so its
+/// offsets can still point at the same instructions of `cfg`.
+fn keeps_instruction_count(
+    cfg: &Cfg,
+    previous: &SecondaryMap<BasicBlockIdx, BlockFacts>,
+) -> bool {
+    let total_instr_num = total_instr_num(cfg);
+    previous.values().all(|facts| {
+        facts.before.len() == total_instr_num
+            && facts.after.len() == total_instr_num
+    })
+}
+
+pub(crate) fn total_instr_num(cfg: &Cfg) -> usize {
+    cfg.vertices
+        .values()
+        .map(|v| v.offset + v.instructions.len())
+        .max()
+        .unwrap_or(0)
+}
+
+fn find_kill_set<S: BitSet>(cfg: &Cfg) -> SecondaryMap<BasicBlockIdx, S> {
+    let total_instr_num = total_instr_num(cfg);
+    let universe = find_definitions(cfg);
 
     let mut kill_set = SecondaryMap::with_capacity(cfg.vertices.capacity());
     for (idx, block) in cfg.vertices.iter() {
         let mut able_to_kill = block.instructions.iter().fold(
-            FixedBitSet::with_capacity(total_instr_num),
+            S::with_capacity(total_instr_num),
             |mut acc, instruction| {
                 if let Some(dest) = instruction.dest() {
                     acc.union_with(&universe[&dest.0]);
@@ -79,21 +270,16 @@ fn find_kill_set(cfg: &Cfg) -> SecondaryMap<BasicBlockIdx, FixedBitSet> {
                 acc
             },
         );
-        able_to_kill.remove_range(
-            block.offset..(block.offset + block.instructions.len()),
-        );
+        for offset in block.offset..(block.offset + block.instructions.len()) {
+            able_to_kill.remove(offset);
+        }
         kill_set.insert(idx, able_to_kill);
     }
     kill_set
 }
 
-fn find_gen_set(cfg: &Cfg) -> SecondaryMap<BasicBlockIdx, FixedBitSet> {
-    let total_instr_num = cfg
-        .vertices
-        .values()
-        .map(|v| v.offset + v.instructions.len())
-        .max()
-        .unwrap_or(0);
+fn find_gen_set<S: BitSet>(cfg: &Cfg) -> SecondaryMap<BasicBlockIdx, S> {
+    let total_instr_num = total_instr_num(cfg);
 
     let mut gen_set = SecondaryMap::with_capacity(cfg.vertices.capacity());
     for (idx, block) in cfg.vertices.iter() {
@@ -106,7 +292,7 @@ fn find_gen_set(cfg: &Cfg) -> SecondaryMap<BasicBlockIdx, FixedBitSet> {
         gen_set.insert(
             idx,
             generated.into_values().fold(
-                FixedBitSet::with_capacity(total_instr_num),
+                S::with_capacity(total_instr_num),
                 |mut acc, offset| {
                     acc.insert(offset);
                     acc
@@ -117,70 +303,59 @@ fn find_gen_set(cfg: &Cfg) -> SecondaryMap<BasicBlockIdx, FixedBitSet> {
     gen_set
 }
 
-fn find_kill_set_para(cfg: &Cfg) -> DashMap<BasicBlockIdx, FixedBitSet> {
-    let total_instr_num = cfg
+fn find_kill_set_para<S: BitSet>(cfg: &Cfg) -> SecondaryMap<BasicBlockIdx, S> {
+    let total_instr_num = total_instr_num(cfg);
+    let universe = find_definitions(cfg);
+
+    let kill_set: Vec<_> = cfg
         .vertices
-        .values()
-        .map(|v| v.offset + v.instructions.len())
-        .max()
-        .unwrap_or(0);
-    let mut universe: HashMap<u32, FixedBitSet> = HashMap::new();
-    for block in cfg.vertices.values() {
-        for (i, instruction) in block.instructions.iter().enumerate() {
-            if let Some(dest) = instruction.dest() {
-                universe
-                    .entry(dest.0)
-                    .or_insert(FixedBitSet::with_capacity(total_instr_num))
-                    .insert(block.offset + i);
+        .iter()
+        .par_bridge()
+        .map(|(idx, block)| {
+            let mut able_to_kill = block.instructions.iter().fold(
+                S::with_capacity(total_instr_num),
+                |mut acc, instruction| {
+                    if let Some(dest) = instruction.dest() {
+                        acc.union_with(&universe[&dest.0]);
+                    }
+                    acc
+                },
+            );
+            for offset in
+                block.offset..(block.offset + block.instructions.len())
+            {
+                able_to_kill.remove(offset);
             }
-        }
-    }
-
-    let kill_set = DashMap::new();
-    cfg.vertices.iter().par_bridge().for_each(|(idx, block)| {
-        let mut able_to_kill = block.instructions.iter().fold(
-            FixedBitSet::with_capacity(total_instr_num),
-            |mut acc, instruction| {
-                if let Some(dest) = instruction.dest() {
-                    acc.union_with(&universe[&dest.0]);
-                }
-                acc
-            },
-        );
-        able_to_kill.remove_range(
-            block.offset..(block.offset + block.instructions.len()),
-        );
-        kill_set.insert(idx, able_to_kill);
-    });
-    kill_set
+            (idx, able_to_kill)
+        })
+        .collect();
+    kill_set.into_iter().collect()
 }
 
-fn find_gen_set_para(cfg: &Cfg) -> DashMap<BasicBlockIdx, FixedBitSet> {
-    let total_instr_num = cfg
-        .vertices
-        .values()
-        .map(|v| v.offset + v.instructions.len())
-        .max()
-        .unwrap_or(0);
+fn find_gen_set_para<S: BitSet>(cfg: &Cfg) -> SecondaryMap<BasicBlockIdx, S> {
+    let total_instr_num = total_instr_num(cfg);
 
-    let gen_set = DashMap::new();
-    cfg.vertices.iter().par_bridge().for_each(|(idx, block)| {
-        let mut generated: HashMap<u32, usize> = HashMap::new();
-        for (i, instruction) in block.instructions.iter().enumerate().rev() {
-            if let Some(dest) = instruction.dest() {
-                generated.entry(dest.0).or_insert(block.offset + i);
+    let gen_set: Vec<_> = cfg
+        .vertices
+        .iter()
+        .par_bridge()
+        .map(|(idx, block)| {
+            let mut generated: HashMap<u32, usize> = HashMap::new();
+            for (i, instruction) in block.instructions.iter().enumerate().rev()
+            {
+                if let Some(dest) = instruction.dest() {
+                    generated.entry(dest.0).or_insert(block.offset + i);
+                }
             }
-        }
-        gen_set.insert(
-            idx,
-            generated.into_values().fold(
-                FixedBitSet::with_capacity(total_instr_num),
+            let generated = generated.into_values().fold(
+                S::with_capacity(total_instr_num),
                 |mut acc, offset| {
                     acc.insert(offset);
                     acc
                 },
-            ),
-        );
-    });
-    gen_set
+            );
+            (idx, generated)
+        })
+        .collect();
+    gen_set.into_iter().collect()
 }
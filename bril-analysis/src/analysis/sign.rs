@@ -0,0 +1,188 @@
+use super::{abstract_interp::*, interval::Interval, prelude::*};
+use std::fmt;
+
+/// The set of signs an `int` value may have. Transfer functions stay sound
+/// when values wrap around on overflow.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Sign(u8);
+
+impl Sign {
+    pub const NEGATIVE: Sign = Sign(1);
+    pub const ZERO: Sign = Sign(2);
+    pub const POSITIVE: Sign = Sign(4);
+    pub const TOP: Sign = Sign(7);
+
+    const EMPTY: Sign = Sign(0);
+    const NONZERO: Sign = Sign(5);
+    const NONNEGATIVE: Sign = Sign(6);
+    const NONPOSITIVE: Sign = Sign(3);
+    const COMPONENTS: [Sign; 3] = [Self::NEGATIVE, Self::ZERO, Self::POSITIVE];
+
+    pub fn contains(&self, other: Sign) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    fn union(self, other: Sign) -> Sign {
+        Sign(self.0 | other.0)
+    }
+
+    fn components(self) -> impl Iterator<Item = Sign> {
+        Self::COMPONENTS
+            .into_iter()
+            .filter(move |component| self.contains(*component))
+    }
+
+    /// Applies `f`, defined on single signs, to every pair of components.
+    fn lift(&self, other: &Self, f: impl Fn(Sign, Sign) -> Sign) -> Sign {
+        self.components()
+            .flat_map(|lhs| other.components().map(move |rhs| (lhs, rhs)))
+            .fold(Self::EMPTY, |acc, (lhs, rhs)| acc.union(f(lhs, rhs)))
+    }
+
+    fn range(component: Sign) -> Interval {
+        match component {
+            Self::NEGATIVE => Interval {
+                lo: i64::MIN,
+                hi: -1,
+            },
+            Self::ZERO => Interval { lo: 0, hi: 0 },
+            _ => Interval {
+                lo: 1,
+                hi: i64::MAX,
+            },
+        }
+    }
+
+    /// The smallest interval containing all the components.
+    fn hull(&self) -> Interval {
+        self.components()
+            .map(Self::range)
+            .reduce(|acc, range| acc.join(&range))
+            .unwrap_or(Interval::TOP)
+    }
+
+    /// The components overlapping `range`, or `None` if there are none.
+    fn restrict(&self, range: Interval) -> Option<Sign> {
+        let sign = Sign(
+            self.components()
+                .filter(|component| {
+                    let component = Self::range(*component);
+                    component.lo <= range.hi && range.lo <= component.hi
+                })
+                .fold(0, |acc, component| acc | component.0),
+        );
+        (sign != Self::EMPTY).then_some(sign)
+    }
+}
+
+impl fmt::Display for Sign {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let symbols = self
+            .components()
+            .map(|component| match component {
+                Self::NEGATIVE => "-",
+                Self::ZERO => "0",
+                _ => "+",
+            })
+            .collect::<Vec<_>>();
+        write!(formatter, "{{{}}}", symbols.join(", "))
+    }
+}
+
+impl AbstractValue for Sign {
+    fn top() -> Self {
+        Self::TOP
+    }
+
+    fn constant(value: i64) -> Self {
+        match value.signum() {
+            -1 => Self::NEGATIVE,
+            0 => Self::ZERO,
+            _ => Self::POSITIVE,
+        }
+    }
+
+    fn may_equal(&self, value: i64) -> bool {
+        self.contains(Self::constant(value))
+    }
+
+    fn join(&self, other: &Self) -> Self {
+        self.union(*other)
+    }
+
+    fn add(&self, other: &Self) -> Self {
+        self.lift(other, |lhs, rhs| match (lhs, rhs) {
+            (Self::ZERO, sign) | (sign, Self::ZERO) => sign,
+            // positive sums can only wrap around to negative values
+            (Self::POSITIVE, Self::POSITIVE) => Self::NONZERO,
+            _ => Self::TOP,
+        })
+    }
+
+    fn sub(&self, other: &Self) -> Self {
+        self.lift(other, |lhs, rhs| match (lhs, rhs) {
+            (sign, Self::ZERO) => sign,
+            (Self::ZERO, Self::POSITIVE) => Self::NEGATIVE,
+            // negating the minimum wraps around to itself
+            (Self::ZERO, Self::NEGATIVE) => Self::NONZERO,
+            (Self::POSITIVE, Self::NEGATIVE)
+            | (Self::NEGATIVE, Self::POSITIVE) => Self::NONZERO,
+            _ => Self::TOP,
+        })
+    }
+
+    fn mul(&self, other: &Self) -> Self {
+        // any nonzero product may wrap around to any value
+        self.lift(other, |lhs, rhs| match (lhs, rhs) {
+            (Self::ZERO, _) | (_, Self::ZERO) => Self::ZERO,
+            _ => Self::TOP,
+        })
+    }
+
+    fn div(&self, other: &Self) -> Self {
+        let quotient = self.lift(other, |lhs, rhs| match (lhs, rhs) {
+            (_, Self::ZERO) => Self::EMPTY,
+            (Self::ZERO, _) => Self::ZERO,
+            (Self::POSITIVE, Self::POSITIVE) => Self::NONNEGATIVE,
+            (Self::POSITIVE, Self::NEGATIVE)
+            | (Self::NEGATIVE, Self::POSITIVE) => Self::NONPOSITIVE,
+            // dividing the minimum by -1 wraps around to itself
+            _ => Self::TOP,
+        });
+        if quotient == Self::EMPTY {
+            // always traps
+            Self::TOP
+        } else {
+            quotient
+        }
+    }
+
+    fn assume(
+        comparison: Comparison,
+        lhs: &Self,
+        rhs: &Self,
+    ) -> Option<(Self, Self)> {
+        if comparison == Comparison::Ne {
+            // zero is the only singleton the other side can rule out
+            let exclude = |this: &Self, other: &Self| {
+                let sign = if *other == Self::ZERO {
+                    Sign(this.0 & !Self::ZERO.0)
+                } else {
+                    *this
+                };
+                (sign != Self::EMPTY).then_some(sign)
+            };
+            return Some((exclude(lhs, rhs)?, exclude(rhs, lhs)?));
+        }
+        let (lhs_range, rhs_range) =
+            Interval::assume(comparison, &lhs.hull(), &rhs.hull())?;
+        Some((lhs.restrict(lhs_range)?, rhs.restrict(rhs_range)?))
+    }
+}
+
+pub type SignEnv = AbstractEnv<Sign>;
+
+/// Signs of the `int` variables at the boundaries of every block.
+pub fn signs(cfg: &Cfg) -> SecondaryMap<BasicBlockIdx, BlockFacts<SignEnv>> {
+    abstract_interp(cfg)
+}
@@ -0,0 +1,50 @@
+use super::prelude::*;
+use std::collections::HashMap;
+
+/// ones of the returned bitsets are the variables possibly not assigned yet,
+/// along some path from the entry. Function parameters are assigned on entry.
+pub fn uninitialized(cfg: &Cfg) -> SecondaryMap<BasicBlockIdx, BlockFacts> {
+    let total_var_num = total_var_num(cfg);
+    let mut entry_input = FixedBitSet::with_capacity(total_var_num);
+    entry_input.insert_range(..);
+    for parameter in &cfg.prototype.parameters {
+        entry_input.set(parameter.0 as usize, false);
+    }
+    sequential::solve_dataflow(
+        cfg,
+        &(),
+        Direction::Forward,
+        HashMap::from([(cfg.entry, entry_input)]),
+        |mut in1, in2| {
+            in1.union_with(in2);
+            in1
+        },
+        |block_idx, mut merged_in| {
+            merged_in.grow(total_var_num);
+            for instruction in cfg.vertices[block_idx].instructions {
+                if let Some(dest) = instruction.dest() {
+                    merged_in.set(dest.0 as usize, false);
+                }
+            }
+            merged_in
+        },
+        SolveOptions::default(),
+    )
+}
+
+/// one more than the largest variable mentioned in the function
+fn total_var_num(cfg: &Cfg) -> usize {
+    let mentioned = cfg.vertices.values().flat_map(|block| {
+        block.instructions.iter().flat_map(|instruction| {
+            instruction.operands().into_iter().chain(instruction.dest())
+        })
+    });
+    cfg.prototype
+        .parameters
+        .iter()
+        .copied()
+        .chain(mentioned)
+        .map(|variable| variable.0 as usize + 1)
+        .max()
+        .unwrap_or(0)
+}
@@ -0,0 +1,350 @@
+// Copyright (C) 2025 Zihan Li and Ethan Uppal.
+use fixedbitset::FixedBitSet;
+
+/// Set of small integers, such as variable numbers or instruction offsets,
+/// that the bitset analyses are generic over. Sets compare equal when they
+/// hold the same elements, except for [`FixedBitSet`], which also compares
+/// lengths.
+pub trait BitSet: Clone + PartialEq + Default + Send + Sync {
+    /// An empty set for elements below `bits`. Only dense sets allocate for
+    /// them up front.
+    fn with_capacity(bits: usize) -> Self;
+
+    fn contains(&self, bit: usize) -> bool;
+
+    /// Inserts `bit`, growing the set if needed.
+    fn insert(&mut self, bit: usize);
+
+    fn remove(&mut self, bit: usize);
+
+    fn union_with(&mut self, other: &Self);
+
+    fn difference_with(&mut self, other: &Self);
+
+    /// Elements in increasing order.
+    fn ones(&self) -> impl Iterator<Item = usize> + '_;
+
+    fn count(&self) -> usize {
+        self.ones().count()
+    }
+}
+
+/// Dense representation, one bit for every element below the length.
+impl BitSet for FixedBitSet {
+    fn with_capacity(bits: usize) -> Self {
+        FixedBitSet::with_capacity(bits)
+    }
+
+    fn contains(&self, bit: usize) -> bool {
+        FixedBitSet::contains(self, bit)
+    }
+
+    fn insert(&mut self, bit: usize) {
+        self.grow_and_insert(bit);
+    }
+
+    fn remove(&mut self, bit: usize) {
+        if bit < self.len() {
+            self.set(bit, false);
+        }
+    }
+
+    fn union_with(&mut self, other: &Self) {
+        FixedBitSet::union_with(self, other);
+    }
+
+    fn difference_with(&mut self, other: &Self) {
+        FixedBitSet::difference_with(self, other);
+    }
+
+    fn ones(&self) -> impl Iterator<Item = usize> + '_ {
+        FixedBitSet::ones(self)
+    }
+
+    fn count(&self) -> usize {
+        self.count_ones(..)
+    }
+}
+
+/// Sparse representation as a sorted vector of the elements, for sets that
+/// hold few elements of a large universe.
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct SparseBitSet {
+    elements: Vec<u32>,
+}
+
+impl BitSet for SparseBitSet {
+    fn with_capacity(_: usize) -> Self {
+        Self::default()
+    }
+
+    fn contains(&self, bit: usize) -> bool {
+        self.elements.binary_search(&(bit as u32)).is_ok()
+    }
+
+    fn insert(&mut self, bit: usize) {
+        if let Err(at) = self.elements.binary_search(&(bit as u32)) {
+            self.elements.insert(at, bit as u32);
+        }
+    }
+
+    fn remove(&mut self, bit: usize) {
+        if let Ok(at) = self.elements.binary_search(&(bit as u32)) {
+            self.elements.remove(at);
+        }
+    }
+
+    fn union_with(&mut self, other: &Self) {
+        if other.elements.is_empty() {
+            return;
+        }
+        let mut merged =
+            Vec::with_capacity(self.elements.len() + other.elements.len());
+        let (mut i, mut j) = (0, 0);
+        while i < self.elements.len() && j < other.elements.len() {
+            let (a, b) = (self.elements[i], other.elements[j]);
+            merged.push(a.min(b));
+            i += usize::from(a <= b);
+            j += usize::from(b <= a);
+        }
+        merged.extend_from_slice(&self.elements[i..]);
+        merged.extend_from_slice(&other.elements[j..]);
+        self.elements = merged;
+    }
+
+    fn difference_with(&mut self, other: &Self) {
+        let mut j = 0;
+        self.elements.retain(|&element| {
+            while j < other.elements.len() && other.elements[j] < element {
+                j += 1;
+            }
+            other.elements.get(j) != Some(&element)
+        });
+    }
+
+    fn ones(&self) -> impl Iterator<Item = usize> + '_ {
+        self.elements.iter().map(|&element| element as usize)
+    }
+
+    fn count(&self) -> usize {
+        self.elements.len()
+    }
+}
+
+const CHUNK_BITS: usize = 1 << 16;
+const CHUNK_WORDS: usize = CHUNK_BITS / 64;
+/// chunks with more elements than this are stored as bitmaps, which then
+/// take up less space than the sorted elements
+const MAX_ARRAY_LEN: usize = CHUNK_BITS / 16;
+
+/// Roaring-style representation. Elements are split into chunks by their
+/// upper bits, and every chunk stores the lower bits of its elements
+/// either as a sorted array or, once dense enough, as a bitmap.
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct ChunkedBitSet {
+    /// non-empty chunks sorted by their upper bits
+    chunks: Vec<(u32, Chunk)>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+enum Chunk {
+    Array(Vec<u16>),
+    Bitmap(Box<[u64; CHUNK_WORDS]>),
+}
+
+impl Chunk {
+    fn contains(&self, low: u16) -> bool {
+        match self {
+            Self::Array(elements) => elements.binary_search(&low).is_ok(),
+            Self::Bitmap(words) => {
+                words[low as usize / 64] & (1 << (low % 64)) != 0
+            }
+        }
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            Self::Array(elements) => elements.len(),
+            Self::Bitmap(words) => {
+                words.iter().map(|word| word.count_ones() as usize).sum()
+            }
+        }
+    }
+
+    fn ones(&self) -> Box<dyn Iterator<Item = u16> + '_> {
+        match self {
+            Self::Array(elements) => Box::new(elements.iter().copied()),
+            Self::Bitmap(words) => {
+                Box::new(words.iter().enumerate().flat_map(|(i, &word)| {
+                    (0..64)
+                        .filter(move |bit| word & (1 << bit) != 0)
+                        .map(move |bit| (i * 64 + bit) as u16)
+                }))
+            }
+        }
+    }
+
+    fn insert(&mut self, low: u16) {
+        match self {
+            Self::Array(elements) => {
+                if let Err(at) = elements.binary_search(&low) {
+                    elements.insert(at, low);
+                    self.normalize();
+                }
+            }
+            Self::Bitmap(words) => words[low as usize / 64] |= 1 << (low % 64),
+        }
+    }
+
+    fn remove(&mut self, low: u16) {
+        match self {
+            Self::Array(elements) => {
+                if let Ok(at) = elements.binary_search(&low) {
+                    elements.remove(at);
+                }
+            }
+            Self::Bitmap(words) => {
+                words[low as usize / 64] &= !(1 << (low % 64));
+                self.normalize();
+            }
+        }
+    }
+
+    /// Switches to the representation matching the count of elements.
+    fn normalize(&mut self) {
+        let len = self.len();
+        match self {
+            Self::Array(elements) if len > MAX_ARRAY_LEN => {
+                let mut words = Box::new([0; CHUNK_WORDS]);
+                for &low in elements.iter() {
+                    words[low as usize / 64] |= 1 << (low % 64);
+                }
+                *self = Self::Bitmap(words);
+            }
+            Self::Bitmap(_) if len <= MAX_ARRAY_LEN => {
+                *self = Self::Array(self.ones().collect());
+            }
+            _ => {}
+        }
+    }
+
+    fn union_with(&mut self, other: &Self) {
+        match (&mut *self, other) {
+            (Self::Array(elements), Self::Array(others)) => {
+                elements.extend_from_slice(others);
+                elements.sort_unstable();
+                elements.dedup();
+            }
+            (Self::Array(elements), Self::Bitmap(others)) => {
+                let mut words = others.clone();
+                for &low in elements.iter() {
+                    words[low as usize / 64] |= 1 << (low % 64);
+                }
+                *self = Self::Bitmap(words);
+            }
+            (Self::Bitmap(words), Self::Array(others)) => {
+                for &low in others {
+                    words[low as usize / 64] |= 1 << (low % 64);
+                }
+            }
+            (Self::Bitmap(words), Self::Bitmap(others)) => {
+                for (word, other_word) in words.iter_mut().zip(others.iter()) {
+                    *word |= other_word;
+                }
+            }
+        }
+        self.normalize();
+    }
+
+    fn difference_with(&mut self, other: &Self) {
+        match (&mut *self, other) {
+            (Self::Array(elements), other) => {
+                elements.retain(|&low| !other.contains(low));
+            }
+            (Self::Bitmap(words), Self::Array(others)) => {
+                for &low in others {
+                    words[low as usize / 64] &= !(1 << (low % 64));
+                }
+            }
+            (Self::Bitmap(words), Self::Bitmap(others)) => {
+                for (word, other_word) in words.iter_mut().zip(others.iter()) {
+                    *word &= !other_word;
+                }
+            }
+        }
+        self.normalize();
+    }
+}
+
+impl ChunkedBitSet {
+    fn chunk(&self, high: u32) -> Option<&Chunk> {
+        self.chunks
+            .binary_search_by_key(&high, |(key, _)| *key)
+            .ok()
+            .map(|at| &self.chunks[at].1)
+    }
+}
+
+fn split(bit: usize) -> (u32, u16) {
+    ((bit / CHUNK_BITS) as u32, (bit % CHUNK_BITS) as u16)
+}
+
+impl BitSet for ChunkedBitSet {
+    fn with_capacity(_: usize) -> Self {
+        Self::default()
+    }
+
+    fn contains(&self, bit: usize) -> bool {
+        let (high, low) = split(bit);
+        self.chunk(high).is_some_and(|chunk| chunk.contains(low))
+    }
+
+    fn insert(&mut self, bit: usize) {
+        let (high, low) = split(bit);
+        match self.chunks.binary_search_by_key(&high, |(key, _)| *key) {
+            Ok(at) => self.chunks[at].1.insert(low),
+            Err(at) => self.chunks.insert(at, (high, Chunk::Array(vec![low]))),
+        }
+    }
+
+    fn remove(&mut self, bit: usize) {
+        let (high, low) = split(bit);
+        if let Ok(at) = self.chunks.binary_search_by_key(&high, |(key, _)| *key)
+        {
+            self.chunks[at].1.remove(low);
+            if self.chunks[at].1.len() == 0 {
+                self.chunks.remove(at);
+            }
+        }
+    }
+
+    fn union_with(&mut self, other: &Self) {
+        for (high, chunk) in &other.chunks {
+            match self.chunks.binary_search_by_key(high, |(key, _)| *key) {
+                Ok(at) => self.chunks[at].1.union_with(chunk),
+                Err(at) => self.chunks.insert(at, (*high, chunk.clone())),
+            }
+        }
+    }
+
+    fn difference_with(&mut self, other: &Self) {
+        self.chunks.retain_mut(|(high, chunk)| {
+            if let Some(other_chunk) = other.chunk(*high) {
+                chunk.difference_with(other_chunk);
+            }
+            chunk.len() > 0
+        });
+    }
+
+    fn ones(&self) -> impl Iterator<Item = usize> + '_ {
+        self.chunks.iter().flat_map(|(high, chunk)| {
+            chunk
+                .ones()
+                .map(move |low| *high as usize * CHUNK_BITS + low as usize)
+        })
+    }
+
+    fn count(&self) -> usize {
+        self.chunks.iter().map(|(_, chunk)| chunk.len()).sum()
+    }
+}
@@ -0,0 +1,126 @@
+// Copyright (C) 2025 Zihan Li and Ethan Uppal.
+use bril::ir::{FunctionIdx, Instruction, Program};
+use std::collections::HashMap;
+
+use crate::scc::{Component, tarjan};
+
+/// Which functions call which. Mutually recursive functions are grouped into
+/// strongly connected components, which interprocedural analyses visit as a
+/// unit.
+pub struct CallGraph {
+    callees: HashMap<FunctionIdx, Vec<FunctionIdx>>,
+    callers: HashMap<FunctionIdx, Vec<FunctionIdx>>,
+    /// components in bottom-up order: callees before their callers
+    components: Vec<Component<FunctionIdx>>,
+    component_of: HashMap<FunctionIdx, usize>,
+}
+
+impl CallGraph {
+    pub fn new(program: &Program) -> Self {
+        let functions: Vec<_> = (0..program.functions().count() as u32)
+            .map(FunctionIdx)
+            .collect();
+        let mut callees = HashMap::with_capacity(functions.len());
+        let mut callers: HashMap<_, Vec<_>> =
+            HashMap::with_capacity(functions.len());
+        for &function_idx in &functions {
+            let mut called = vec![];
+            for instruction in program.get_function(function_idx).instructions {
+                if let Instruction::Call(_, callee, _) = instruction
+                    && !called.contains(callee)
+                {
+                    called.push(*callee);
+                    callers.entry(*callee).or_default().push(function_idx);
+                }
+            }
+            callees.insert(function_idx, called);
+        }
+
+        let components = tarjan::<_, HashMap<_, _>>(
+            functions.iter().copied(),
+            |function_idx| callees[&function_idx].clone(),
+            functions.len(),
+        );
+        let mut component_of = HashMap::with_capacity(functions.len());
+        for (comp_idx, component) in components.iter().enumerate() {
+            for &function_idx in &component.vertices {
+                component_of.insert(function_idx, comp_idx);
+            }
+        }
+        Self {
+            callees,
+            callers,
+            components,
+            component_of,
+        }
+    }
+
+    /// Distinct functions called by `function`, in order of first call.
+    pub fn callees(&self, function: FunctionIdx) -> &[FunctionIdx] {
+        self.callees.get(&function).map_or(&[], Vec::as_slice)
+    }
+
+    /// Distinct functions calling `function`.
+    pub fn callers(&self, function: FunctionIdx) -> &[FunctionIdx] {
+        self.callers.get(&function).map_or(&[], Vec::as_slice)
+    }
+
+    /// Strongly connected components in bottom-up order, indexed by
+    /// [`CallGraph::component`].
+    pub fn components(&self) -> &[Component<FunctionIdx>] {
+        &self.components
+    }
+
+    /// Index of the component containing `function`.
+    pub fn component(&self, function: FunctionIdx) -> usize {
+        self.component_of[&function]
+    }
+
+    /// Whether `function` can call itself, directly or through others.
+    pub fn is_recursive(&self, function: FunctionIdx) -> bool {
+        self.components[self.component(function)].vertices.len() > 1
+            || self.callees(function).contains(&function)
+    }
+
+    /// Functions ordered so that callees come before their callers, except
+    /// within a recursive component.
+    pub fn bottom_up(&self) -> impl Iterator<Item = FunctionIdx> + '_ {
+        self.components
+            .iter()
+            .flat_map(|component| component.vertices.iter().copied())
+    }
+
+    /// Functions ordered so that callers come before their callees, except
+    /// within a recursive component.
+    pub fn top_down(&self) -> impl Iterator<Item = FunctionIdx> + '_ {
+        self.components
+            .iter()
+            .rev()
+            .flat_map(|component| component.vertices.iter().copied())
+    }
+
+    /// Components grouped into levels, bottom-up. Every callee of a level
+    /// lies in an earlier level or the same component, so the components of
+    /// one level are independent and can be analyzed in parallel.
+    pub fn bottom_up_levels(&self) -> Vec<Vec<usize>> {
+        let mut level_of = vec![0; self.components.len()];
+        let mut levels: Vec<Vec<usize>> = vec![];
+        for (comp_idx, component) in self.components.iter().enumerate() {
+            let level = component
+                .vertices
+                .iter()
+                .flat_map(|&function| self.callees(function))
+                .map(|&callee| self.component(callee))
+                .filter(|&callee_comp| callee_comp != comp_idx)
+                .map(|callee_comp| level_of[callee_comp] + 1)
+                .max()
+                .unwrap_or(0);
+            level_of[comp_idx] = level;
+            if levels.len() <= level {
+                levels.resize_with(level + 1, Vec::new);
+            }
+            levels[level].push(comp_idx);
+        }
+        levels
+    }
+}
@@ -0,0 +1,215 @@
+// Copyright (C) 2025 Zihan Li and Ethan Uppal.
+use bril::builder::BasicBlockIdx;
+use bril_cfg::Cfg;
+use slotmap::SecondaryMap;
+use std::collections::HashSet;
+
+use crate::construct_postorder;
+
+/// Blocks forming a cycle entered only through its header.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct NaturalLoop {
+    pub header: BasicBlockIdx,
+    /// sources of the back edges to the header
+    pub latches: Vec<BasicBlockIdx>,
+    /// every block of the loop, the header included
+    pub blocks: HashSet<BasicBlockIdx>,
+}
+
+/// Dominator tree of the blocks reachable from the entry of a CFG, computed
+/// with the iterative algorithm of Cooper, Harvey and Kennedy.
+pub struct Dominators {
+    entry: BasicBlockIdx,
+    /// immediate dominators, the entry being its own
+    idoms: SecondaryMap<BasicBlockIdx, BasicBlockIdx>,
+    children: SecondaryMap<BasicBlockIdx, Vec<BasicBlockIdx>>,
+    reverse_postorder: Vec<BasicBlockIdx>,
+}
+
+impl Dominators {
+    pub fn new(cfg: &Cfg) -> Self {
+        let postorder = construct_postorder(cfg, &());
+        let mut postorder_numbers = SecondaryMap::new();
+        for (i, &block_idx) in postorder.iter().enumerate() {
+            postorder_numbers.insert(block_idx, i);
+        }
+        let reverse_postorder: Vec<_> = postorder.into_iter().rev().collect();
+
+        let mut idoms = SecondaryMap::new();
+        idoms.insert(cfg.entry, cfg.entry);
+        let intersect = |idoms: &SecondaryMap<_, _>, mut lhs, mut rhs| {
+            while lhs != rhs {
+                while postorder_numbers[lhs] < postorder_numbers[rhs] {
+                    lhs = idoms[lhs];
+                }
+                while postorder_numbers[rhs] < postorder_numbers[lhs] {
+                    rhs = idoms[rhs];
+                }
+            }
+            lhs
+        };
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for &block_idx in reverse_postorder.iter().skip(1) {
+                let mut processed = cfg
+                    .predecessors(block_idx)
+                    .into_iter()
+                    .filter(|predecessor| idoms.contains_key(*predecessor));
+                // a predecessor precedes the block in reverse postorder
+                let first = processed.next().unwrap();
+                let idom = processed.fold(first, |idom, predecessor| {
+                    intersect(&idoms, idom, predecessor)
+                });
+                if idoms.get(block_idx) != Some(&idom) {
+                    idoms.insert(block_idx, idom);
+                    changed = true;
+                }
+            }
+        }
+
+        let mut children: SecondaryMap<_, Vec<_>> = SecondaryMap::new();
+        for &block_idx in &reverse_postorder {
+            children.insert(block_idx, vec![]);
+        }
+        for &block_idx in reverse_postorder.iter().skip(1) {
+            children[idoms[block_idx]].push(block_idx);
+        }
+        Self {
+            entry: cfg.entry,
+            idoms,
+            children,
+            reverse_postorder,
+        }
+    }
+
+    /// `None` for the entry and blocks unreachable from it.
+    pub fn idom(&self, block_idx: BasicBlockIdx) -> Option<BasicBlockIdx> {
+        if block_idx == self.entry {
+            return None;
+        }
+        self.idoms.get(block_idx).copied()
+    }
+
+    pub fn is_reachable(&self, block_idx: BasicBlockIdx) -> bool {
+        self.idoms.contains_key(block_idx)
+    }
+
+    /// Whether every path from the entry to `block_idx` passes through
+    /// `dominator`. Blocks dominate themselves.
+    pub fn dominates(
+        &self,
+        dominator: BasicBlockIdx,
+        mut block_idx: BasicBlockIdx,
+    ) -> bool {
+        if !self.is_reachable(block_idx) {
+            return false;
+        }
+        loop {
+            if block_idx == dominator {
+                return true;
+            }
+            match self.idom(block_idx) {
+                Some(idom) => block_idx = idom,
+                None => return false,
+            }
+        }
+    }
+
+    /// Blocks immediately dominated by `block_idx`.
+    pub fn children(&self, block_idx: BasicBlockIdx) -> &[BasicBlockIdx] {
+        self.children.get(block_idx).map_or(&[], Vec::as_slice)
+    }
+
+    /// Reachable blocks in reverse postorder, so that every block comes after
+    /// its dominators.
+    pub fn reverse_postorder(&self) -> &[BasicBlockIdx] {
+        &self.reverse_postorder
+    }
+
+    /// Reachable blocks in a preorder walk of the dominator tree.
+    pub fn preorder(&self) -> Vec<BasicBlockIdx> {
+        let mut preorder = vec![];
+        let mut stack = vec![self.entry];
+        while let Some(block_idx) = stack.pop() {
+            preorder.push(block_idx);
+            stack.extend(self.children(block_idx).iter().rev());
+        }
+        preorder
+    }
+
+    /// Dominance frontier of every reachable block: the blocks where its
+    /// dominance ends.
+    pub fn frontiers(
+        &self,
+        cfg: &Cfg,
+    ) -> SecondaryMap<BasicBlockIdx, Vec<BasicBlockIdx>> {
+        let mut frontiers: SecondaryMap<_, Vec<_>> = SecondaryMap::new();
+        for &block_idx in &self.reverse_postorder {
+            frontiers.insert(block_idx, vec![]);
+        }
+        for &block_idx in &self.reverse_postorder {
+            let predecessors: Vec<_> = cfg
+                .predecessors(block_idx)
+                .into_iter()
+                .filter(|predecessor| self.is_reachable(*predecessor))
+                .collect();
+            // the entry is also entered from outside the function
+            if predecessors.len() < 2 && block_idx != self.entry {
+                continue;
+            }
+            for predecessor in predecessors {
+                let mut runner = predecessor;
+                while Some(runner) != self.idom(block_idx) {
+                    if !frontiers[runner].contains(&block_idx) {
+                        frontiers[runner].push(block_idx);
+                    }
+                    match self.idom(runner) {
+                        Some(idom) => runner = idom,
+                        None => break,
+                    }
+                }
+            }
+        }
+        frontiers
+    }
+
+    /// Natural loops of the back edges of `cfg`, edges to a block dominating
+    /// their source. Loops sharing a header are merged.
+    pub fn natural_loops(&self, cfg: &Cfg) -> Vec<NaturalLoop> {
+        let mut loops: Vec<NaturalLoop> = vec![];
+        for &block_idx in &self.reverse_postorder {
+            for successor in cfg.successors(block_idx) {
+                if !self.dominates(successor, block_idx) {
+                    continue;
+                }
+                match loops.iter_mut().find(|found| found.header == successor) {
+                    Some(found) => {
+                        if !found.latches.contains(&block_idx) {
+                            found.latches.push(block_idx);
+                        }
+                    }
+                    None => loops.push(NaturalLoop {
+                        header: successor,
+                        latches: vec![block_idx],
+                        blocks: HashSet::new(),
+                    }),
+                }
+            }
+        }
+        for natural_loop in &mut loops {
+            natural_loop.blocks.insert(natural_loop.header);
+            let mut stack = natural_loop.latches.clone();
+            while let Some(block_idx) = stack.pop() {
+                if natural_loop.blocks.insert(block_idx) {
+                    stack.extend(
+                        cfg.predecessors(block_idx).into_iter().filter(
+                            |predecessor| self.is_reachable(*predecessor),
+                        ),
+                    );
+                }
+            }
+        }
+        loops
+    }
+}
@@ -0,0 +1,108 @@
+// Copyright (C) 2025 Zihan Li and Ethan Uppal.
+use bril::builder::BasicBlockIdx;
+use fixedbitset::{Block, FixedBitSet};
+use slotmap::SecondaryMap;
+
+use crate::Direction;
+
+/// A dataflow problem whose facts are merged by union and transferred
+/// through every block as `(in − kill) ∪ gen`. The gen and kill sets of all
+/// blocks are stored back to back in one arena of words, so the transfer is
+/// a single pass over contiguous memory.
+pub struct GenKill {
+    direction: Direction,
+    /// length of every set, in bits
+    bits: usize,
+    /// length of every set, in words
+    words: usize,
+    /// offset of the gen set of every block in `sets`, followed by its kill
+    /// set
+    offsets: SecondaryMap<BasicBlockIdx, usize>,
+    sets: Vec<Block>,
+}
+
+impl GenKill {
+    /// Packs the `gen` and `kill` sets of every block, which may have any
+    /// length. Blocks missing from `kill` kill nothing.
+    pub fn new(
+        direction: Direction,
+        gen_sets: &SecondaryMap<BasicBlockIdx, FixedBitSet>,
+        kill_sets: &SecondaryMap<BasicBlockIdx, FixedBitSet>,
+    ) -> Self {
+        let bits = gen_sets
+            .values()
+            .chain(kill_sets.values())
+            .map(FixedBitSet::len)
+            .max()
+            .unwrap_or(0);
+        let words = bits.div_ceil(Block::BITS as usize);
+        let mut offsets = SecondaryMap::with_capacity(gen_sets.len());
+        let mut sets = vec![0; 2 * words * gen_sets.len()];
+        for (i, (block_idx, gen_set)) in gen_sets.iter().enumerate() {
+            let offset = 2 * words * i;
+            offsets.insert(block_idx, offset);
+            let (gen_words, kill_words) =
+                sets[offset..offset + 2 * words].split_at_mut(words);
+            copy_words(gen_words, gen_set);
+            if let Some(kill_set) = kill_sets.get(block_idx) {
+                copy_words(kill_words, kill_set);
+            }
+        }
+        Self {
+            direction,
+            bits,
+            words,
+            offsets,
+            sets,
+        }
+    }
+
+    pub fn direction(&self) -> Direction {
+        self.direction
+    }
+
+    /// Length of every fact, in bits.
+    pub fn bits(&self) -> usize {
+        self.bits
+    }
+
+    /// Length of every fact, in words.
+    pub fn words(&self) -> usize {
+        self.words
+    }
+
+    /// Writes `(input − kill) ∪ gen` of `block_idx` to `output`, returning
+    /// whether `output` changed. Both slices are [`GenKill::words`] long.
+    pub fn transfer_into(
+        &self,
+        block_idx: BasicBlockIdx,
+        input: &[Block],
+        output: &mut [Block],
+    ) -> bool {
+        let offset = self.offsets[block_idx];
+        let (gen_words, kill_words) =
+            self.sets[offset..offset + 2 * self.words].split_at(self.words);
+        let mut changed = 0;
+        for (((out, &input), &generated), &killed) in
+            output.iter_mut().zip(input).zip(gen_words).zip(kill_words)
+        {
+            let new = (input & !killed) | generated;
+            changed |= *out ^ new;
+            *out = new;
+        }
+        changed != 0
+    }
+
+    /// Fact of the problem holding `words`.
+    pub fn to_bitset(&self, words: &[Block]) -> FixedBitSet {
+        FixedBitSet::with_capacity_and_blocks(self.bits, words.iter().copied())
+    }
+}
+
+/// Copies the words of `set` to the start of `words`, truncating it to their
+/// length.
+pub(crate) fn copy_words(words: &mut [Block], set: &FixedBitSet) {
+    for (word, &set_word) in words.iter_mut().zip(set.as_slice()) {
+        *word = set_word;
+    }
+}
@@ -0,0 +1,145 @@
+// Copyright (C) 2025 Zihan Li and Ethan Uppal.
+
+use bril::{builder::BasicBlockIdx, ir::Instruction};
+use bril_cfg::Cfg;
+use fixedbitset::FixedBitSet;
+use slotmap::SecondaryMap;
+
+use crate::{BlockFacts, Direction};
+
+/// Instruction-level view over a block-level dataflow solution.
+///
+/// Nothing is precomputed: every query replays `transfer` over the
+/// instructions of the queried block, starting from the block boundary the
+/// solver propagated into it. `transfer` receives the offset of the
+/// instruction relative to function's instruction buffer.
+pub struct InstrFacts<'a, 'program, T, D = FixedBitSet>
+where
+    T: Fn(usize, &Instruction, D) -> D,
+{
+    cfg: &'a Cfg<'program>,
+    direction: Direction,
+    blocks: &'a SecondaryMap<BasicBlockIdx, BlockFacts<D>>,
+    transfer: T,
+}
+
+impl<'a, 'program, T, D> InstrFacts<'a, 'program, T, D>
+where
+    T: Fn(usize, &Instruction, D) -> D,
+    D: Clone,
+{
+    pub fn new(
+        cfg: &'a Cfg<'program>,
+        direction: Direction,
+        blocks: &'a SecondaryMap<BasicBlockIdx, BlockFacts<D>>,
+        transfer: T,
+    ) -> Self {
+        Self {
+            cfg,
+            direction,
+            blocks,
+            transfer,
+        }
+    }
+
+    /// The block-level facts this view refines.
+    pub fn block(&self, block_idx: BasicBlockIdx) -> Option<&BlockFacts<D>> {
+        self.blocks.get(block_idx)
+    }
+
+    /// Fact holding right before the `index`-th instruction of `block_idx`,
+    /// or `None` if the block is unreachable.
+    pub fn before(&self, block_idx: BasicBlockIdx, index: usize) -> Option<D> {
+        let len = self.cfg.vertices[block_idx].instructions.len();
+        assert!(index < len, "index out of block");
+        let facts = self.blocks.get(block_idx)?;
+        Some(match self.direction {
+            Direction::Forward => self.replay(block_idx, &facts.before, index),
+            Direction::Backward => {
+                self.replay(block_idx, &facts.after, len - index)
+            }
+        })
+    }
+
+    /// Fact holding right after the `index`-th instruction of `block_idx`,
+    /// or `None` if the block is unreachable.
+    pub fn after(&self, block_idx: BasicBlockIdx, index: usize) -> Option<D> {
+        let len = self.cfg.vertices[block_idx].instructions.len();
+        assert!(index < len, "index out of block");
+        let facts = self.blocks.get(block_idx)?;
+        Some(match self.direction {
+            Direction::Forward => {
+                self.replay(block_idx, &facts.before, index + 1)
+            }
+            Direction::Backward => {
+                self.replay(block_idx, &facts.after, len - index - 1)
+            }
+        })
+    }
+
+    /// Facts at every program point of `block_idx`: the `i`-th element holds
+    /// before the `i`-th instruction, and the last one after the block.
+    pub fn points(&self, block_idx: BasicBlockIdx) -> Option<Vec<D>> {
+        let block = &self.cfg.vertices[block_idx];
+        let facts = self.blocks.get(block_idx)?;
+        let mut points = Vec::with_capacity(block.instructions.len() + 1);
+        match self.direction {
+            Direction::Forward => {
+                let mut fact = facts.before.clone();
+                for (i, instruction) in block.instructions.iter().enumerate() {
+                    points.push(fact.clone());
+                    fact = (self.transfer)(block.offset + i, instruction, fact);
+                }
+                points.push(fact);
+            }
+            Direction::Backward => {
+                let mut fact = facts.after.clone();
+                for (i, instruction) in
+                    block.instructions.iter().enumerate().rev()
+                {
+                    points.push(fact.clone());
+                    fact = (self.transfer)(block.offset + i, instruction, fact);
+                }
+                points.push(fact);
+                points.reverse();
+            }
+        }
+        Some(points)
+    }
+
+    /// Finds the block containing the instruction at `offset` relative to
+    /// function's instruction buffer, along with its index in the block.
+    pub fn locate(&self, offset: usize) -> Option<(BasicBlockIdx, usize)> {
+        self.cfg.vertices.iter().find_map(|(block_idx, block)| {
+            (block.offset..block.offset + block.instructions.len())
+                .contains(&offset)
+                .then(|| (block_idx, offset - block.offset))
+        })
+    }
+
+    /// Replays `transfer` from `boundary` over `count` instructions of
+    /// `block_idx`, walking in the solver's direction.
+    fn replay(
+        &self,
+        block_idx: BasicBlockIdx,
+        boundary: &D,
+        count: usize,
+    ) -> D {
+        let block = &self.cfg.vertices[block_idx];
+        let instructions = block.instructions.iter().enumerate();
+        let mut fact = boundary.clone();
+        match self.direction {
+            Direction::Forward => {
+                for (i, instruction) in instructions.take(count) {
+                    fact = (self.transfer)(block.offset + i, instruction, fact);
+                }
+            }
+            Direction::Backward => {
+                for (i, instruction) in instructions.rev().take(count) {
+                    fact = (self.transfer)(block.offset + i, instruction, fact);
+                }
+            }
+        }
+        fact
+    }
+}
@@ -0,0 +1,102 @@
+// Copyright (C) 2025 Zihan Li and Ethan Uppal.
+use bril::ir::Instruction;
+use bril_cfg::Cfg;
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::analysis::{InstructionExt, liveness, liveness_instr};
+
+/// Variables of a function that cannot share a register, because one is
+/// defined while the other is live. Variables are keyed by their numbering.
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct InterferenceGraph {
+    adjacency: BTreeMap<u32, BTreeSet<u32>>,
+    /// copies `dest = id src` of the reachable blocks, as `(dest, src)`,
+    /// which do not make their variables interfere by themselves
+    pub moves: Vec<(u32, u32)>,
+}
+
+impl InterferenceGraph {
+    /// Builds the graph from the instruction-level liveness of the blocks
+    /// reachable from the entry. Parameters are defined together when the
+    /// function is entered, so they all interfere with each other.
+    pub fn new(cfg: &Cfg) -> Self {
+        let blocks = liveness(cfg);
+        let instr_facts = liveness_instr(cfg, &blocks);
+        let mut graph = Self::default();
+
+        let parameters = &cfg.prototype.parameters;
+        for parameter in parameters {
+            graph.add_node(parameter.0);
+            for other in parameters {
+                graph.add_edge(parameter.0, other.0);
+            }
+            for live in blocks[cfg.entry].before.ones() {
+                graph.add_edge(parameter.0, live as u32);
+            }
+        }
+
+        for (block_idx, block) in &cfg.vertices {
+            let Some(points) = instr_facts.points(block_idx) else {
+                continue;
+            };
+            for (i, instruction) in block.instructions.iter().enumerate() {
+                for operand in instruction.operands() {
+                    graph.add_node(operand.0);
+                }
+                let Some(dest) = instruction.dest() else {
+                    continue;
+                };
+                graph.add_node(dest.0);
+                let copied = match instruction {
+                    Instruction::Id(_, src) => {
+                        graph.moves.push((dest.0, src.0));
+                        Some(src.0)
+                    }
+                    _ => None,
+                };
+                for live in points[i + 1].ones() {
+                    if Some(live as u32) != copied {
+                        graph.add_edge(dest.0, live as u32);
+                    }
+                }
+            }
+        }
+        graph
+    }
+
+    pub fn add_node(&mut self, variable: u32) {
+        self.adjacency.entry(variable).or_default();
+    }
+
+    /// Makes `lhs` and `rhs` interfere. Variables never interfere with
+    /// themselves.
+    pub fn add_edge(&mut self, lhs: u32, rhs: u32) {
+        if lhs == rhs {
+            return;
+        }
+        self.adjacency.entry(lhs).or_default().insert(rhs);
+        self.adjacency.entry(rhs).or_default().insert(lhs);
+    }
+
+    /// Variables in ascending order.
+    pub fn nodes(&self) -> impl Iterator<Item = u32> + '_ {
+        self.adjacency.keys().copied()
+    }
+
+    pub fn neighbors(&self, variable: u32) -> impl Iterator<Item = u32> + '_ {
+        self.adjacency
+            .get(&variable)
+            .into_iter()
+            .flat_map(|neighbors| neighbors.iter().copied())
+    }
+
+    pub fn degree(&self, variable: u32) -> usize {
+        self.adjacency.get(&variable).map_or(0, BTreeSet::len)
+    }
+
+    pub fn interferes(&self, lhs: u32, rhs: u32) -> bool {
+        self.adjacency
+            .get(&lhs)
+            .is_some_and(|neighbors| neighbors.contains(&rhs))
+    }
+}
@@ -0,0 +1,251 @@
+// Copyright (C) 2025 Zihan Li and Ethan Uppal.
+use bril::ir::{FunctionIdx, Instruction, Program, Value};
+use bril_cfg::build_cfg;
+use rayon::prelude::*;
+use std::collections::BTreeMap;
+
+use crate::{
+    analysis::{Constants, const_prop_with},
+    call_graph::CallGraph,
+};
+
+/// Value the returns of a function agree on.
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub enum ReturnValue {
+    /// no execution returns a value
+    #[default]
+    None,
+    Constant(Value),
+    Varying,
+}
+
+impl ReturnValue {
+    pub fn constant(&self) -> Option<&Value> {
+        match self {
+            Self::Constant(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn join(self, other: &Self) -> Self {
+        match (self, other) {
+            (Self::None, other) => other.clone(),
+            (this, Self::None) => this,
+            (Self::Constant(value), Self::Constant(other_value))
+                if value == *other_value =>
+            {
+                Self::Constant(value)
+            }
+            _ => Self::Varying,
+        }
+    }
+}
+
+/// Solution of [`interprocedural_const_prop`].
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct InterproceduralConstants {
+    /// constants the parameters of every function hold on entry, joined
+    /// over the reachable call sites. Functions never called are
+    /// [`Constants::Unreachable`].
+    pub entries: Vec<Constants>,
+    pub returns: Vec<ReturnValue>,
+}
+
+impl InterproceduralConstants {
+    pub fn entry(&self, function: FunctionIdx) -> &Constants {
+        &self.entries[function.0 as usize]
+    }
+
+    pub fn return_value(&self, function: FunctionIdx) -> &ReturnValue {
+        &self.returns[function.0 as usize]
+    }
+
+    /// Functions always called with the same constant for some of their
+    /// parameters, with the positions and values of these parameters.
+    pub fn constant_arguments(
+        &self,
+        program: &Program,
+    ) -> Vec<(FunctionIdx, Vec<(usize, Value)>)> {
+        program
+            .functions()
+            .enumerate()
+            .filter_map(|(idx, function)| {
+                let function_idx = FunctionIdx(idx as u32);
+                let entry = self.entry(function_idx);
+                let arguments: Vec<_> = function
+                    .parameters
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(i, parameter)| {
+                        entry.get(*parameter).map(|value| (i, value.clone()))
+                    })
+                    .collect();
+                (!arguments.is_empty()).then_some((function_idx, arguments))
+            })
+            .collect()
+    }
+}
+
+/// Context-insensitive constant propagation across calls: the constant
+/// arguments of the reachable call sites flow into the parameters of the
+/// callee, and the constant return value of the callee flows back into the
+/// result of the call. `main`, or every function if there is none, may be
+/// called with anything.
+pub fn interprocedural_const_prop(
+    program: &Program,
+    call_graph: &CallGraph,
+) -> InterproceduralConstants {
+    solve(program, call_graph, None)
+}
+
+/// Same as [`interprocedural_const_prop`], analyzing the independent
+/// components of every level of [`CallGraph::bottom_up_levels`] in parallel.
+pub fn interprocedural_const_prop_para(
+    program: &Program,
+    call_graph: &CallGraph,
+    num_threads: usize,
+) -> InterproceduralConstants {
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(num_threads)
+        .build()
+        .unwrap();
+    solve(program, call_graph, Some(&pool))
+}
+
+/// What analyzing a function with the current solution found out.
+struct FunctionResult {
+    function_idx: FunctionIdx,
+    returned: ReturnValue,
+    /// constants on entry to the callee of every reachable call site
+    calls: Vec<(FunctionIdx, Constants)>,
+}
+
+/// Analyzes the levels of the call graph top-down, so that arguments reach
+/// the callees in the same round, until the solution stops changing. It
+/// starts from no function being called nor returning, and only grows.
+fn solve(
+    program: &Program,
+    call_graph: &CallGraph,
+    pool: Option<&rayon::ThreadPool>,
+) -> InterproceduralConstants {
+    let num_functions = program.functions().count();
+    let has_main = program.functions().any(|function| function.name == "main");
+    let mut solution = InterproceduralConstants {
+        entries: program
+            .functions()
+            .map(|function| {
+                if !has_main || function.name == "main" {
+                    Constants::Reachable(BTreeMap::new())
+                } else {
+                    Constants::Unreachable
+                }
+            })
+            .collect(),
+        returns: vec![ReturnValue::None; num_functions],
+    };
+
+    let levels = call_graph.bottom_up_levels();
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for level in levels.iter().rev() {
+            let functions: Vec<_> = level
+                .iter()
+                .flat_map(|&comp_idx| {
+                    call_graph.components()[comp_idx].vertices.iter().copied()
+                })
+                .filter(|&function_idx| {
+                    solution.entry(function_idx).is_reachable()
+                })
+                .collect();
+            let results: Vec<_> = match pool {
+                Some(pool) => pool.install(|| {
+                    functions
+                        .par_iter()
+                        .map(|&function_idx| {
+                            analyze(program, &solution, function_idx)
+                        })
+                        .collect()
+                }),
+                None => functions
+                    .iter()
+                    .map(|&function_idx| {
+                        analyze(program, &solution, function_idx)
+                    })
+                    .collect(),
+            };
+
+            for result in results {
+                let returned =
+                    &mut solution.returns[result.function_idx.0 as usize];
+                let joined = returned.clone().join(&result.returned);
+                if joined != *returned {
+                    *returned = joined;
+                    changed = true;
+                }
+                for (callee, constants) in result.calls {
+                    let entry = &mut solution.entries[callee.0 as usize];
+                    let joined = entry.clone().join(&constants);
+                    if joined != *entry {
+                        *entry = joined;
+                        changed = true;
+                    }
+                }
+            }
+        }
+    }
+    solution
+}
+
+fn analyze(
+    program: &Program,
+    solution: &InterproceduralConstants,
+    function_idx: FunctionIdx,
+) -> FunctionResult {
+    let returns =
+        |callee: FunctionIdx| solution.return_value(callee).constant().cloned();
+    let function = program.get_function(function_idx);
+    let cfg = build_cfg(&function);
+    let facts =
+        const_prop_with(&cfg, solution.entry(function_idx).clone(), returns);
+
+    let mut result = FunctionResult {
+        function_idx,
+        returned: ReturnValue::None,
+        calls: vec![],
+    };
+    for (block_idx, block) in &cfg.vertices {
+        let Some(facts) = facts.get(block_idx) else {
+            continue;
+        };
+        let mut constants = facts.before.clone();
+        for instruction in block.instructions {
+            if !constants.is_reachable() {
+                break;
+            }
+            match instruction {
+                Instruction::Call(_, callee, args) => {
+                    let parameters = program.get_function(*callee).parameters;
+                    let values = parameters
+                        .iter()
+                        .zip(args)
+                        .filter_map(|(parameter, arg)| {
+                            Some((parameter.0, constants.get(*arg)?.clone()))
+                        })
+                        .collect();
+                    result.calls.push((*callee, Constants::Reachable(values)));
+                }
+                Instruction::Ret(Some(value)) => {
+                    let returned = match constants.get(*value) {
+                        Some(value) => ReturnValue::Constant(value.clone()),
+                        None => ReturnValue::Varying,
+                    };
+                    result.returned = result.returned.join(&returned);
+                }
+                _ => {}
+            }
+            constants = constants.transfer_with(instruction, returns);
+        }
+    }
+    result
+}
@@ -1,11 +1,24 @@
 // Copyright (C) 2025 Zihan Li and Ethan Uppal.
 pub mod analysis;
+pub mod bitset;
+pub mod call_graph;
+pub mod dominators;
+pub mod gen_kill;
+pub mod instr;
+pub mod interference;
+pub mod interprocedural_const;
 pub mod scc;
+pub mod summary;
 
 use bril::builder::BasicBlockIdx;
 use bril_cfg::Cfg;
+use fixedbitset::FixedBitSet;
 use scc::{Component, CondensedCfg};
 use slotmap::SecondaryMap;
+use std::{
+    collections::{HashMap, HashSet},
+    time::Duration,
+};
 
 pub mod parallel;
 pub mod sequential;
@@ -16,6 +29,152 @@ pub enum Direction {
     Backward,
 }
 
+/// Dataflow facts at both boundaries of a basic block, independent of the
+/// direction the problem is solved in.
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct BlockFacts<D = FixedBitSet> {
+    /// fact holding before the first instruction of the block
+    pub before: D,
+    /// fact holding after the last instruction of the block
+    pub after: D,
+}
+
+impl<D> BlockFacts<D> {
+    /// `input` is the merged fact flowing into the block and `output` is the
+    /// result of transferring it through the block, both in `direction`.
+    pub fn new(direction: Direction, input: D, output: D) -> Self {
+        match direction {
+            Direction::Forward => Self {
+                before: input,
+                after: output,
+            },
+            Direction::Backward => Self {
+                before: output,
+                after: input,
+            },
+        }
+    }
+
+    /// The merged fact flowing into the block in `direction`.
+    pub fn input(&self, direction: Direction) -> &D {
+        match direction {
+            Direction::Forward => &self.before,
+            Direction::Backward => &self.after,
+        }
+    }
+
+    /// The fact the block propagates to its neighbors in `direction`.
+    pub fn output(&self, direction: Direction) -> &D {
+        match direction {
+            Direction::Forward => &self.after,
+            Direction::Backward => &self.before,
+        }
+    }
+}
+
+/// Order in which the solvers take blocks off their worklist.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub enum Worklist {
+    /// first in, first out, starting in reverse postorder. A block may be
+    /// queued several times.
+    #[default]
+    Fifo,
+    /// last in, first out. A block may be queued several times.
+    Lifo,
+    /// always the queued block that comes first in reverse postorder, with
+    /// every block queued at most once
+    Priority,
+    /// sweeps over all blocks in reverse postorder until a sweep changes
+    /// nothing
+    RoundRobin,
+}
+
+/// How much work solving a problem took.
+#[derive(Debug, Default, Clone)]
+pub struct SolveStats {
+    /// blocks taken off the worklist
+    pub iterations: usize,
+    /// calls to the transfer function, including those of narrowing
+    pub transfers: usize,
+    /// count of blocks and time spent solving every component
+    pub component_timings: Vec<(usize, Duration)>,
+}
+
+impl SolveStats {
+    /// Adds the work of `other`, solving another part of the same problem.
+    pub fn absorb(&mut self, other: SolveStats) {
+        self.iterations += other.iterations;
+        self.transfers += other.transfers;
+        self.component_timings.extend(other.component_timings);
+    }
+}
+
+/// Widening and narrowing operators for lattices with infinite ascending
+/// chains. Both take the previous input of a block and the newly merged one.
+pub struct Widening<'w, D> {
+    pub widen: &'w (dyn Fn(&D, D) -> D + Sync),
+    pub narrow: &'w (dyn Fn(&D, D) -> D + Sync),
+    /// upper bound on the number of descending passes run after the
+    /// ascending phase reaches a fixpoint
+    pub narrowing_passes: usize,
+}
+
+impl<D> Clone for Widening<'_, D> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<D> Copy for Widening<'_, D> {}
+
+/// Solution of a problem before the transfer of the `changed` blocks was
+/// changed, on a CFG with the same blocks and edges. Its facts have to mean
+/// the same in the changed CFG, e.g. instruction offsets may not move.
+pub struct Previous<'p, D> {
+    pub solution: SecondaryMap<BasicBlockIdx, BlockFacts<D>>,
+    pub changed: &'p HashSet<BasicBlockIdx>,
+}
+
+/// How the solvers solve a problem, beyond its merge and transfer
+/// functions. The default solves every block from scratch with a
+/// [`Worklist::Fifo`].
+pub struct SolveOptions<'o, D = FixedBitSet> {
+    pub worklist: Worklist,
+    /// refine the facts flowing along some edges before they are merged
+    pub edge_transfers: Option<&'o EdgeTransfers<'o, D>>,
+    /// widen the inputs of blocks closing a cycle, so solving also
+    /// terminates on lattices of infinite height, and narrow the resulting
+    /// post-fixpoint afterwards
+    pub widening: Option<Widening<'o, D>>,
+    /// only solve the changed blocks and the blocks their facts flow to
+    /// again, starting over from the default fact, while the others keep
+    /// their previous facts
+    pub previous: Option<Previous<'o, D>>,
+    /// receives how much work solving took, which is only measured when
+    /// asked for
+    pub stats: Option<&'o mut SolveStats>,
+}
+
+impl<D> Default for SolveOptions<'_, D> {
+    fn default() -> Self {
+        Self {
+            worklist: Worklist::default(),
+            edge_transfers: None,
+            widening: None,
+            previous: None,
+            stats: None,
+        }
+    }
+}
+
+/// Transfer functions refining the facts flowing along particular edges,
+/// keyed by `(from, to)` in the direction the problem is solved in. Edges
+/// without an entry pass facts through unchanged.
+pub type EdgeTransfers<'e, D = FixedBitSet> = HashMap<
+    (BasicBlockIdx, BasicBlockIdx),
+    Box<dyn Fn(&D) -> D + Send + Sync + 'e>,
+>;
+
 pub trait TraverseCfgLike<'a> {
     type Context;
 
@@ -132,3 +291,43 @@ fn construct_postorder<'a, C: TraverseCfgLike<'a>>(
     );
     traversal
 }
+
+/// Targets of the back edges found by a depth-first search from the entry.
+/// Every cycle passes through at least one of them, so they are where
+/// widening has to happen for the solver to terminate.
+fn find_back_edge_targets<'a, C: TraverseCfgLike<'a>>(
+    cfg_like: &C,
+    context: &C::Context,
+) -> HashSet<BasicBlockIdx> {
+    fn helper<'a, C: TraverseCfgLike<'a>>(
+        cfg_like: &C,
+        context: &C::Context,
+        current: BasicBlockIdx,
+        on_stack: &mut SecondaryMap<BasicBlockIdx, bool>,
+        targets: &mut HashSet<BasicBlockIdx>,
+    ) {
+        on_stack.insert(current, true);
+        for successor in cfg_like.successors(context, current) {
+            match on_stack.get(successor) {
+                Some(true) => {
+                    targets.insert(successor);
+                }
+                Some(false) => {}
+                None => helper(cfg_like, context, successor, on_stack, targets),
+            }
+        }
+        on_stack[current] = false;
+    }
+
+    let mut targets = HashSet::new();
+    let mut on_stack =
+        SecondaryMap::with_capacity(cfg_like.vertices_capacity());
+    helper(
+        cfg_like,
+        context,
+        cfg_like.entry(),
+        &mut on_stack,
+        &mut targets,
+    );
+    targets
+}
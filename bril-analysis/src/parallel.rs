@@ -1,67 +1,251 @@
 // Copyright (C) 2025 Zihan Li and Ethan Uppal.
 
-use dashmap::DashMap;
 use fixedbitset::FixedBitSet;
 use rayon::Scope;
-use std::collections::{HashMap, HashSet, VecDeque};
+use slotmap::SecondaryMap;
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{
+        Mutex, OnceLock,
+        atomic::{AtomicUsize, Ordering},
+    },
+};
 
 use bril::builder::BasicBlockIdx;
 use bril_cfg::Cfg;
 
 use crate::{
-    Direction,
-    scc::{ComponentIdx, CondensedCfg},
+    BlockFacts, Direction, EdgeTransfers, Previous, SolveOptions, SolveStats,
+    Widening, Worklist,
+    gen_kill::GenKill,
+    scc::{Component, ComponentIdx, CondensedCfg},
     sequential,
 };
 
-pub fn solve_dataflow(
+/// Solves the problem over the blocks reachable from the entry of `cfg`,
+/// solving independent strongly connected components in parallel. Facts
+/// flowing into the entry component start out as `entry_inputs`.
+pub fn solve_dataflow<D: Clone + PartialEq + Default + Send + Sync>(
     cfg: &Cfg,
     direction: Direction,
-    entry_inputs: FixedBitSet,
-    merge: impl Fn(FixedBitSet, &FixedBitSet) -> FixedBitSet + Sync,
-    transfer: impl Fn(BasicBlockIdx, FixedBitSet) -> FixedBitSet + Sync,
+    entry_inputs: D,
+    merge: impl Fn(D, &D) -> D + Sync,
+    transfer: impl Fn(BasicBlockIdx, D) -> D + Sync,
     threads: usize,
-) -> DashMap<BasicBlockIdx, FixedBitSet> {
-    let solver = ParallelSolver {
-        condensed_cfg: CondensedCfg::from_cfg(cfg),
-        pool: rayon::ThreadPoolBuilder::new()
-            .num_threads(threads)
-            .build()
-            .unwrap(),
-        entry_inputs,
+    options: SolveOptions<D>,
+) -> SecondaryMap<BasicBlockIdx, BlockFacts<D>> {
+    let SolveOptions {
+        worklist,
+        edge_transfers,
+        widening,
+        previous,
+        stats,
+    } = options;
+    let no_edge_transfers = EdgeTransfers::new();
+    let edge_transfers = edge_transfers.unwrap_or(&no_edge_transfers);
+    ParallelSolver::new(
+        cfg,
         direction,
-        merge,
-        transfer,
-        solution: DashMap::new(),
-    };
+        entry_inputs,
+        &merge,
+        Sequential {
+            direction,
+            merge: &merge,
+            transfer,
+            edge_transfers,
+            worklist,
+            widening,
+        },
+        edge_transfers,
+        threads,
+    )
+    .run(previous, stats)
+}
 
-    solver.solve();
-    solver.solution
+/// Same as [`sequential::solve_gen_kill`], solving the components of `cfg`
+/// in parallel from an empty boundary.
+pub fn solve_gen_kill(
+    cfg: &Cfg,
+    problem: &GenKill,
+    threads: usize,
+    options: SolveOptions,
+) -> SecondaryMap<BasicBlockIdx, BlockFacts> {
+    let SolveOptions {
+        worklist,
+        edge_transfers,
+        widening,
+        previous,
+        stats,
+    } = options;
+    assert!(
+        edge_transfers.is_none() && widening.is_none(),
+        "gen/kill facts only flow through the block transfer"
+    );
+    let edge_transfers = EdgeTransfers::new();
+    ParallelSolver::new(
+        cfg,
+        problem.direction(),
+        FixedBitSet::new(),
+        |mut in1: FixedBitSet, in2: &FixedBitSet| {
+            in1.union_with(in2);
+            in1
+        },
+        GenKillComponents { problem, worklist },
+        &edge_transfers,
+        threads,
+    )
+    .run(previous, stats)
+}
+
+/// How [`ParallelSolver`] solves a component, given the inputs flowing into
+/// it from the components it depends on, adding its work to `stats` if
+/// asked for.
+trait ComponentSolver<D>: Sync {
+    fn solve_component(
+        &self,
+        component: &Component,
+        condensed_cfg: &CondensedCfg,
+        entry_inputs: HashMap<BasicBlockIdx, D>,
+        stats: Option<&mut SolveStats>,
+    ) -> SecondaryMap<BasicBlockIdx, BlockFacts<D>>;
+}
+
+/// Solves components with [`sequential::solve_dataflow`].
+struct Sequential<'o, 'f, M, T, D> {
+    direction: Direction,
+    merge: &'f M,
+    transfer: T,
+    edge_transfers: &'o EdgeTransfers<'o, D>,
+    worklist: Worklist,
+    widening: Option<Widening<'o, D>>,
+}
+
+impl<D, M, T> ComponentSolver<D> for Sequential<'_, '_, M, T, D>
+where
+    D: Clone + PartialEq + Default + Send + Sync,
+    M: Fn(D, &D) -> D + Sync,
+    T: Fn(BasicBlockIdx, D) -> D + Sync,
+{
+    fn solve_component(
+        &self,
+        component: &Component,
+        condensed_cfg: &CondensedCfg,
+        entry_inputs: HashMap<BasicBlockIdx, D>,
+        stats: Option<&mut SolveStats>,
+    ) -> SecondaryMap<BasicBlockIdx, BlockFacts<D>> {
+        sequential::solve_dataflow(
+            component,
+            condensed_cfg,
+            self.direction,
+            entry_inputs,
+            self.merge,
+            &self.transfer,
+            SolveOptions {
+                worklist: self.worklist,
+                edge_transfers: Some(self.edge_transfers),
+                widening: self.widening,
+                previous: None,
+                stats,
+            },
+        )
+    }
+}
+
+/// Solves components with [`sequential::solve_gen_kill`].
+struct GenKillComponents<'p> {
+    problem: &'p GenKill,
+    worklist: Worklist,
+}
+
+impl ComponentSolver<FixedBitSet> for GenKillComponents<'_> {
+    fn solve_component(
+        &self,
+        component: &Component,
+        condensed_cfg: &CondensedCfg,
+        entry_inputs: HashMap<BasicBlockIdx, FixedBitSet>,
+        stats: Option<&mut SolveStats>,
+    ) -> SecondaryMap<BasicBlockIdx, BlockFacts> {
+        sequential::solve_gen_kill(
+            component,
+            condensed_cfg,
+            self.problem,
+            &entry_inputs,
+            SolveOptions {
+                worklist: self.worklist,
+                stats,
+                ..Default::default()
+            },
+        )
+    }
+}
+
+impl<'cfg, 'e, D, M, S> ParallelSolver<'cfg, 'e, D, M, S>
+where
+    D: Clone + PartialEq + Default + Send + Sync,
+    M: Fn(D, &D) -> D + Sync,
+    S: ComponentSolver<D>,
+{
+    fn new(
+        cfg: &'cfg Cfg,
+        direction: Direction,
+        entry_inputs: D,
+        merge: M,
+        component_solver: S,
+        edge_transfers: &'e EdgeTransfers<'e, D>,
+        threads: usize,
+    ) -> Self {
+        Self {
+            condensed_cfg: CondensedCfg::from_cfg(cfg),
+            pool: rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build()
+                .unwrap(),
+            entry_inputs,
+            direction,
+            merge,
+            component_solver,
+            edge_transfers,
+            solution: cfg
+                .vertices
+                .keys()
+                .map(|block_idx| (block_idx, OnceLock::new()))
+                .collect(),
+            stats: None,
+        }
+    }
 }
 
-struct ParallelSolver<'cfg, M, T>
+struct ParallelSolver<'cfg, 'e, D, M, S>
 where
-    M: Fn(FixedBitSet, &FixedBitSet) -> FixedBitSet + Sync,
-    T: Fn(BasicBlockIdx, FixedBitSet) -> FixedBitSet + Sync,
+    D: Clone + PartialEq + Default + Send + Sync,
+    M: Fn(D, &D) -> D + Sync,
+    S: ComponentSolver<D>,
 {
     condensed_cfg: CondensedCfg<'cfg, 'cfg>,
     pool: rayon::ThreadPool,
-    entry_inputs: FixedBitSet,
+    entry_inputs: D,
     direction: Direction,
+    /// merges the inputs flowing into a component
     merge: M,
-    transfer: T,
-    solution: DashMap<BasicBlockIdx, FixedBitSet>,
+    component_solver: S,
+    edge_transfers: &'e EdgeTransfers<'e, D>,
+    /// facts of every block, set once by the worker solving its component
+    /// and only read by the components depending on it after that
+    solution: SecondaryMap<BasicBlockIdx, OnceLock<BlockFacts<D>>>,
+    /// work done by all workers, only collected when asked for
+    stats: Option<Mutex<SolveStats>>,
 }
 
-impl<M, T> ParallelSolver<'_, M, T>
+impl<D, M, S> ParallelSolver<'_, '_, D, M, S>
 where
-    M: Fn(FixedBitSet, &FixedBitSet) -> FixedBitSet + Sync,
-    T: Fn(BasicBlockIdx, FixedBitSet) -> FixedBitSet + Sync,
+    D: Clone + PartialEq + Default + Send + Sync,
+    M: Fn(D, &D) -> D + Sync,
+    S: ComponentSolver<D>,
 {
     fn component_entry_inputs(
         &self,
         component_idx: ComponentIdx,
-    ) -> HashMap<BasicBlockIdx, FixedBitSet> {
+    ) -> HashMap<BasicBlockIdx, D> {
         let component = &self.condensed_cfg.components[component_idx];
         let entries = match self.direction {
             Direction::Forward => vec![component.entry],
@@ -74,28 +258,34 @@ where
                 }),
             ),
         };
-        let mut entry_inputs: HashMap<BasicBlockIdx, FixedBitSet> =
-            HashMap::new();
+        let mut entry_inputs: HashMap<BasicBlockIdx, D> = HashMap::new();
         for entry in entries {
             let predecessors: Vec<_> = match self.direction {
                 Direction::Forward => self
                     .condensed_cfg
                     .cfg
-                    .predecessors(component.entry)
+                    .predecessors(entry)
                     .into_iter()
                     .filter(|&pred| !component.contains(pred))
                     .collect(),
                 Direction::Backward => self
                     .condensed_cfg
                     .cfg
-                    .successors(component.entry)
+                    .successors(entry)
                     .into_iter()
                     .filter(|&succ| !component.contains(succ))
                     .collect(),
             };
             let input = predecessors
                 .iter()
-                .filter_map(|pred| self.solution.get(pred).map(|v| v.clone()))
+                .filter_map(|pred| {
+                    let facts = self.solution.get(*pred)?.get()?;
+                    let output = facts.output(self.direction);
+                    Some(match self.edge_transfers.get(&(*pred, entry)) {
+                        Some(edge_transfer) => edge_transfer(output),
+                        None => output.clone(),
+                    })
+                })
                 .reduce(|in1, in2| (self.merge)(in1, &in2))
                 .unwrap_or(self.entry_inputs.clone());
             entry_inputs.insert(entry, input);
@@ -107,36 +297,111 @@ where
         &'a self,
         scope: &Scope<'scope>,
         current: ComponentIdx,
-        dependencies_left: &'scope DashMap<ComponentIdx, usize>,
+        dependencies_left: &'scope SecondaryMap<ComponentIdx, AtomicUsize>,
     ) {
         // sequential dataflow
-        let partial_solution = sequential::solve_dataflow(
+        let mut stats = self.stats.as_ref().map(|_| SolveStats::default());
+        let partial_solution = self.component_solver.solve_component(
             &self.condensed_cfg.components[current],
             &self.condensed_cfg,
-            self.direction,
             self.component_entry_inputs(current),
-            &self.merge,
-            &self.transfer,
+            stats.as_mut(),
         );
+        if let (Some(total), Some(stats)) = (&self.stats, stats) {
+            total.lock().unwrap().absorb(stats);
+        }
 
-        for (block_idx, v) in partial_solution {
-            self.solution.insert(block_idx, v);
+        // no other worker solves the blocks of this component
+        for (block_idx, facts) in partial_solution {
+            let set = self.solution[block_idx].set(facts);
+            assert!(set.is_ok(), "block solved twice");
         }
-        let dependents = match self.direction {
-            Direction::Forward => self.condensed_cfg.successors(current),
-            Direction::Backward => self.condensed_cfg.predecessors(current),
+        for dependent in self.dependents(current) {
+            if let Some(remaining) = dependencies_left.get(dependent)
+                && remaining.fetch_sub(1, Ordering::AcqRel) == 1
+            {
+                scope.spawn(move |scope| {
+                    self.worker(scope, dependent, dependencies_left);
+                });
+            }
+        }
+    }
+
+    /// Solves every component, or with `previous` only the components
+    /// its changes reach, adding the work of all workers to `stats`.
+    fn run(
+        mut self,
+        previous: Option<Previous<D>>,
+        stats: Option<&mut SolveStats>,
+    ) -> SecondaryMap<BasicBlockIdx, BlockFacts<D>> {
+        if stats.is_some() {
+            self.stats = Some(Mutex::default());
+        }
+        let scheduled = match previous {
+            Some(previous) => self.restore(previous),
+            None => self.condensed_cfg.components.keys().collect(),
         };
-        for dependent in dependents {
-            let mut remaining = dependencies_left.entry(dependent).or_default();
-            if *remaining > 0 {
-                *remaining -= 1;
-                if *remaining == 0 {
-                    scope.spawn(move |scope| {
-                        self.worker(scope, dependent, dependencies_left);
-                    });
+        self.solve(&scheduled);
+        if let (Some(total), Some(stats)) = (stats, self.stats.take()) {
+            total.absorb(stats.into_inner().unwrap());
+        }
+        self.into_solution()
+    }
+
+    /// Keeps the facts of `previous` for the components its changes cannot
+    /// reach, returning the components that have to be solved again: those
+    /// containing changed blocks and the components their facts flow to.
+    fn restore(&mut self, previous: Previous<D>) -> HashSet<ComponentIdx> {
+        let mut affected: HashSet<_> = self
+            .condensed_cfg
+            .components
+            .iter()
+            .filter(|(_, component)| {
+                component
+                    .vertices
+                    .iter()
+                    .any(|block_idx| previous.changed.contains(block_idx))
+            })
+            .map(|(comp_idx, _)| comp_idx)
+            .collect();
+        let mut stack = Vec::from_iter(affected.iter().copied());
+        while let Some(current) = stack.pop() {
+            for dependent in self.dependents(current) {
+                if affected.insert(dependent) {
+                    stack.push(dependent);
                 }
             }
         }
+        let affected_blocks: HashSet<_> = affected
+            .iter()
+            .flat_map(|comp_idx| {
+                &self.condensed_cfg.components[*comp_idx].vertices
+            })
+            .copied()
+            .collect();
+        for (block_idx, facts) in previous.solution {
+            if !affected_blocks.contains(&block_idx) {
+                self.solution.insert(block_idx, OnceLock::from(facts));
+            }
+        }
+        affected
+    }
+
+    fn into_solution(self) -> SecondaryMap<BasicBlockIdx, BlockFacts<D>> {
+        self.solution
+            .into_iter()
+            .filter_map(|(block_idx, facts)| {
+                Some((block_idx, facts.into_inner()?))
+            })
+            .collect()
+    }
+
+    /// Components the facts of `current` flow to.
+    fn dependents(&self, current: ComponentIdx) -> Vec<ComponentIdx> {
+        match self.direction {
+            Direction::Forward => self.condensed_cfg.successors(current),
+            Direction::Backward => self.condensed_cfg.predecessors(current),
+        }
     }
 
     fn dependencies(&self, current: ComponentIdx) -> Vec<ComponentIdx> {
@@ -156,38 +421,23 @@ where
         }
     }
 
-    fn solve(&self) {
-        let dependencies_left = DashMap::new();
-        for component_idx in self.condensed_cfg.components.keys() {
-            dependencies_left
-                .insert(component_idx, self.dependencies(component_idx).len());
-        }
-
-        let mut starting_set = HashSet::new();
-        match self.direction {
-            Direction::Forward => {
-                starting_set.insert(self.condensed_cfg.entry);
-            }
-            Direction::Backward => {
-                let mut bfs = VecDeque::from_iter([self.condensed_cfg.entry]);
-                let mut frontier = HashSet::new();
-                while let Some(next) = bfs.pop_front() {
-                    let neighbors = self
-                        .condensed_cfg
-                        .edges
-                        .get(next)
-                        .cloned()
-                        .unwrap_or_default();
-                    if neighbors.is_empty() {
-                        frontier.insert(next);
-                    } else {
-                        for neighbor in neighbors {
-                            bfs.push_back(neighbor);
-                        }
-                    }
-                }
-                starting_set = frontier;
+    /// Solves the `scheduled` components, given the solution of the other
+    /// components. Facts only flow from a component to its dependents, so
+    /// the scheduled components have to be closed under dependents.
+    fn solve(&self, scheduled: &HashSet<ComponentIdx>) {
+        let mut dependencies_left = SecondaryMap::new();
+        let mut starting_set = vec![];
+        for &component_idx in scheduled {
+            let dependencies = self
+                .dependencies(component_idx)
+                .into_iter()
+                .filter(|dependency| scheduled.contains(dependency))
+                .count();
+            if dependencies == 0 {
+                starting_set.push(component_idx);
             }
+            dependencies_left
+                .insert(component_idx, AtomicUsize::new(dependencies));
         }
 
         let dependencies_left = &dependencies_left;
@@ -1,24 +1,150 @@
 // Copyright (C) 2025 Zihan Li and Ethan Uppal.
 use bril::builder::BasicBlockIdx;
 use bril_cfg::Cfg;
-use slotmap::{SecondaryMap, SlotMap, new_key_type};
-use std::collections::HashSet;
+use slotmap::{Key, SecondaryMap, SlotMap, new_key_type};
+use std::{
+    collections::{HashMap, HashSet},
+    hash::Hash,
+};
 
 new_key_type! { pub struct ComponentIdx; }
 
-pub struct Component {
-    pub vertices: Vec<BasicBlockIdx>,
-    pub entry: BasicBlockIdx,
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Component<V = BasicBlockIdx> {
+    pub vertices: Vec<V>,
+    pub entry: V,
     /// count of back edges within the component
     pub num_back_edges: usize,
 }
 
-impl Component {
-    pub fn contains(&self, query: BasicBlockIdx) -> bool {
+impl<V: PartialEq> Component<V> {
+    pub fn contains(&self, query: V) -> bool {
         self.vertices.iter().any(|idx| query.eq(idx))
     }
 }
 
+/// Map from the vertices of a graph to the numbers [`tarjan`] tracks for
+/// them, so that graphs keyed by a slot map can use a dense
+/// [`SecondaryMap`] instead of hashing.
+pub trait VertexMap<V> {
+    fn with_capacity(capacity: usize) -> Self;
+
+    fn get(&self, vertex: V) -> Option<usize>;
+
+    fn insert(&mut self, vertex: V, value: usize);
+}
+
+impl<V: Eq + Hash> VertexMap<V> for HashMap<V, usize> {
+    fn with_capacity(capacity: usize) -> Self {
+        HashMap::with_capacity(capacity)
+    }
+
+    fn get(&self, vertex: V) -> Option<usize> {
+        HashMap::get(self, &vertex).copied()
+    }
+
+    fn insert(&mut self, vertex: V, value: usize) {
+        HashMap::insert(self, vertex, value);
+    }
+}
+
+impl<K: Key> VertexMap<K> for SecondaryMap<K, usize> {
+    fn with_capacity(capacity: usize) -> Self {
+        SecondaryMap::with_capacity(capacity)
+    }
+
+    fn get(&self, vertex: K) -> Option<usize> {
+        SecondaryMap::get(self, vertex).copied()
+    }
+
+    fn insert(&mut self, vertex: K, value: usize) {
+        SecondaryMap::insert(self, vertex, value);
+    }
+}
+
+/// tarjan algorithm for the strongly connected components of the vertices
+/// reachable from `roots`, tracking them in maps of type `M` sized for
+/// `capacity` vertices. Components are returned in reverse topological
+/// order: every component comes after the components it has edges to.
+pub fn tarjan<V: Copy + Eq + Hash, M: VertexMap<V>>(
+    roots: impl IntoIterator<Item = V>,
+    successors: impl Fn(V) -> Vec<V>,
+    capacity: usize,
+) -> Vec<Component<V>> {
+    struct Visitor<V, M, S> {
+        successors: S,
+        val: usize,
+        lowest: M,
+        preorder: M,
+        back_edges_cnt: M,
+        stack: Vec<V>,
+        in_stack: HashSet<V>,
+        components: Vec<Component<V>>,
+    }
+
+    impl<V: Copy + Eq + Hash, M: VertexMap<V>, S: Fn(V) -> Vec<V>>
+        Visitor<V, M, S>
+    {
+        fn tarjan(&mut self, current: V) {
+            if self.preorder.get(current).is_some() {
+                return;
+            }
+
+            self.preorder.insert(current, self.val);
+            let mut lowest = self.val;
+            self.val += 1;
+            self.stack.push(current);
+            self.in_stack.insert(current);
+
+            for successor in (self.successors)(current) {
+                if self.in_stack.contains(&successor) {
+                    let count = self.back_edges_cnt.get(successor).unwrap_or(0);
+                    self.back_edges_cnt.insert(successor, count + 1);
+                    lowest = lowest.min(self.preorder.get(successor).unwrap());
+                } else if self.preorder.get(successor).is_none() {
+                    self.tarjan(successor);
+                    lowest = lowest.min(self.lowest.get(successor).unwrap());
+                }
+            }
+            self.lowest.insert(current, lowest);
+            if Some(lowest) == self.preorder.get(current) {
+                let mut vertices = vec![];
+                let mut num_back_edges = 0;
+                while let Some(v) = self.stack.pop() {
+                    vertices.push(v);
+                    if let Some(cnt) = self.back_edges_cnt.get(v) {
+                        num_back_edges += cnt;
+                    }
+                    self.in_stack.remove(&v);
+                    if v == current {
+                        break;
+                    }
+                }
+                self.components.push(Component {
+                    entry: current,
+                    vertices,
+                    num_back_edges,
+                });
+            }
+        }
+    }
+
+    let mut visitor = Visitor {
+        successors,
+        val: 0,
+        lowest: M::with_capacity(capacity),
+        preorder: M::with_capacity(capacity),
+        back_edges_cnt: M::with_capacity(capacity),
+        stack: vec![],
+        in_stack: HashSet::new(),
+        components: vec![],
+    };
+    for root in roots {
+        visitor.tarjan(root);
+    }
+    visitor.components
+}
+
 pub struct CondensedCfg<'cfg, 'program> {
     pub cfg: &'cfg Cfg<'program>,
     pub entry: ComponentIdx,
@@ -92,84 +218,19 @@ impl<'cfg, 'program> CondensedCfg<'cfg, 'program> {
 
     /// tarjan algorithm for constructing strongly connected components
     pub fn from_cfg(cfg: &'cfg Cfg<'program>) -> CondensedCfg<'cfg, 'program> {
-        struct Visitor<'a, 'program> {
-            cfg: &'a Cfg<'program>,
-            val: usize,
-            lowest: SecondaryMap<BasicBlockIdx, usize>,
-            preorder: SecondaryMap<BasicBlockIdx, usize>,
-            back_edges_cnt: SecondaryMap<BasicBlockIdx, usize>,
-            stack: Vec<BasicBlockIdx>,
-            in_stack: HashSet<BasicBlockIdx>,
-            components: SlotMap<ComponentIdx, Component>,
-            block2comp: SecondaryMap<BasicBlockIdx, ComponentIdx>,
-        }
-
-        let mut visitor = Visitor {
-            cfg,
-            val: 0,
-            lowest: SecondaryMap::with_capacity(cfg.vertices.capacity()),
-            preorder: SecondaryMap::with_capacity(cfg.vertices.capacity()),
-            back_edges_cnt: SecondaryMap::with_capacity(
-                cfg.vertices.capacity(),
-            ),
-            stack: vec![],
-            in_stack: HashSet::new(),
-            components: SlotMap::with_key(),
-            block2comp: SecondaryMap::with_capacity(cfg.vertices.capacity()),
-        };
-
-        impl Visitor<'_, '_> {
-            fn tarjan(&mut self, current: BasicBlockIdx) {
-                if self.preorder.contains_key(current) {
-                    return;
-                }
-
-                self.preorder.insert(current, self.val);
-                let mut lowest = self.val;
-                self.val += 1;
-                self.stack.push(current);
-                self.in_stack.insert(current);
-
-                for successor in self.cfg.successors(current) {
-                    if self.in_stack.contains(&successor) {
-                        *self
-                            .back_edges_cnt
-                            .entry(successor)
-                            .unwrap()
-                            .or_default() += 1;
-                        lowest = lowest.min(self.preorder[successor]);
-                    } else if !self.preorder.contains_key(successor) {
-                        self.tarjan(successor);
-                        lowest = lowest.min(self.lowest[successor]);
-                    }
-                }
-                self.lowest.insert(current, lowest);
-                if lowest == self.preorder[current] {
-                    let mut vertices = vec![];
-                    let mut num_back_edges = 0;
-                    while let Some(v) = self.stack.pop() {
-                        vertices.push(v);
-                        if let Some(cnt) = self.back_edges_cnt.get(v) {
-                            num_back_edges += cnt;
-                        }
-                        self.in_stack.remove(&v);
-                        if v == current {
-                            break;
-                        }
-                    }
-                    let comp_idx = self.components.insert(Component {
-                        entry: current,
-                        vertices: vertices.clone(),
-                        num_back_edges,
-                    });
-                    for block_idx in vertices {
-                        self.block2comp.insert(block_idx, comp_idx);
-                    }
-                }
+        let mut components = SlotMap::with_key();
+        let mut block2comp =
+            SecondaryMap::with_capacity(cfg.vertices.capacity());
+        for component in tarjan::<_, SecondaryMap<_, _>>(
+            [cfg.entry],
+            |block_idx| cfg.successors(block_idx),
+            cfg.vertices.capacity(),
+        ) {
+            let comp_idx = components.insert(component);
+            for &block_idx in &components[comp_idx].vertices {
+                block2comp.insert(block_idx, comp_idx);
             }
         }
-        visitor.tarjan(cfg.entry);
-        let components = visitor.components;
 
         // build edges between components
         let mut edges = SecondaryMap::with_capacity(components.len());
@@ -181,7 +242,7 @@ impl<'cfg, 'program> CondensedCfg<'cfg, 'program> {
                     cfg.successors(*block_idx).into_iter().filter_map(
                         |successor| {
                             if !comp.contains(successor) {
-                                Some(visitor.block2comp[successor])
+                                Some(block2comp[successor])
                             } else {
                                 None
                             }
@@ -204,7 +265,7 @@ impl<'cfg, 'program> CondensedCfg<'cfg, 'program> {
                     .push(source);
             }
         }
-        let entry = visitor.block2comp[cfg.entry];
+        let entry = block2comp[cfg.entry];
         Self {
             cfg,
             entry,
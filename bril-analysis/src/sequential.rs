@@ -1,65 +1,434 @@
 // Copyright (C) 2025 Zihan Li and Ethan Uppal.
 
-use fixedbitset::FixedBitSet;
-use std::collections::{HashMap, VecDeque};
+use std::{
+    collections::{BTreeSet, HashMap, HashSet, VecDeque},
+    time::Instant,
+};
 
 use bril::builder::BasicBlockIdx;
+use fixedbitset::FixedBitSet;
 use slotmap::SecondaryMap;
 
-use crate::{construct_postorder, Direction, TraverseCfgLike};
+use crate::{
+    BlockFacts, Direction, EdgeTransfers, Previous, SolveOptions, SolveStats,
+    TraverseCfgLike, Worklist, construct_postorder, find_back_edge_targets,
+    gen_kill::{GenKill, copy_words},
+};
 
-pub fn solve_dataflow<'a, C: TraverseCfgLike<'a>>(
+/// Solves the problem over the blocks reachable from the entry of
+/// `cfg_like`, with `entry_inputs` merged into the input of their blocks.
+pub fn solve_dataflow<
+    'a,
+    C: TraverseCfgLike<'a>,
+    D: Clone + PartialEq + Default,
+>(
     cfg_like: &C,
     context: &C::Context,
     direction: Direction,
-    entry_inputs: HashMap<BasicBlockIdx, FixedBitSet>,
-    merge: impl Fn(FixedBitSet, &FixedBitSet) -> FixedBitSet,
-    transfer: impl Fn(BasicBlockIdx, FixedBitSet) -> FixedBitSet,
-) -> SecondaryMap<BasicBlockIdx, FixedBitSet> {
+    entry_inputs: HashMap<BasicBlockIdx, D>,
+    merge: impl Fn(D, &D) -> D,
+    transfer: impl Fn(BasicBlockIdx, D) -> D,
+    options: SolveOptions<D>,
+) -> SecondaryMap<BasicBlockIdx, BlockFacts<D>> {
+    let SolveOptions {
+        worklist,
+        edge_transfers,
+        widening,
+        previous,
+        stats: total,
+    } = options;
+    let start = total.is_some().then(Instant::now);
+    let mut stats = SolveStats::default();
+    let no_edge_transfers = EdgeTransfers::new();
+    let edge_transfers = edge_transfers.unwrap_or(&no_edge_transfers);
     let postorder_traversal = construct_postorder(cfg_like, context);
+    let widening_points = if widening.is_some() {
+        find_back_edge_targets(cfg_like, context)
+    } else {
+        HashSet::new()
+    };
+    let mut inputs: SecondaryMap<BasicBlockIdx, D> =
+        SecondaryMap::with_capacity(cfg_like.vertices_capacity());
     let mut solution =
         SecondaryMap::with_capacity(cfg_like.vertices_capacity());
     for &block_idx in &postorder_traversal {
-        solution.insert(block_idx, FixedBitSet::new());
+        solution.insert(block_idx, D::default());
     }
 
-    let mut blocks = match direction {
+    let order = match direction {
         Direction::Forward => {
-            VecDeque::from_iter(postorder_traversal.into_iter().rev())
+            postorder_traversal.into_iter().rev().collect::<Vec<_>>()
         }
-        Direction::Backward => VecDeque::from_iter(postorder_traversal),
+        Direction::Backward => postorder_traversal,
+    };
+    let positions = positions(cfg_like, &order);
+    let flows_to = |current| match direction {
+        Direction::Forward => cfg_like.successors(context, current),
+        Direction::Backward => cfg_like.predecessors(context, current),
     };
 
-    while let Some(current) = blocks.pop_front() {
-        let mut initial_in = entry_inputs
-            .get(&current)
-            .cloned()
-            .unwrap_or(FixedBitSet::new());
-        match direction {
-            Direction::Forward => {
-                for predecessor in cfg_like.predecessors(context, current) {
-                    initial_in = merge(initial_in, &solution[predecessor]);
-                }
+    if let Some(Previous {
+        solution: mut previous,
+        changed,
+    }) = previous
+    {
+        // blocks the changes cannot reach keep their facts
+        let affected = affected(changed, flows_to);
+        for &block_idx in &order {
+            if !affected.contains(&block_idx)
+                && let Some(facts) = previous.remove(block_idx)
+            {
+                let BlockFacts { before, after } = facts;
+                let (input, output) = match direction {
+                    Direction::Forward => (before, after),
+                    Direction::Backward => (after, before),
+                };
+                inputs.insert(block_idx, input);
+                solution[block_idx] = output;
             }
-            Direction::Backward => {
-                for predecessor in cfg_like.successors(context, current) {
-                    initial_in = merge(initial_in, &solution[predecessor]);
-                }
+        }
+    }
+    let merged_input =
+        |current: BasicBlockIdx, solution: &SecondaryMap<BasicBlockIdx, D>| {
+            let mut initial_in =
+                entry_inputs.get(&current).cloned().unwrap_or_default();
+            let predecessors = match direction {
+                Direction::Forward => cfg_like.predecessors(context, current),
+                Direction::Backward => cfg_like.successors(context, current),
+            };
+            // blocks unreachable from the entry are never solved
+            for predecessor in predecessors {
+                let Some(output) = solution.get(predecessor) else {
+                    continue;
+                };
+                initial_in = match edge_transfers.get(&(predecessor, current)) {
+                    Some(edge_transfer) => {
+                        merge(initial_in, &edge_transfer(output))
+                    }
+                    None => merge(initial_in, output),
+                };
             }
+            initial_in
+        };
+
+    let mut queue = Queue::new(
+        worklist,
+        (0..order.len())
+            .filter(|&position| inputs.get(order[position]).is_none())
+            .collect(),
+        order.len(),
+    );
+    while let Some(position) = queue.pop() {
+        let current = order[position];
+        stats.iterations += 1;
+        let mut initial_in = merged_input(current, &solution);
+        if let Some(widening) = &widening
+            && widening_points.contains(&current)
+            && let Some(previous_in) = inputs.get(current)
+        {
+            initial_in = (widening.widen)(previous_in, initial_in);
         }
+        inputs.insert(current, initial_in.clone());
 
         let new_out = transfer(current, initial_in);
+        stats.transfers += 1;
         if !new_out.eq(&solution[current]) {
             solution[current] = new_out;
-            match direction {
-                Direction::Forward => {
-                    blocks.extend(cfg_like.successors(context, current));
+            for next in flows_to(current) {
+                if let Some(&position) = positions.get(next) {
+                    queue.push(position);
                 }
-                Direction::Backward => {
-                    blocks.extend(cfg_like.predecessors(context, current));
+            }
+        }
+    }
+
+    if let Some(widening) = &widening {
+        for _ in 0..widening.narrowing_passes {
+            let mut changed = false;
+            for &current in &order {
+                let mut initial_in = merged_input(current, &solution);
+                if widening_points.contains(&current) {
+                    initial_in =
+                        (widening.narrow)(&inputs[current], initial_in);
+                }
+                inputs.insert(current, initial_in.clone());
+
+                let new_out = transfer(current, initial_in);
+                stats.transfers += 1;
+                if !new_out.eq(&solution[current]) {
+                    solution[current] = new_out;
+                    changed = true;
                 }
             }
+            if !changed {
+                break;
+            }
         }
     }
+
+    let solution: SecondaryMap<_, _> = solution
+        .into_iter()
+        .map(|(block_idx, output)| {
+            let input = inputs.remove(block_idx).unwrap_or_default();
+            (block_idx, BlockFacts::new(direction, input, output))
+        })
+        .collect();
+    if let (Some(total), Some(start)) = (total, start) {
+        stats
+            .component_timings
+            .push((solution.len(), start.elapsed()));
+        total.absorb(stats);
+    }
     solution
 }
+
+/// Same as [`solve_dataflow`] for a gen/kill `problem`. Facts are kept in
+/// arenas of words for the whole solve and transferred with
+/// [`GenKill::transfer_into`], so iterating allocates nothing. They only
+/// flow through that transfer, so `options` may neither refine edges nor
+/// widen.
+pub fn solve_gen_kill<'a, C: TraverseCfgLike<'a>>(
+    cfg_like: &C,
+    context: &C::Context,
+    problem: &GenKill,
+    entry_inputs: &HashMap<BasicBlockIdx, FixedBitSet>,
+    options: SolveOptions,
+) -> SecondaryMap<BasicBlockIdx, BlockFacts> {
+    let SolveOptions {
+        worklist,
+        edge_transfers,
+        widening,
+        previous,
+        stats: total,
+    } = options;
+    assert!(
+        edge_transfers.is_none() && widening.is_none(),
+        "gen/kill facts only flow through the block transfer"
+    );
+    let start = total.is_some().then(Instant::now);
+    let mut stats = SolveStats::default();
+    let direction = problem.direction();
+    let postorder_traversal = construct_postorder(cfg_like, context);
+    let order = match direction {
+        Direction::Forward => {
+            postorder_traversal.into_iter().rev().collect::<Vec<_>>()
+        }
+        Direction::Backward => postorder_traversal,
+    };
+    let positions = positions(cfg_like, &order);
+
+    // positions every block merges from and flows to, flattened so that
+    // iterating does not ask the CFG for them again
+    let (mut sources, mut source_ranges): (Vec<usize>, _) = (vec![], vec![]);
+    let (mut targets, mut target_ranges): (Vec<usize>, _) = (vec![], vec![]);
+    for &block_idx in &order {
+        let (from, to) = match direction {
+            Direction::Forward => (
+                cfg_like.predecessors(context, block_idx),
+                cfg_like.successors(context, block_idx),
+            ),
+            Direction::Backward => (
+                cfg_like.successors(context, block_idx),
+                cfg_like.predecessors(context, block_idx),
+            ),
+        };
+        let start = sources.len();
+        sources.extend(from.iter().filter_map(|idx| positions.get(*idx)));
+        source_ranges.push(start..sources.len());
+        let start = targets.len();
+        targets.extend(to.iter().filter_map(|idx| positions.get(*idx)));
+        target_ranges.push(start..targets.len());
+    }
+
+    let words = problem.words();
+    let slot = |position: usize| position * words..(position + 1) * words;
+    let mut boundary = vec![0; order.len() * words];
+    for (block_idx, input) in entry_inputs {
+        if let Some(&position) = positions.get(*block_idx) {
+            copy_words(&mut boundary[slot(position)], input);
+        }
+    }
+    let mut inputs = vec![0; order.len() * words];
+    let mut outputs = vec![0; order.len() * words];
+
+    let mut restored = vec![false; order.len()];
+    if let Some(Previous { solution, changed }) = previous {
+        // blocks the changes cannot reach keep their facts
+        let affected = affected(changed, |current| match direction {
+            Direction::Forward => cfg_like.successors(context, current),
+            Direction::Backward => cfg_like.predecessors(context, current),
+        });
+        for (position, &block_idx) in order.iter().enumerate() {
+            if !affected.contains(&block_idx)
+                && let Some(facts) = solution.get(block_idx)
+            {
+                copy_words(&mut inputs[slot(position)], facts.input(direction));
+                copy_words(
+                    &mut outputs[slot(position)],
+                    facts.output(direction),
+                );
+                restored[position] = true;
+            }
+        }
+    }
+
+    let mut queue = Queue::new(
+        worklist,
+        (0..order.len())
+            .filter(|&position| !restored[position])
+            .collect(),
+        order.len(),
+    );
+    while let Some(position) = queue.pop() {
+        stats.iterations += 1;
+        let input = &mut inputs[slot(position)];
+        input.copy_from_slice(&boundary[slot(position)]);
+        for &source in &sources[source_ranges[position].clone()] {
+            for (word, &output) in input.iter_mut().zip(&outputs[slot(source)])
+            {
+                *word |= output;
+            }
+        }
+
+        stats.transfers += 1;
+        let output = &mut outputs[slot(position)];
+        if problem.transfer_into(order[position], input, output) {
+            for &target in &targets[target_ranges[position].clone()] {
+                queue.push(target);
+            }
+        }
+    }
+
+    let solution: SecondaryMap<_, _> = order
+        .iter()
+        .enumerate()
+        .map(|(position, &block_idx)| {
+            let input = problem.to_bitset(&inputs[slot(position)]);
+            let output = problem.to_bitset(&outputs[slot(position)]);
+            (block_idx, BlockFacts::new(direction, input, output))
+        })
+        .collect();
+    if let (Some(total), Some(start)) = (total, start) {
+        stats
+            .component_timings
+            .push((solution.len(), start.elapsed()));
+        total.absorb(stats);
+    }
+    solution
+}
+
+/// Position of every block in `order`.
+fn positions<'a, C: TraverseCfgLike<'a>>(
+    cfg_like: &C,
+    order: &[BasicBlockIdx],
+) -> SecondaryMap<BasicBlockIdx, usize> {
+    let mut positions =
+        SecondaryMap::with_capacity(cfg_like.vertices_capacity());
+    for (position, &block_idx) in order.iter().enumerate() {
+        positions.insert(block_idx, position);
+    }
+    positions
+}
+
+/// The `changed` blocks and all blocks their facts flow to.
+fn affected<I: IntoIterator<Item = BasicBlockIdx>>(
+    changed: &HashSet<BasicBlockIdx>,
+    flows_to: impl Fn(BasicBlockIdx) -> I,
+) -> HashSet<BasicBlockIdx> {
+    let mut affected = changed.clone();
+    let mut stack = Vec::from_iter(changed.iter().copied());
+    while let Some(current) = stack.pop() {
+        for next in flows_to(current) {
+            if affected.insert(next) {
+                stack.push(next);
+            }
+        }
+    }
+    affected
+}
+
+/// Positions of the blocks waiting to be solved, in the order they are
+/// first solved in, taken off in the order of a [`Worklist`]. Only the
+/// positions it starts with are ever queued again.
+struct Queue {
+    /// positions it starts with, in increasing order
+    sweep: Vec<usize>,
+    /// whether every position is one of them
+    queueable: Vec<bool>,
+    pending: Pending,
+}
+
+enum Pending {
+    Fifo(VecDeque<usize>),
+    Lifo(Vec<usize>),
+    Priority(BTreeSet<usize>),
+    /// index of the next position of the current sweep, and whether a
+    /// position was queued during it
+    RoundRobin {
+        next: usize,
+        queued: bool,
+    },
+}
+
+impl Queue {
+    /// Queues `sweep`, positions in increasing order out of `len`.
+    fn new(worklist: Worklist, sweep: Vec<usize>, len: usize) -> Self {
+        let mut queueable = vec![false; len];
+        for &position in &sweep {
+            queueable[position] = true;
+        }
+        let pending = match worklist {
+            Worklist::Fifo => Pending::Fifo(VecDeque::from(sweep.clone())),
+            Worklist::Lifo => {
+                Pending::Lifo(sweep.iter().rev().copied().collect())
+            }
+            Worklist::Priority => {
+                Pending::Priority(sweep.iter().copied().collect())
+            }
+            Worklist::RoundRobin => Pending::RoundRobin {
+                next: 0,
+                queued: false,
+            },
+        };
+        Self {
+            sweep,
+            queueable,
+            pending,
+        }
+    }
+
+    fn push(&mut self, position: usize) {
+        if !self.queueable[position] {
+            return;
+        }
+        match &mut self.pending {
+            Pending::Fifo(pending) => pending.push_back(position),
+            Pending::Lifo(pending) => pending.push(position),
+            Pending::Priority(pending) => {
+                pending.insert(position);
+            }
+            Pending::RoundRobin { queued, .. } => *queued = true,
+        }
+    }
+
+    fn pop(&mut self) -> Option<usize> {
+        match &mut self.pending {
+            Pending::Fifo(pending) => pending.pop_front(),
+            Pending::Lifo(pending) => pending.pop(),
+            Pending::Priority(pending) => pending.pop_first(),
+            Pending::RoundRobin { next, queued } => {
+                // another sweep is needed while blocks keep changing
+                if *next == self.sweep.len() {
+                    if !*queued {
+                        return None;
+                    }
+                    *next = 0;
+                    *queued = false;
+                }
+                let position = self.sweep.get(*next).copied();
+                *next += 1;
+                position
+            }
+        }
+    }
+}
@@ -0,0 +1,202 @@
+// Copyright (C) 2025 Zihan Li and Ethan Uppal.
+use bril::ir::{FunctionIdx, Instruction, Program, Variable};
+use bril_cfg::build_cfg;
+use fixedbitset::FixedBitSet;
+use rayon::prelude::*;
+use std::collections::HashMap;
+
+use crate::{
+    analysis::{InstructionExt, liveness_with},
+    call_graph::CallGraph,
+    find_back_edge_targets,
+    scc::Component,
+};
+
+/// What a call to a function can do, as seen from its callers. Variables
+/// are local and passed by value, so the only caller state a function refers
+/// to is its arguments, and the only state it modifies besides the result of
+/// the call is the output.
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct FunctionSummary {
+    /// positions of the parameters whose value the function may read
+    pub reads: FixedBitSet,
+    /// whether the function may print, itself or through its callees
+    pub prints: bool,
+    /// whether the function may loop forever, recurse or trap instead of
+    /// returning
+    pub may_not_return: bool,
+}
+
+impl FunctionSummary {
+    /// Whether calls to the function can be removed when their result is
+    /// dead, or executed where they did not run before.
+    pub fn is_pure(&self) -> bool {
+        !self.prints && !self.may_not_return
+    }
+}
+
+/// Summaries of every function of a program, computed bottom-up over the
+/// call graph. The functions of a recursive component are summarized
+/// together until their summaries stop changing.
+pub struct Summaries {
+    summaries: Vec<FunctionSummary>,
+}
+
+impl Summaries {
+    pub fn new(program: &Program, call_graph: &CallGraph) -> Self {
+        let mut summaries = Self::unsummarized(program);
+        for component in call_graph.components() {
+            for (function_idx, summary) in
+                summaries.summarize_component(program, call_graph, component)
+            {
+                summaries.summaries[function_idx.0 as usize] = summary;
+            }
+        }
+        summaries
+    }
+
+    /// Same as [`Summaries::new`], summarizing the independent components
+    /// of every level of [`CallGraph::bottom_up_levels`] in parallel.
+    pub fn new_para(
+        program: &Program,
+        call_graph: &CallGraph,
+        num_threads: usize,
+    ) -> Self {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(num_threads)
+            .build()
+            .unwrap();
+        let mut summaries = Self::unsummarized(program);
+        for level in call_graph.bottom_up_levels() {
+            let summarized: Vec<_> = pool.install(|| {
+                level
+                    .par_iter()
+                    .flat_map_iter(|&comp_idx| {
+                        summaries.summarize_component(
+                            program,
+                            call_graph,
+                            &call_graph.components()[comp_idx],
+                        )
+                    })
+                    .collect()
+            });
+            for (function_idx, summary) in summarized {
+                summaries.summaries[function_idx.0 as usize] = summary;
+            }
+        }
+        summaries
+    }
+
+    pub fn get(&self, function: FunctionIdx) -> &FunctionSummary {
+        &self.summaries[function.0 as usize]
+    }
+
+    /// The variables `instruction` may read, which for calls are only the
+    /// arguments the callee reads.
+    pub fn operands(&self, instruction: &Instruction) -> Vec<Variable> {
+        operands(instruction, |callee| self.get(callee))
+    }
+
+    fn unsummarized(program: &Program) -> Self {
+        Self {
+            summaries: vec![
+                FunctionSummary::default();
+                program.functions().count()
+            ],
+        }
+    }
+
+    /// Summaries of the functions of `component`, given the summaries of
+    /// every component it calls.
+    fn summarize_component(
+        &self,
+        program: &Program,
+        call_graph: &CallGraph,
+        component: &Component<FunctionIdx>,
+    ) -> HashMap<FunctionIdx, FunctionSummary> {
+        let recursive = call_graph.is_recursive(component.entry);
+        let mut summaries: HashMap<_, _> = component
+            .vertices
+            .iter()
+            .map(|&function_idx| {
+                let summary = FunctionSummary {
+                    may_not_return: recursive,
+                    ..Default::default()
+                };
+                (function_idx, summary)
+            })
+            .collect();
+
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for &function_idx in &component.vertices {
+                let summary = summarize(program, function_idx, |callee| {
+                    summaries.get(&callee).unwrap_or_else(|| self.get(callee))
+                });
+                if summary != summaries[&function_idx] {
+                    summaries.insert(function_idx, summary);
+                    changed = true;
+                }
+            }
+        }
+        summaries
+    }
+}
+
+/// Summary of `function_idx`, looking up the summaries of its callees in
+/// `callee_summary`.
+fn summarize<'s>(
+    program: &Program,
+    function_idx: FunctionIdx,
+    callee_summary: impl Fn(FunctionIdx) -> &'s FunctionSummary,
+) -> FunctionSummary {
+    let function = program.get_function(function_idx);
+    let cfg = build_cfg(&function);
+    let own = callee_summary(function_idx);
+    let mut summary = FunctionSummary {
+        reads: FixedBitSet::with_capacity(function.parameters.len()),
+        prints: false,
+        may_not_return: own.may_not_return
+            || !find_back_edge_targets(&cfg, &()).is_empty(),
+    };
+    for instruction in function.instructions {
+        match instruction {
+            Instruction::Print(_) => summary.prints = true,
+            Instruction::Div(..) => summary.may_not_return = true,
+            Instruction::Call(_, callee, _) => {
+                let callee = callee_summary(*callee);
+                summary.prints |= callee.prints;
+                summary.may_not_return |= callee.may_not_return;
+            }
+            _ => {}
+        }
+    }
+
+    let live = liveness_with(&cfg, |instruction| {
+        operands(instruction, &callee_summary)
+    });
+    for (i, parameter) in function.parameters.iter().enumerate() {
+        if live[cfg.entry].before.contains(parameter.0 as usize) {
+            summary.reads.insert(i);
+        }
+    }
+    summary
+}
+
+fn operands<'s>(
+    instruction: &Instruction,
+    callee_summary: impl Fn(FunctionIdx) -> &'s FunctionSummary,
+) -> Vec<Variable> {
+    match instruction {
+        Instruction::Call(_, callee, args) => {
+            let reads = &callee_summary(*callee).reads;
+            args.iter()
+                .enumerate()
+                .filter(|(i, _)| reads.contains(*i))
+                .map(|(_, arg)| *arg)
+                .collect()
+        }
+        _ => instruction.operands(),
+    }
+}
@@ -1,6 +1,10 @@
 // Copyright (C) 2025 Zihan Li and Ethan Uppal.
 
-use std::{collections::HashMap, mem, ops::Range};
+use std::{
+    collections::{HashMap, HashSet},
+    mem,
+    ops::Range,
+};
 
 use bril::{
     builder::BasicBlockIdx,
@@ -10,6 +14,9 @@ use bril::{
 };
 use slotmap::{SecondaryMap, SlotMap};
 
+mod owned;
+pub use owned::*;
+
 #[derive(Debug, Default, PartialEq, Eq, Clone)]
 pub enum LabeledExit {
     #[default]
@@ -67,6 +74,53 @@ impl Cfg<'_> {
     pub fn predecessors(&self, current: BasicBlockIdx) -> Vec<BasicBlockIdx> {
         self.rev_edges.get(current).cloned().unwrap_or_default()
     }
+
+    /// Edges from blocks with several successors to blocks with several
+    /// predecessors. Split them with [`OwnedCfg::split_critical_edges`].
+    pub fn critical_edges(&self) -> Vec<(BasicBlockIdx, BasicBlockIdx)> {
+        find_critical_edges(
+            self.vertices.keys(),
+            |block_idx| self.successors(block_idx),
+            |block_idx| self.predecessors(block_idx),
+        )
+    }
+}
+
+pub(crate) fn find_critical_edges(
+    blocks: impl IntoIterator<Item = BasicBlockIdx>,
+    successors: impl Fn(BasicBlockIdx) -> Vec<BasicBlockIdx>,
+    predecessors: impl Fn(BasicBlockIdx) -> Vec<BasicBlockIdx>,
+) -> Vec<(BasicBlockIdx, BasicBlockIdx)> {
+    let distinct = |mut blocks: Vec<BasicBlockIdx>| {
+        blocks.sort_unstable();
+        blocks.dedup();
+        blocks
+    };
+    let mut critical = vec![];
+    for block_idx in blocks {
+        let successors = distinct(successors(block_idx));
+        if successors.len() < 2 {
+            continue;
+        }
+        for successor in successors {
+            if distinct(predecessors(successor)).len() > 1 {
+                critical.push((block_idx, successor));
+            }
+        }
+    }
+    critical
+}
+
+/// A label named after `base` that is not in `used`, which it is added to.
+pub fn fresh_label(used: &mut HashSet<String>, base: &str) -> String {
+    let mut label = base.to_string();
+    let mut i = 0;
+    while used.contains(&label) {
+        i += 1;
+        label = format!("{base}.{i}");
+    }
+    used.insert(label.clone());
+    label
 }
 
 pub struct CfgBuilder<'a, 'program> {
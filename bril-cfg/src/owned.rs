@@ -0,0 +1,383 @@
+// Copyright (C) 2025 Zihan Li and Ethan Uppal.
+
+use std::collections::HashSet;
+
+use bril::{
+    builder::{BasicBlockBuilder, BasicBlockIdx, ProgramBuilder},
+    ir::{FunctionPrototype, Instruction, Program, Variable},
+};
+use slotmap::{SecondaryMap, SlotMap};
+
+use crate::{Cfg, Exit, find_critical_edges, fresh_label};
+
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct OwnedBlock {
+    pub label: Option<String>,
+    /// The final `jmp` or `br` is not included, the exit of the block
+    /// stands for it. A `ret` is kept.
+    pub instructions: Vec<Instruction>,
+    /// variable a conditional exit branches on
+    pub condition: Option<Variable>,
+}
+
+/// A CFG owning its instructions, which blocks and edges can be added to and
+/// removed from. Blocks are laid out in `layout` when lowered back to a
+/// [`Program`], where jumps are added for exits that no longer fall through.
+#[derive(Debug, Clone)]
+pub struct OwnedCfg {
+    pub prototype: FunctionPrototype,
+    pub entry: BasicBlockIdx,
+    pub vertices: SlotMap<BasicBlockIdx, OwnedBlock>,
+    pub edges: SecondaryMap<BasicBlockIdx, Exit>,
+    /// predecessors of every block, once per edge
+    pub rev_edges: SecondaryMap<BasicBlockIdx, Vec<BasicBlockIdx>>,
+    pub layout: Vec<BasicBlockIdx>,
+}
+
+impl OwnedCfg {
+    /// A copy of `cfg` whose blocks keep their indices, so facts computed on
+    /// `cfg` apply to them.
+    pub fn from_cfg(cfg: &Cfg) -> Self {
+        let mut owned = Self {
+            prototype: cfg.prototype.clone(),
+            entry: cfg.entry,
+            vertices: SlotMap::with_key(),
+            edges: SecondaryMap::new(),
+            rev_edges: SecondaryMap::new(),
+            layout: vec![],
+        };
+        for (block_idx, block) in &cfg.vertices {
+            let mut instructions = block.instructions.to_vec();
+            let condition = match instructions.last() {
+                Some(Instruction::Jmp(_)) => {
+                    instructions.pop();
+                    None
+                }
+                Some(Instruction::Br(condition, ..)) => {
+                    let condition = *condition;
+                    instructions.pop();
+                    Some(condition)
+                }
+                _ => None,
+            };
+            // `build_cfg` never removes blocks, so inserting them in the same
+            // order hands out the same keys
+            let owned_idx = owned.vertices.insert(OwnedBlock {
+                label: block.label.map(|label| label.name.to_string()),
+                instructions,
+                condition,
+            });
+            assert_eq!(owned_idx, block_idx);
+            owned.layout.push(block_idx);
+            owned.edges.insert(block_idx, cfg.edges[block_idx].clone());
+            owned
+                .rev_edges
+                .insert(block_idx, cfg.predecessors(block_idx));
+        }
+        owned
+    }
+
+    pub fn successors(&self, current: BasicBlockIdx) -> Vec<BasicBlockIdx> {
+        match &self.edges[current] {
+            Exit::Unconditional(successor) => vec![*successor],
+            Exit::Conditional { if_true, if_false } => {
+                vec![*if_true, *if_false]
+            }
+            Exit::Return => vec![],
+        }
+    }
+
+    pub fn predecessors(&self, current: BasicBlockIdx) -> Vec<BasicBlockIdx> {
+        self.rev_edges.get(current).cloned().unwrap_or_default()
+    }
+
+    /// Adds `block` with `exit`, laid out right after `after` or at the end
+    /// if `None`.
+    pub fn add_block(
+        &mut self,
+        block: OwnedBlock,
+        exit: Exit,
+        after: Option<BasicBlockIdx>,
+    ) -> BasicBlockIdx {
+        let block_idx = self.vertices.insert(block);
+        self.edges.insert(block_idx, Exit::Return);
+        self.rev_edges.insert(block_idx, vec![]);
+        self.set_exit(block_idx, exit);
+        let at = after
+            .and_then(|after| self.layout.iter().position(|idx| *idx == after))
+            .map_or(self.layout.len(), |position| position + 1);
+        self.layout.insert(at, block_idx);
+        block_idx
+    }
+
+    /// Removes `block_idx`, which no other block may branch to anymore.
+    pub fn remove_block(&mut self, block_idx: BasicBlockIdx) -> OwnedBlock {
+        assert!(
+            self.predecessors(block_idx)
+                .iter()
+                .all(|predecessor| *predecessor == block_idx),
+            "removed block still has predecessors"
+        );
+        self.set_exit(block_idx, Exit::Return);
+        self.edges.remove(block_idx);
+        self.rev_edges.remove(block_idx);
+        self.layout.retain(|idx| *idx != block_idx);
+        self.vertices.remove(block_idx).unwrap()
+    }
+
+    /// Removes the blocks the entry does not reach, returning their number.
+    pub fn remove_unreachable(&mut self) -> usize {
+        let mut reachable = HashSet::from([self.entry]);
+        let mut stack = vec![self.entry];
+        while let Some(block_idx) = stack.pop() {
+            for successor in self.successors(block_idx) {
+                if reachable.insert(successor) {
+                    stack.push(successor);
+                }
+            }
+        }
+        let unreachable: Vec<_> = self
+            .vertices
+            .keys()
+            .filter(|block_idx| !reachable.contains(block_idx))
+            .collect();
+        // they may branch to each other
+        for &block_idx in &unreachable {
+            self.set_exit(block_idx, Exit::Return);
+        }
+        for &block_idx in &unreachable {
+            self.remove_block(block_idx);
+        }
+        unreachable.len()
+    }
+
+    /// Replaces the exit of `block_idx`, updating the predecessors of its old
+    /// and new successors.
+    pub fn set_exit(&mut self, block_idx: BasicBlockIdx, exit: Exit) {
+        for successor in self.successors(block_idx) {
+            let predecessors = &mut self.rev_edges[successor];
+            let position = predecessors
+                .iter()
+                .position(|predecessor| *predecessor == block_idx)
+                .unwrap();
+            predecessors.remove(position);
+        }
+        self.edges.insert(block_idx, exit);
+        for successor in self.successors(block_idx) {
+            self.rev_edges[successor].push(block_idx);
+        }
+    }
+
+    /// Makes every edge from `from` to `to` go to `target` instead.
+    pub fn redirect(
+        &mut self,
+        from: BasicBlockIdx,
+        to: BasicBlockIdx,
+        target: BasicBlockIdx,
+    ) {
+        let retarget = |successor: BasicBlockIdx| {
+            if successor == to { target } else { successor }
+        };
+        let exit = match self.edges[from] {
+            Exit::Unconditional(successor) => {
+                Exit::Unconditional(retarget(successor))
+            }
+            Exit::Conditional { if_true, if_false } => Exit::Conditional {
+                if_true: retarget(if_true),
+                if_false: retarget(if_false),
+            },
+            Exit::Return => Exit::Return,
+        };
+        self.set_exit(from, exit);
+    }
+
+    /// A label named after `base` that no block uses.
+    pub fn fresh_label(&self, base: &str) -> String {
+        let mut used = self
+            .vertices
+            .values()
+            .filter_map(|block| block.label.clone())
+            .collect();
+        fresh_label(&mut used, base)
+    }
+
+    /// Places an empty block on the edges from `from` to `to`, laid out
+    /// right after `from`.
+    pub fn split_edge(
+        &mut self,
+        from: BasicBlockIdx,
+        to: BasicBlockIdx,
+    ) -> BasicBlockIdx {
+        let base = match &self.vertices[to].label {
+            Some(label) => format!("{label}.split"),
+            None => "split".to_string(),
+        };
+        let block = OwnedBlock {
+            label: Some(self.fresh_label(&base)),
+            ..Default::default()
+        };
+        let split_idx =
+            self.add_block(block, Exit::Unconditional(to), Some(from));
+        self.redirect(from, to, split_idx);
+        split_idx
+    }
+
+    /// Edges from blocks with several successors to blocks with several
+    /// predecessors.
+    pub fn critical_edges(&self) -> Vec<(BasicBlockIdx, BasicBlockIdx)> {
+        find_critical_edges(
+            self.layout.iter().copied(),
+            |block_idx| self.successors(block_idx),
+            |block_idx| self.predecessors(block_idx),
+        )
+    }
+
+    /// Splits every critical edge, returning the added blocks in the order
+    /// of [`OwnedCfg::critical_edges`].
+    pub fn split_critical_edges(&mut self) -> Vec<BasicBlockIdx> {
+        self.critical_edges()
+            .into_iter()
+            .map(|(from, to)| self.split_edge(from, to))
+            .collect()
+    }
+
+    /// Appends every block to its only predecessor when it is that
+    /// predecessor's only successor. Returns the number of merged blocks.
+    pub fn merge_straight_line(&mut self) -> usize {
+        let mut merged = 0;
+        let mut i = 0;
+        while i < self.layout.len() {
+            let block_idx = self.layout[i];
+            let Exit::Unconditional(successor) = self.edges[block_idx] else {
+                i += 1;
+                continue;
+            };
+            if successor == block_idx
+                || successor == self.entry
+                || self.predecessors(successor) != [block_idx]
+            {
+                i += 1;
+                continue;
+            }
+            // the block takes over the exit of its successor first, so
+            // nothing branches to the successor anymore
+            let exit = self.edges[successor].clone();
+            self.set_exit(block_idx, exit);
+            let absorbed = self.remove_block(successor);
+            let block = &mut self.vertices[block_idx];
+            block.instructions.extend(absorbed.instructions);
+            block.condition = absorbed.condition;
+            merged += 1;
+            // the block may absorb its new successor too
+            i = self
+                .layout
+                .iter()
+                .position(|idx| *idx == block_idx)
+                .unwrap();
+        }
+        merged
+    }
+
+    /// Adds the function to `program_builder`. Calls refer to the functions
+    /// of `source`, which are resolved by name.
+    pub fn lower(
+        &self,
+        program_builder: &mut ProgramBuilder,
+        source: &Program,
+    ) {
+        let mut function_builder =
+            program_builder.new_function(self.prototype.name.clone());
+        function_builder.parameters(&self.prototype.parameters);
+        if let Some(return_type) = self.prototype.return_type {
+            function_builder.return_type(return_type);
+        }
+
+        // every block ends up labeled, so unlabeled ones are given names the
+        // function does not use yet
+        let mut fresh = (0..).map(|i| format!("L{i}")).filter(|name| {
+            self.vertices
+                .values()
+                .all(|block| block.label.as_ref() != Some(name))
+        });
+        let mut labels = SecondaryMap::new();
+        for &block_idx in &self.layout {
+            let label = self.vertices[block_idx]
+                .label
+                .clone()
+                .unwrap_or_else(|| fresh.next().unwrap());
+            labels.insert(block_idx, label);
+        }
+
+        // the function starts executing at its first block
+        if self.layout.first() != Some(&self.entry) {
+            let mut block_builder =
+                BasicBlockBuilder::with_label(fresh.next().unwrap());
+            block_builder.add_patched_instr(
+                Instruction::Jmp(Default::default()),
+                vec![labels[self.entry].clone()],
+            );
+            function_builder.seal_block(block_builder);
+        }
+        for (i, &block_idx) in self.layout.iter().enumerate() {
+            let block = &self.vertices[block_idx];
+            let next = self.layout.get(i + 1).copied();
+            let mut block_builder =
+                BasicBlockBuilder::with_label(labels[block_idx].clone());
+            for instruction in &block.instructions {
+                match instruction {
+                    Instruction::Call(_, callee, _) => block_builder
+                        .add_patched_instr(
+                            instruction.clone(),
+                            vec![source.get_function(*callee).name.to_string()],
+                        ),
+                    _ => block_builder.add_instr(instruction.clone()),
+                }
+            }
+            match self.edges[block_idx] {
+                Exit::Unconditional(successor) => {
+                    if next != Some(successor) {
+                        block_builder.add_patched_instr(
+                            Instruction::Jmp(Default::default()),
+                            vec![labels[successor].clone()],
+                        );
+                    }
+                }
+                Exit::Conditional { if_true, if_false } => {
+                    let condition = block
+                        .condition
+                        .expect("conditional exit without a condition");
+                    block_builder.add_patched_instr(
+                        Instruction::Br(
+                            condition,
+                            Default::default(),
+                            Default::default(),
+                        ),
+                        vec![labels[if_true].clone(), labels[if_false].clone()],
+                    );
+                }
+                Exit::Return => {
+                    // the block used to fall off the end of the function
+                    if next.is_some()
+                        && !matches!(
+                            block.instructions.last(),
+                            Some(Instruction::Ret(_))
+                        )
+                    {
+                        block_builder.add_instr(Instruction::Ret(None));
+                    }
+                }
+            }
+            function_builder.seal_block(block_builder);
+        }
+        function_builder.finish();
+    }
+}
+
+/// Lowers the CFG of every function of `source`, in order, to a new program.
+pub fn lower_program(source: &Program, cfgs: &[OwnedCfg]) -> Program {
+    let mut program_builder = ProgramBuilder::new();
+    for cfg in cfgs {
+        cfg.lower(&mut program_builder, source);
+    }
+    program_builder.finish()
+}
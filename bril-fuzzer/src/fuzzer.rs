@@ -14,11 +14,22 @@ use std::collections::HashMap;
 pub struct Fuzzer<'a, R: Rng + ?Sized> {
     ctx: Context<'a, R>,
     root_ast_layout: AstLayout,
+    print_vars: bool,
 }
 
 impl<R: Rng + ?Sized> Fuzzer<'_, R> {
     pub fn fuzz(&mut self) -> Program {
-        let root = self.ctx.sample_ast(&self.root_ast_layout);
+        let mut root = self.ctx.sample_ast(&self.root_ast_layout);
+        if self.print_vars {
+            let mut vars: Vec<_> =
+                self.ctx.live_vars.values().flatten().copied().collect();
+            vars.sort();
+            let print = self
+                .ctx
+                .ast
+                .insert(Ast::Instruction(Instruction::Print(vars.into())));
+            root = self.ctx.ast.insert(Ast::Seq(vec![root, print]));
+        }
         ast_to_ir(&self.ctx.ast, root)
     }
 }
@@ -27,6 +38,7 @@ pub struct FuzzerBuilder<'a, R: Rng + ?Sized> {
     config: FuzzConfig,
     num_blocks: usize,
     max_block_depth: usize,
+    print_vars: bool,
     rng: &'a mut R,
 }
 
@@ -38,6 +50,7 @@ impl<'a, R: Rng + ?Sized> FuzzerBuilder<'a, R> {
             },
             num_blocks: 4,
             max_block_depth: 1,
+            print_vars: false,
             rng,
         }
     }
@@ -58,6 +71,13 @@ impl<'a, R: Rng + ?Sized> FuzzerBuilder<'a, R> {
         self
     }
 
+    /// Prints every variable in scope at the end of the program, so that
+    /// its runs can be compared.
+    pub fn print_vars(mut self, print_vars: bool) -> Self {
+        self.print_vars = print_vars;
+        self
+    }
+
     pub fn finish(self) -> Fuzzer<'a, R> {
         Fuzzer {
             ctx: Context {
@@ -71,6 +91,7 @@ impl<'a, R: Rng + ?Sized> FuzzerBuilder<'a, R> {
                 num_blocks: self.num_blocks,
                 max_block_depth: self.max_block_depth,
             },
+            print_vars: self.print_vars,
         }
     }
 }
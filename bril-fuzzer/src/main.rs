@@ -30,6 +30,10 @@ struct Args {
     /// Maximum nesting level, including if-else and loop constructs
     #[arg(long = "max-nesting", default_value_t = 3)]
     max_block_depth: usize,
+
+    /// Print every variable in scope at the end of the emitted function
+    #[arg(long)]
+    print_vars: bool,
 }
 
 fn main() {
@@ -44,6 +48,7 @@ fn main() {
         .num_blocks(args.num_blocks)
         .block_size(args.block_size_mean, args.block_size_std)
         .max_block_depth(args.max_block_depth)
+        .print_vars(args.print_vars)
         .finish();
     let prog = fuzzer.fuzz();
 
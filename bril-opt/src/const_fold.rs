@@ -0,0 +1,80 @@
+use bril::ir::{FunctionIdx, Instruction, Program, Value};
+use bril_analysis::analysis::{InstructionExt, const_prop, const_prop_instr};
+
+use crate::rebuild::{Block, layout, rebuild};
+
+/// What [`constant_folding`] changed.
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct FoldStats {
+    /// instructions replaced with a `const`
+    pub folded: usize,
+    /// `br`s replaced with a `jmp`
+    pub branches: usize,
+    /// blocks no execution reaches anymore
+    pub removed_blocks: usize,
+    /// divisions by a known zero, which are left to trap, as the function
+    /// and offset of the instruction in the original program
+    pub zero_divisions: Vec<(FunctionIdx, usize)>,
+}
+
+/// Replaces instructions computing a constant with a `const` and branches
+/// on a known condition with a `jmp`, given the facts of [`const_prop`].
+/// Blocks no execution reaches are removed.
+pub fn constant_folding(program: &Program) -> (Program, FoldStats) {
+    let mut stats = FoldStats::default();
+    let optimized = rebuild(program, |function_idx, cfg| {
+        let facts = const_prop(cfg);
+        let instr_facts = const_prop_instr(cfg, &facts);
+
+        let mut blocks = vec![];
+        for block_idx in layout(cfg) {
+            let points = match instr_facts.points(block_idx) {
+                Some(points) if points[0].is_reachable() => points,
+                _ => {
+                    stats.removed_blocks += 1;
+                    continue;
+                }
+            };
+            let mut block = Block::from_cfg(cfg, block_idx);
+            let offset = cfg.vertices[block_idx].offset;
+            let mut taken = None;
+            for (i, (instruction, constants)) in
+                block.instructions.iter_mut().zip(points).enumerate()
+            {
+                match instruction {
+                    Instruction::Const(..) | Instruction::Call(..) => {}
+                    Instruction::Div(_, _, divisor)
+                        if constants.get(*divisor) == Some(&Value::Int(0)) =>
+                    {
+                        stats.zero_divisions.push((function_idx, offset + i));
+                    }
+                    Instruction::Br(condition, ..) => {
+                        let Some(Value::Bool(outcome)) =
+                            constants.get(*condition)
+                        else {
+                            continue;
+                        };
+                        *instruction = Instruction::Jmp(Default::default());
+                        taken = Some(usize::from(!outcome));
+                        stats.branches += 1;
+                    }
+                    _ => {
+                        if let (Some(dest), Some(value)) = (
+                            instruction.dest(),
+                            constants.evaluate(instruction),
+                        ) {
+                            *instruction = Instruction::Const(dest, value);
+                            stats.folded += 1;
+                        }
+                    }
+                }
+            }
+            if let Some(taken) = taken {
+                block.targets = vec![block.targets.swap_remove(taken)];
+            }
+            blocks.push(block);
+        }
+        blocks
+    });
+    (optimized, stats)
+}
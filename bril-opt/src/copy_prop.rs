@@ -0,0 +1,95 @@
+use bril::ir::{Instruction, Program, Variable};
+use bril_analysis::analysis::{
+    InstructionExt, available_copies, available_copies_instr, find_copies,
+};
+use bril_cfg::build_cfg;
+use std::collections::HashMap;
+
+use crate::{dce::remove_dead_instructions, function_indices};
+
+/// Replaces uses of copies with their sources, then removes the copies left
+/// dead. Returns the number of rewritten operands and removed copies.
+pub fn copy_propagation(program: &mut Program) -> (usize, usize) {
+    let propagated = propagate_copies(program);
+    let removed = remove_dead_copies(program);
+    (propagated, removed)
+}
+
+/// Rewrites every operand holding the same value as an available copy's
+/// source to that source, following chains of copies. Returns the number of
+/// rewritten operands.
+pub fn propagate_copies(program: &mut Program) -> usize {
+    let mut propagated = 0;
+    for function_idx in function_indices(program) {
+        let rewrites = {
+            let function = program.get_function(function_idx);
+            let cfg = build_cfg(&function);
+            let copies = find_copies(&cfg);
+            let blocks = available_copies(&cfg);
+            let instr_facts = available_copies_instr(&cfg, &blocks);
+
+            let mut rewrites = vec![];
+            for (block_idx, block) in cfg.vertices.iter() {
+                let Some(points) = instr_facts.points(block_idx) else {
+                    continue;
+                };
+                for (i, (instruction, available)) in
+                    block.instructions.iter().zip(points).enumerate()
+                {
+                    let sources: HashMap<u32, Variable> = available
+                        .ones()
+                        .map(|offset| {
+                            let (dest, src) = copies[&offset];
+                            (dest.0, src)
+                        })
+                        .collect();
+                    let operands: Vec<_> = instruction
+                        .operands()
+                        .into_iter()
+                        .map(|operand| resolve(&sources, operand))
+                        .collect();
+                    if operands != instruction.operands() {
+                        rewrites.push((block.offset + i, operands));
+                    }
+                }
+            }
+            rewrites
+        };
+
+        let instructions = program.function_instructions_mut(function_idx);
+        for (offset, operands) in rewrites {
+            for (operand, rewritten) in instructions[offset]
+                .operands_mut()
+                .into_iter()
+                .zip(operands)
+            {
+                if *operand != rewritten {
+                    *operand = rewritten;
+                    propagated += 1;
+                }
+            }
+        }
+    }
+    propagated
+}
+
+/// Removes copies whose destination is dead right after them, until none is
+/// left. Returns the number of removed copies.
+pub fn remove_dead_copies(program: &mut Program) -> usize {
+    remove_dead_instructions(program, |_, _, instruction| {
+        matches!(instruction, Instruction::Id(..))
+    })
+}
+
+/// Follows the chain of available copies `operand` is the destination of,
+/// back to the earliest source.
+fn resolve(
+    sources: &HashMap<u32, Variable>,
+    mut operand: Variable,
+) -> Variable {
+    // a copy kills every copy of its destination, so chains are acyclic
+    while let Some(src) = sources.get(&operand.0) {
+        operand = *src;
+    }
+    operand
+}
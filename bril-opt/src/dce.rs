@@ -0,0 +1,95 @@
+use std::collections::HashSet;
+
+use bril::ir::{FunctionIdx, Instruction, Program};
+use bril_analysis::{
+    analysis::{
+        InstructionExt, Interval, abstract_interp, liveness, liveness_instr,
+        nonzero_divisors,
+    },
+    call_graph::CallGraph,
+    summary::Summaries,
+};
+use bril_cfg::build_cfg;
+
+use crate::function_indices;
+
+/// Removes instructions without side effects whose destination is dead right
+/// after them, until none is left. Divisions are only removed when interval
+/// analysis proves their divisor nonzero, so a division by zero still traps.
+/// Calls are removed when the summary of the callee says it is pure, even
+/// without a destination. Returns the number of removed instructions.
+pub fn eliminate_dead_code(program: &mut Program) -> usize {
+    let summaries = Summaries::new(program, &CallGraph::new(program));
+    // removed instructions are left as nops until the end, so the offsets
+    // stay valid
+    let safe_divisions: Vec<HashSet<usize>> = function_indices(program)
+        .into_iter()
+        .map(|function_idx| {
+            let cfg = build_cfg(&program.get_function(function_idx));
+            let blocks = abstract_interp::<Interval>(&cfg);
+            nonzero_divisors(&cfg, &blocks).into_iter().collect()
+        })
+        .collect();
+    remove_dead_instructions(program, |function_idx, offset, instruction| {
+        match instruction {
+            Instruction::Call(_, callee, _) => summaries.get(*callee).is_pure(),
+            Instruction::Div(..) => {
+                safe_divisions[function_idx.0 as usize].contains(&offset)
+            }
+            _ => instruction.dest().is_some(),
+        }
+    })
+}
+
+/// Removes instructions satisfying `removable`, which is given the function
+/// and offset of each, whose destination, if any, is dead right after them,
+/// iterating since removing a use may kill another definition. Returns the
+/// number of removed instructions.
+pub(crate) fn remove_dead_instructions(
+    program: &mut Program,
+    removable: impl Fn(FunctionIdx, usize, &Instruction) -> bool,
+) -> usize {
+    let mut removed = 0;
+    for function_idx in function_indices(program) {
+        loop {
+            let dead = {
+                let function = program.get_function(function_idx);
+                let cfg = build_cfg(&function);
+                let blocks = liveness(&cfg);
+                let instr_facts = liveness_instr(&cfg, &blocks);
+
+                let mut dead = vec![];
+                for (block_idx, block) in cfg.vertices.iter() {
+                    let Some(points) = instr_facts.points(block_idx) else {
+                        continue;
+                    };
+                    for (i, instruction) in
+                        block.instructions.iter().enumerate()
+                    {
+                        if removable(
+                            function_idx,
+                            block.offset + i,
+                            instruction,
+                        ) && instruction.dest().is_none_or(|dest| {
+                            !points[i + 1].contains(dest.0 as usize)
+                        }) {
+                            dead.push(block.offset + i);
+                        }
+                    }
+                }
+                dead
+            };
+            if dead.is_empty() {
+                break;
+            }
+
+            let instructions = program.function_instructions_mut(function_idx);
+            for offset in &dead {
+                instructions[*offset] = Instruction::Nop;
+            }
+            removed += dead.len();
+        }
+    }
+    program.remove_nops();
+    removed
+}
@@ -0,0 +1,177 @@
+use bril::{
+    builder::BasicBlockIdx,
+    ir::{Instruction, Program, Value, Variable},
+};
+use bril_analysis::analysis::{Constants, InstructionExt};
+use std::{
+    collections::HashMap,
+    mem::{self, Discriminant},
+};
+
+use crate::{lvn::is_commutative, rebuild::rebuild, ssa::SsaFunction};
+
+/// What [`global_value_numbering`] removed.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct GvnStats {
+    /// instructions computing a value already available, copies included
+    pub eliminated: usize,
+    /// φ-functions choosing between equal values
+    pub redundant_phis: usize,
+}
+
+/// Removes instructions recomputing a value some dominating instruction
+/// already computed, by numbering values over the dominator tree of every
+/// function in SSA form. Constants are folded along the way.
+pub fn global_value_numbering(program: &Program) -> (Program, GvnStats) {
+    let mut stats = GvnStats::default();
+    let optimized = rebuild(program, |_, cfg| {
+        let mut ssa = SsaFunction::new(cfg);
+        ValueNumbering::default().number_block(&mut ssa, cfg.entry, &mut stats);
+        ssa.into_blocks()
+    });
+    (optimized, stats)
+}
+
+#[derive(PartialEq, Eq, Hash, Clone)]
+enum Key {
+    Const(Value),
+    /// an operation on leaders
+    Op(Discriminant<Instruction>, Vec<u32>),
+    /// a φ-function of a block, `None` for undefined arguments
+    Phi(BasicBlockIdx, Vec<Option<u32>>),
+}
+
+#[derive(Default)]
+struct ValueNumbering {
+    /// maps removed variables to a variable holding the same value
+    leaders: HashMap<u32, Variable>,
+    /// values of leaders assigned a constant
+    constants: HashMap<u32, Value>,
+    /// leaders of the values computed in the dominators of the current block
+    available: HashMap<Key, Variable>,
+}
+
+impl ValueNumbering {
+    fn leader(&self, mut variable: Variable) -> Variable {
+        while let Some(leader) = self.leaders.get(&variable.0) {
+            variable = *leader;
+        }
+        variable
+    }
+
+    fn number_block(
+        &mut self,
+        ssa: &mut SsaFunction,
+        block_idx: BasicBlockIdx,
+        stats: &mut GvnStats,
+    ) {
+        let mut scope = vec![];
+        let block = &mut ssa.blocks[block_idx];
+
+        for phi in mem::take(&mut block.phis) {
+            let args: Vec<_> = phi
+                .args
+                .iter()
+                .map(|(_, arg)| arg.map(|arg| self.leader(arg)))
+                .collect();
+            // values flowing back into the φ-function do not change it
+            let mut incoming: Vec<_> =
+                args.iter().filter(|arg| **arg != Some(phi.dest)).collect();
+            incoming.sort_unstable();
+            incoming.dedup();
+            if let [Some(only)] = incoming[..] {
+                self.leaders.insert(phi.dest.0, *only);
+                stats.redundant_phis += 1;
+                continue;
+            }
+            let key = Key::Phi(
+                block_idx,
+                args.iter().map(|arg| arg.map(|arg| arg.0)).collect(),
+            );
+            if let Some(leader) = self.available.get(&key) {
+                self.leaders.insert(phi.dest.0, *leader);
+                stats.redundant_phis += 1;
+                continue;
+            }
+            self.available.insert(key.clone(), phi.dest);
+            scope.push(key);
+            block.phis.push(phi);
+        }
+
+        for mut instruction in mem::take(&mut block.block.instructions) {
+            for operand in instruction.operands_mut() {
+                *operand = self.leader(*operand);
+            }
+            let dest = match (&instruction, instruction.dest()) {
+                (Instruction::Call(..), _) | (_, None) => {
+                    block.block.instructions.push(instruction);
+                    continue;
+                }
+                (Instruction::Id(_, src), Some(dest)) => {
+                    self.leaders.insert(dest.0, *src);
+                    stats.eliminated += 1;
+                    continue;
+                }
+                (_, Some(dest)) => dest,
+            };
+
+            let key = self.key(&instruction);
+            if let Some(leader) = self.available.get(&key) {
+                self.leaders.insert(dest.0, *leader);
+                stats.eliminated += 1;
+                continue;
+            }
+            if let Key::Const(value) = &key {
+                self.constants.insert(dest.0, value.clone());
+                instruction = Instruction::Const(dest, value.clone());
+            }
+            self.available.insert(key.clone(), dest);
+            scope.push(key);
+            block.block.instructions.push(instruction);
+        }
+
+        for successor in ssa.successors(block_idx) {
+            for phi in &mut ssa.blocks[successor].phis {
+                for (predecessor, arg) in &mut phi.args {
+                    if *predecessor == Some(block_idx) {
+                        *arg = arg.map(|arg| self.leader(arg));
+                    }
+                }
+            }
+        }
+
+        for child in ssa.dominators.children(block_idx).to_vec() {
+            self.number_block(ssa, child, stats);
+        }
+        for key in scope {
+            self.available.remove(&key);
+        }
+    }
+
+    /// The key of the value `instruction` computes, folded to a constant when
+    /// its operands are known.
+    fn key(&self, instruction: &Instruction) -> Key {
+        let constants = instruction
+            .operands()
+            .into_iter()
+            .filter_map(|operand| {
+                let value = self.constants.get(&operand.0)?;
+                Some((operand.0, value.clone()))
+            })
+            .collect();
+        if let Some(value) =
+            Constants::Reachable(constants).evaluate(instruction)
+        {
+            return Key::Const(value);
+        }
+        let mut operands: Vec<_> = instruction
+            .operands()
+            .into_iter()
+            .map(|operand| operand.0)
+            .collect();
+        if is_commutative(instruction) {
+            operands.sort_unstable();
+        }
+        Key::Op(mem::discriminant(instruction), operands)
+    }
+}
@@ -0,0 +1,235 @@
+use bril::ir::{FunctionIdx, Instruction, Program, Variable};
+use bril_analysis::{analysis::InstructionExt, call_graph::CallGraph};
+use bril_cfg::{build_cfg, fresh_label};
+use std::collections::{HashMap, HashSet};
+
+use crate::rebuild::{Block, layout, rebuild};
+
+/// Limits on the calls [`inline_calls`] inlines.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct InlineThreshold {
+    /// callees with more instructions than this are never inlined
+    pub max_callee_size: usize,
+    /// callers are not grown past this many instructions
+    pub max_caller_size: usize,
+}
+
+impl Default for InlineThreshold {
+    fn default() -> Self {
+        Self {
+            max_callee_size: 32,
+            max_caller_size: 1024,
+        }
+    }
+}
+
+/// What [`inline_calls`] changed.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct InlineStats {
+    /// call sites replaced with the body of their callee
+    pub inlined: usize,
+    /// instructions copied from the inlined bodies, including the copies
+    /// binding their parameters
+    pub added_instructions: usize,
+}
+
+/// Replaces calls to functions that are not recursive with a copy of their
+/// body, within `threshold`. The variables of the copy are renumbered past
+/// those of the caller and its labels renamed after the callee, and every
+/// `ret` becomes a jump to the rest of the calling block. Functions are
+/// visited bottom-up, so inlined bodies have their own calls inlined
+/// already.
+pub fn inline_calls(
+    program: &Program,
+    threshold: InlineThreshold,
+) -> (Program, InlineStats) {
+    let call_graph = CallGraph::new(program);
+    let mut stats = InlineStats::default();
+    let mut bodies: Vec<Option<Vec<Block>>> =
+        vec![None; program.functions().count()];
+    for function_idx in call_graph.bottom_up() {
+        let function = program.get_function(function_idx);
+        let cfg = build_cfg(&function);
+        let blocks: Vec<_> = layout(&cfg)
+            .into_iter()
+            .map(|block_idx| Block::from_cfg(&cfg, block_idx))
+            .collect();
+
+        let mut inliner = Inliner {
+            program,
+            call_graph: &call_graph,
+            bodies: &bodies,
+            threshold,
+            used_labels: blocks
+                .iter()
+                .filter_map(|block| block.label.clone())
+                .collect(),
+            next_variable: variable_count(function.parameters, &blocks),
+            size: size(&blocks),
+        };
+        let mut inlined = vec![];
+        for block in blocks {
+            inliner.inline_block(block, &mut stats, &mut inlined);
+        }
+        bodies[function_idx.0 as usize] = Some(inlined);
+    }
+
+    let optimized = rebuild(program, |function_idx, _| {
+        bodies[function_idx.0 as usize].take().unwrap()
+    });
+    (optimized, stats)
+}
+
+struct Inliner<'a> {
+    program: &'a Program,
+    call_graph: &'a CallGraph,
+    /// bodies of the functions visited so far
+    bodies: &'a [Option<Vec<Block>>],
+    threshold: InlineThreshold,
+    used_labels: HashSet<String>,
+    /// first variable number the caller does not use
+    next_variable: u32,
+    /// instructions of the caller
+    size: usize,
+}
+
+impl<'a> Inliner<'a> {
+    /// Body to replace a call to `callee` with, if it is within the
+    /// threshold.
+    fn inlinable(&self, callee: FunctionIdx) -> Option<&'a [Block]> {
+        if self.call_graph.is_recursive(callee) {
+            return None;
+        }
+        let body = self.bodies[callee.0 as usize].as_deref()?;
+        (size(body) <= self.threshold.max_callee_size
+            && self.size + size(body) <= self.threshold.max_caller_size)
+            .then_some(body)
+    }
+
+    /// Pushes `block` to `inlined`, split around the calls to inline.
+    fn inline_block(
+        &mut self,
+        block: Block,
+        stats: &mut InlineStats,
+        inlined: &mut Vec<Block>,
+    ) {
+        let Block {
+            label,
+            instructions,
+            targets,
+        } = block;
+        let mut current = Block {
+            label,
+            ..Default::default()
+        };
+        for instruction in instructions {
+            let Instruction::Call(dest, callee, args) = &instruction else {
+                current.instructions.push(instruction);
+                continue;
+            };
+            let Some(body) = self.inlinable(*callee) else {
+                current.instructions.push(instruction);
+                continue;
+            };
+
+            let callee_function = self.program.get_function(*callee);
+            let offset = self.next_variable;
+            self.next_variable +=
+                variable_count(callee_function.parameters, body);
+            let rename =
+                |variable: Variable| Variable(variable.0 + offset, variable.1);
+
+            // arguments are all read before any parameter is bound, and the
+            // renamed parameters are distinct from them
+            for (parameter, arg) in callee_function.parameters.iter().zip(args)
+            {
+                current
+                    .instructions
+                    .push(Instruction::Id(rename(*parameter), *arg));
+            }
+            let continuation = fresh_label(
+                &mut self.used_labels,
+                &format!("{}.return", callee_function.name),
+            );
+            let labels: HashMap<_, _> = body
+                .iter()
+                .filter_map(|block| block.label.clone())
+                .map(|label| {
+                    let renamed = fresh_label(
+                        &mut self.used_labels,
+                        &format!("{}.{label}", callee_function.name),
+                    );
+                    (label, renamed)
+                })
+                .collect();
+
+            inlined.push(current);
+            for callee_block in body {
+                let mut block = Block {
+                    label: callee_block
+                        .label
+                        .as_ref()
+                        .map(|label| labels[label].clone()),
+                    instructions: callee_block.instructions.clone(),
+                    targets: callee_block
+                        .targets
+                        .iter()
+                        .map(|target| labels[target].clone())
+                        .collect(),
+                };
+                for instruction in &mut block.instructions {
+                    if let Some(dest) = instruction.dest_mut() {
+                        *dest = rename(*dest);
+                    }
+                    for operand in instruction.operands_mut() {
+                        *operand = rename(*operand);
+                    }
+                }
+                if let Some(Instruction::Ret(value)) = block.instructions.last()
+                {
+                    let value = *value;
+                    block.instructions.pop();
+                    if let (Some(dest), Some(value)) = (dest, value) {
+                        block.instructions.push(Instruction::Id(*dest, value));
+                    }
+                    block
+                        .instructions
+                        .push(Instruction::Jmp(Default::default()));
+                    block.targets = vec![continuation.clone()];
+                }
+                inlined.push(block);
+            }
+            // the last block of the body may fall through to the
+            // continuation, which returns nothing
+            current = Block {
+                label: Some(continuation),
+                ..Default::default()
+            };
+
+            let added = size(body) + callee_function.parameters.len();
+            self.size += added;
+            stats.inlined += 1;
+            stats.added_instructions += added;
+        }
+        current.targets = targets;
+        inlined.push(current);
+    }
+}
+
+fn size(blocks: &[Block]) -> usize {
+    blocks.iter().map(|block| block.instructions.len()).sum()
+}
+
+/// One past the largest variable number of a function.
+fn variable_count(parameters: &[Variable], blocks: &[Block]) -> u32 {
+    blocks
+        .iter()
+        .flat_map(|block| &block.instructions)
+        .flat_map(|instruction| {
+            instruction.operands().into_iter().chain(instruction.dest())
+        })
+        .chain(parameters.iter().copied())
+        .map(|variable| variable.0 + 1)
+        .max()
+        .unwrap_or(0)
+}
@@ -0,0 +1,22 @@
+// Copyright (C) 2025 Zihan Li and Ethan Uppal.
+
+pub mod const_fold;
+pub mod copy_prop;
+pub mod dce;
+pub mod gvn;
+pub mod inline;
+pub mod licm;
+pub mod lvn;
+pub mod pre;
+pub mod rebuild;
+pub mod regalloc;
+pub mod simplify_cfg;
+pub mod ssa;
+
+use bril::ir::{FunctionIdx, Program};
+
+pub(crate) fn function_indices(program: &Program) -> Vec<FunctionIdx> {
+    (0..program.functions().count())
+        .map(|idx| FunctionIdx(idx as u32))
+        .collect()
+}
@@ -0,0 +1,345 @@
+use bril::{
+    builder::BasicBlockIdx,
+    ir::{Instruction, Program},
+};
+use bril_analysis::{
+    BlockFacts,
+    analysis::{
+        InstructionExt, liveness_with, reaching_def, reaching_def_instr,
+        uninitialized,
+    },
+    call_graph::CallGraph,
+    dominators::{Dominators, NaturalLoop},
+    summary::Summaries,
+};
+use bril_cfg::{Cfg, fresh_label};
+use fixedbitset::FixedBitSet;
+use slotmap::SecondaryMap;
+use std::{
+    cmp::Reverse,
+    collections::{HashMap, HashSet},
+};
+
+use crate::rebuild::{Block, layout, rebuild};
+
+/// Hoists loop-invariant instructions into a preheader created in front of
+/// the header of their loop, until nothing moves anymore. Only instructions
+/// without side effects that cannot trap are hoisted, including calls to
+/// functions whose summary says they are pure. Returns the optimized program
+/// and the number of hoisted instructions.
+pub fn loop_invariant_code_motion(program: &Program) -> (Program, usize) {
+    let mut optimized = program.clone();
+    let mut total = 0;
+    loop {
+        let mut hoisted = 0;
+        let summaries = Summaries::new(&optimized, &CallGraph::new(&optimized));
+        optimized = rebuild(&optimized, |_, cfg| {
+            hoist_invariants(cfg, &summaries, &mut hoisted)
+        });
+        if hoisted == 0 {
+            return (optimized, total);
+        }
+        total += hoisted;
+    }
+}
+
+/// Hoists the invariants of every loop of `cfg`, each out of the outermost
+/// loop it is invariant in.
+fn hoist_invariants(
+    cfg: &Cfg,
+    summaries: &Summaries,
+    hoisted: &mut usize,
+) -> Vec<Block> {
+    let dominators = Dominators::new(cfg);
+    let mut loops = dominators.natural_loops(cfg);
+    loops.sort_by_key(|natural_loop| Reverse(natural_loop.blocks.len()));
+    let facts = LoopFacts::new(cfg, dominators, summaries);
+
+    let mut moved = HashSet::new();
+    let mut preheaders = HashMap::new();
+    for natural_loop in &loops {
+        let invariants = facts.invariants(natural_loop, &moved);
+        if !invariants.is_empty() {
+            moved.extend(invariants.iter().copied());
+            preheaders.insert(natural_loop.header, (natural_loop, invariants));
+        }
+    }
+    *hoisted += moved.len();
+
+    let order = layout(cfg);
+    let mut blocks: HashMap<_, _> = order
+        .iter()
+        .map(|&block_idx| {
+            let mut block = Block::from_cfg(cfg, block_idx);
+            let offset = cfg.vertices[block_idx].offset;
+            block.instructions = block
+                .instructions
+                .into_iter()
+                .enumerate()
+                .filter(|(i, _)| !moved.contains(&(offset + i)))
+                .map(|(_, instruction)| instruction)
+                .collect();
+            (block_idx, block)
+        })
+        .collect();
+    let mut used_labels: HashSet<String> = blocks
+        .values()
+        .filter_map(|block| block.label.clone())
+        .collect();
+
+    let mut labels = HashMap::new();
+    for (&header_idx, (natural_loop, _)) in &preheaders {
+        let header = blocks[&header_idx].label.clone().unwrap();
+        let label =
+            fresh_label(&mut used_labels, &format!("{header}.preheader"));
+        for predecessor in cfg.predecessors(header_idx) {
+            if !natural_loop.blocks.contains(&predecessor) {
+                for target in &mut blocks.get_mut(&predecessor).unwrap().targets
+                {
+                    if *target == header {
+                        *target = label.clone();
+                    }
+                }
+            }
+        }
+        labels.insert(header_idx, (header, label));
+    }
+
+    let mut lowered: Vec<(Option<BasicBlockIdx>, Block)> = vec![];
+    for block_idx in order {
+        if let Some((natural_loop, invariants)) = preheaders.get(&block_idx) {
+            let (header, label) = labels.remove(&block_idx).unwrap();
+            // a loop block falling through to the header must not fall
+            // into the preheader instead
+            if let Some((Some(previous_idx), previous)) = lowered.last_mut()
+                && natural_loop.blocks.contains(previous_idx)
+                && !matches!(
+                    previous.instructions.last(),
+                    Some(
+                        Instruction::Jmp(_)
+                            | Instruction::Br(..)
+                            | Instruction::Ret(_)
+                    )
+                )
+            {
+                previous
+                    .instructions
+                    .push(Instruction::Jmp(Default::default()));
+                previous.targets = vec![header];
+            }
+            let instructions = invariants
+                .iter()
+                .map(|offset| cfg_instruction(cfg, &facts.locations, *offset))
+                .collect();
+            lowered.push((
+                None,
+                Block {
+                    label: Some(label),
+                    instructions,
+                    targets: vec![],
+                },
+            ));
+        }
+        lowered.push((Some(block_idx), blocks.remove(&block_idx).unwrap()));
+    }
+    lowered.into_iter().map(|(_, block)| block).collect()
+}
+
+fn cfg_instruction(
+    cfg: &Cfg,
+    locations: &HashMap<usize, (BasicBlockIdx, usize)>,
+    offset: usize,
+) -> Instruction {
+    let (block_idx, i) = locations[&offset];
+    cfg.vertices[block_idx].instructions[i].clone()
+}
+
+/// Whole-function facts the invariance of instructions is decided on.
+struct LoopFacts<'a, 'program> {
+    cfg: &'a Cfg<'program>,
+    dominators: Dominators,
+    summaries: &'a Summaries,
+    /// reaching definitions at every point of the reachable blocks
+    reaching: Vec<(BasicBlockIdx, Vec<FixedBitSet>)>,
+    /// liveness where calls only read the arguments their callee reads
+    live: SecondaryMap<BasicBlockIdx, BlockFacts>,
+    uninitialized: SecondaryMap<BasicBlockIdx, BlockFacts>,
+    /// maps the offset of every instruction to its block and index in it
+    locations: HashMap<usize, (BasicBlockIdx, usize)>,
+    /// offsets of the definitions of every variable
+    definitions: HashMap<u32, Vec<usize>>,
+}
+
+impl<'a, 'program> LoopFacts<'a, 'program> {
+    fn new(
+        cfg: &'a Cfg<'program>,
+        dominators: Dominators,
+        summaries: &'a Summaries,
+    ) -> Self {
+        let blocks = reaching_def(cfg);
+        let instr_facts = reaching_def_instr(cfg, &blocks);
+        let reaching = dominators
+            .reverse_postorder()
+            .iter()
+            .map(|&block_idx| {
+                (block_idx, instr_facts.points(block_idx).unwrap())
+            })
+            .collect();
+
+        let mut locations = HashMap::new();
+        let mut definitions: HashMap<u32, Vec<usize>> = HashMap::new();
+        for (block_idx, block) in &cfg.vertices {
+            for (i, instruction) in block.instructions.iter().enumerate() {
+                locations.insert(block.offset + i, (block_idx, i));
+                if let Some(dest) = instruction.dest() {
+                    definitions
+                        .entry(dest.0)
+                        .or_default()
+                        .push(block.offset + i);
+                }
+            }
+        }
+        Self {
+            cfg,
+            dominators,
+            summaries,
+            reaching,
+            live: liveness_with(cfg, |instruction| {
+                summaries.operands(instruction)
+            }),
+            uninitialized: uninitialized(cfg),
+            locations,
+            definitions,
+        }
+    }
+
+    fn in_loop(&self, natural_loop: &NaturalLoop, offset: usize) -> bool {
+        natural_loop.blocks.contains(&self.locations[&offset].0)
+    }
+
+    /// Offsets of the instructions of `natural_loop` that can be hoisted into
+    /// its preheader, in an order respecting their dependencies. `moved`
+    /// instructions are already hoisted out of an enclosing loop.
+    fn invariants(
+        &self,
+        natural_loop: &NaturalLoop,
+        moved: &HashSet<usize>,
+    ) -> Vec<usize> {
+        let header = natural_loop.header;
+        let exits: Vec<_> = natural_loop
+            .blocks
+            .iter()
+            .flat_map(|&block_idx| {
+                self.cfg
+                    .successors(block_idx)
+                    .into_iter()
+                    .filter(|successor| {
+                        !natural_loop.blocks.contains(successor)
+                    })
+                    .map(move |successor| (block_idx, successor))
+            })
+            .collect();
+
+        let mut invariants = vec![];
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (block_idx, points) in &self.reaching {
+                if !natural_loop.blocks.contains(block_idx) {
+                    continue;
+                }
+                let block = &self.cfg.vertices[*block_idx];
+                for (i, instruction) in block.instructions.iter().enumerate() {
+                    let offset = block.offset + i;
+                    if invariants.contains(&offset)
+                        || moved.contains(&offset)
+                        || !self.is_movable(instruction)
+                    {
+                        continue;
+                    }
+                    let dest = instruction.dest().unwrap();
+                    // the only definition in the loop, and no use in the
+                    // loop can see a value from before it
+                    let defined_in_loop = self.definitions[&dest.0]
+                        .iter()
+                        .filter(|offset| self.in_loop(natural_loop, **offset))
+                        .count();
+                    if defined_in_loop != 1
+                        || self.live[header].before.contains(dest.0 as usize)
+                    {
+                        continue;
+                    }
+                    // leaving the loop before the instruction ran must not
+                    // observe the hoisted value
+                    if !exits.iter().all(|(from, to)| {
+                        self.dominators.dominates(*block_idx, *from)
+                            || !self.live[*to].before.contains(dest.0 as usize)
+                    }) {
+                        continue;
+                    }
+                    let operands_invariant =
+                        instruction.operands().iter().all(|operand| {
+                            let reaching: Vec<_> = points[i]
+                                .ones()
+                                .filter(|offset| {
+                                    self.definitions
+                                        .get(&operand.0)
+                                        .is_some_and(|definitions| {
+                                            definitions.contains(offset)
+                                        })
+                                })
+                                .collect();
+                            let inside: Vec<_> = reaching
+                                .iter()
+                                .filter(|offset| {
+                                    self.in_loop(natural_loop, **offset)
+                                        && !moved.contains(offset)
+                                })
+                                .collect();
+                            match inside[..] {
+                                // the value from before the loop has to be
+                                // there in the preheader already
+                                [] => !self.uninitialized[header]
+                                    .before
+                                    .contains(operand.0 as usize),
+                                [offset] => {
+                                    reaching.len() == 1
+                                        && invariants.contains(offset)
+                                }
+                                _ => false,
+                            }
+                        });
+                    if operands_invariant {
+                        invariants.push(offset);
+                        changed = true;
+                    }
+                }
+            }
+        }
+        invariants
+    }
+
+    /// Whether executing `instruction` where it did not run before is
+    /// harmless.
+    fn is_movable(&self, instruction: &Instruction) -> bool {
+        match instruction {
+            Instruction::Add(..)
+            | Instruction::Sub(..)
+            | Instruction::Mul(..)
+            | Instruction::Eq(..)
+            | Instruction::Lt(..)
+            | Instruction::Gt(..)
+            | Instruction::Le(..)
+            | Instruction::Ge(..)
+            | Instruction::Not(..)
+            | Instruction::And(..)
+            | Instruction::Or(..)
+            | Instruction::Const(..)
+            | Instruction::Id(..) => true,
+            Instruction::Call(Some(_), callee, _) => {
+                self.summaries.get(*callee).is_pure()
+            }
+            _ => false,
+        }
+    }
+}
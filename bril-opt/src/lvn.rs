@@ -0,0 +1,193 @@
+use bril::ir::{Instruction, Program, Value, Variable};
+use bril_analysis::analysis::{Constants, InstructionExt};
+use std::{
+    collections::{BTreeMap, HashMap},
+    mem::{self, Discriminant},
+};
+
+use crate::rebuild::{Block, layout, rebuild};
+
+/// Numbers the values computed in every basic block, replacing
+/// recomputations with copies, folding constants and propagating copies.
+/// Commutative operations are numbered regardless of the order of their
+/// operands.
+pub fn local_value_numbering(program: &Program) -> Program {
+    rebuild(program, |_, cfg| {
+        layout(cfg)
+            .into_iter()
+            .map(|block_idx| {
+                let mut block = Block::from_cfg(cfg, block_idx);
+                block.instructions = number_values(&block.instructions);
+                block
+            })
+            .collect()
+    })
+}
+
+/// Runs local value numbering over the instructions of a single block.
+pub fn number_values(instructions: &[Instruction]) -> Vec<Instruction> {
+    let mut table = ValueTable::default();
+    let mut numbered = vec![];
+    for instruction in instructions {
+        let mut instruction = instruction.clone();
+        if matches!(instruction, Instruction::Nop) {
+            continue;
+        }
+        for operand in instruction.operands_mut() {
+            let number = table.number_of(*operand);
+            if let Some(canonical) = table.values[number].canonical {
+                *operand = canonical;
+            }
+        }
+
+        let Some(dest) = instruction.dest() else {
+            numbered.push(instruction);
+            continue;
+        };
+        let Some(number) = table.value_of(&instruction) else {
+            // calls are not known to produce the same value twice
+            let number = table.push(dest, None);
+            table.assign(dest, number);
+            numbered.push(instruction);
+            continue;
+        };
+        if table.variables.get(&dest.0).map(|(_, held)| *held) == Some(number) {
+            // the destination already holds the value
+            continue;
+        }
+        let value = &table.values[number];
+        if let Some(constant) = &value.constant {
+            instruction = Instruction::Const(dest, constant.clone());
+        } else if let Some(canonical) = value.canonical
+            && canonical != dest
+        {
+            instruction = Instruction::Id(dest, canonical);
+        }
+        table.assign(dest, number);
+        numbered.push(instruction);
+    }
+    numbered
+}
+
+#[derive(PartialEq, Eq, Hash)]
+enum Key {
+    Const(Value),
+    /// an operation on numbered operands
+    Op(Discriminant<Instruction>, Vec<usize>),
+}
+
+struct NumberedValue {
+    /// a variable holding the value, if one still does
+    canonical: Option<Variable>,
+    constant: Option<Value>,
+}
+
+#[derive(Default)]
+struct ValueTable {
+    values: Vec<NumberedValue>,
+    numbers: HashMap<Key, usize>,
+    /// maps every variable to the number of the value it holds
+    variables: HashMap<u32, (Variable, usize)>,
+}
+
+impl ValueTable {
+    fn push(&mut self, canonical: Variable, constant: Option<Value>) -> usize {
+        self.values.push(NumberedValue {
+            canonical: Some(canonical),
+            constant,
+        });
+        self.values.len() - 1
+    }
+
+    /// The number of the value `variable` holds. Variables read before being
+    /// assigned in the block hold values of their own.
+    fn number_of(&mut self, variable: Variable) -> usize {
+        if let Some((_, number)) = self.variables.get(&variable.0) {
+            return *number;
+        }
+        let number = self.push(variable, None);
+        self.variables.insert(variable.0, (variable, number));
+        number
+    }
+
+    fn assign(&mut self, dest: Variable, number: usize) {
+        if let Some((_, previous)) =
+            self.variables.insert(dest.0, (dest, number))
+            && previous != number
+            && self.values[previous].canonical == Some(dest)
+        {
+            // the overwritten value may still be held by another variable
+            self.values[previous].canonical = self
+                .variables
+                .values()
+                .find(|(_, number)| *number == previous)
+                .map(|(variable, _)| *variable);
+        }
+        if self.values[number].canonical.is_none() {
+            self.values[number].canonical = Some(dest);
+        }
+    }
+
+    /// The number of the value `instruction` computes, numbering it if it is
+    /// new. `None` for calls.
+    fn value_of(&mut self, instruction: &Instruction) -> Option<usize> {
+        match instruction {
+            Instruction::Call(..) => return None,
+            Instruction::Id(_, src) => return Some(self.number_of(*src)),
+            _ => {}
+        }
+        let dest = instruction.dest()?;
+        let key = self.key(instruction);
+        if let Some(&number) = self.numbers.get(&key) {
+            return Some(number);
+        }
+        let constant = match &key {
+            Key::Const(value) => Some(value.clone()),
+            Key::Op(..) => None,
+        };
+        let number = self.push(dest, constant);
+        self.numbers.insert(key, number);
+        Some(number)
+    }
+
+    /// The key of the value `instruction` computes, folded to a constant when
+    /// its operands are known.
+    fn key(&mut self, instruction: &Instruction) -> Key {
+        let operands = instruction.operands();
+        let numbers: Vec<usize> = operands
+            .iter()
+            .map(|operand| self.number_of(*operand))
+            .collect();
+
+        let constants: BTreeMap<u32, Value> = operands
+            .iter()
+            .zip(&numbers)
+            .filter_map(|(operand, number)| {
+                let constant = self.values[*number].constant.clone()?;
+                Some((operand.0, constant))
+            })
+            .collect();
+        if let Some(value) =
+            Constants::Reachable(constants).evaluate(instruction)
+        {
+            return Key::Const(value);
+        }
+
+        let mut numbers = numbers;
+        if is_commutative(instruction) {
+            numbers.sort_unstable();
+        }
+        Key::Op(mem::discriminant(instruction), numbers)
+    }
+}
+
+pub(crate) fn is_commutative(instruction: &Instruction) -> bool {
+    matches!(
+        instruction,
+        Instruction::Add(..)
+            | Instruction::Mul(..)
+            | Instruction::Eq(..)
+            | Instruction::And(..)
+            | Instruction::Or(..)
+    )
+}
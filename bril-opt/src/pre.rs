@@ -0,0 +1,243 @@
+use bril::ir::{Instruction, Program, Variable};
+use bril_analysis::analysis::{InstructionExt, lazy_code_motion};
+use bril_cfg::{Cfg, fresh_label};
+use std::collections::{HashMap, HashSet};
+
+use crate::rebuild::{Block, layout, rebuild};
+
+/// What [`partial_redundancy_elimination`] changed.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct PreStats {
+    /// computations placed into a temporary
+    pub inserted: usize,
+    /// computations replaced with a copy of a temporary
+    pub replaced: usize,
+}
+
+/// Removes computations that are redundant on some paths by moving them to
+/// where they are needed on every path, as late as possible, with lazy code
+/// motion. Blocks inserted on the edges into joins are only kept for
+/// critical edges something is computed on.
+pub fn partial_redundancy_elimination(
+    program: &Program,
+) -> (Program, PreStats) {
+    let mut splits = HashMap::new();
+    let split = rebuild(program, |function_idx, cfg| {
+        let (blocks, labels) = split_joins(cfg);
+        splits.insert(function_idx, labels);
+        blocks
+    });
+
+    let mut stats = PreStats::default();
+    let optimized = rebuild(&split, |function_idx, cfg| {
+        let blocks = move_computations(cfg, &mut stats);
+        unsplit(blocks, &splits[&function_idx])
+    });
+    (optimized, stats)
+}
+
+/// Places an empty block on every edge into a block with several
+/// predecessors, right after the predecessor, and in front of an entry that
+/// has predecessors. Returns the blocks and the labels of those added.
+fn split_joins(cfg: &Cfg) -> (Vec<Block>, HashSet<String>) {
+    let order = layout(cfg);
+    let mut blocks: HashMap<_, _> = order
+        .iter()
+        .map(|&block_idx| (block_idx, Block::from_cfg(cfg, block_idx)))
+        .collect();
+    let mut used_labels: HashSet<String> = blocks
+        .values()
+        .filter_map(|block| block.label.clone())
+        .collect();
+    let mut split_labels = HashSet::new();
+
+    let mut split_blocks: HashMap<_, Vec<Block>> = HashMap::new();
+    for &block_idx in &order {
+        let mut predecessors = cfg.predecessors(block_idx);
+        if predecessors.len() < 2 {
+            continue;
+        }
+        predecessors.sort_unstable();
+        predecessors.dedup();
+        // only labeled blocks can have several predecessors
+        let target = blocks[&block_idx].label.clone().unwrap();
+        for predecessor in predecessors {
+            let label =
+                fresh_label(&mut used_labels, &format!("{target}.split"));
+            for redirected in &mut blocks.get_mut(&predecessor).unwrap().targets
+            {
+                if *redirected == target {
+                    *redirected = label.clone();
+                }
+            }
+            split_labels.insert(label.clone());
+            split_blocks.entry(predecessor).or_default().push(Block {
+                label: Some(label),
+                instructions: vec![Instruction::Jmp(Default::default())],
+                targets: vec![target.clone()],
+            });
+        }
+    }
+
+    let mut split = vec![];
+    if !cfg.predecessors(cfg.entry).is_empty() {
+        let entry = blocks[&cfg.entry].label.as_deref().unwrap_or("entry");
+        let label = fresh_label(&mut used_labels, &format!("{entry}.split"));
+        split_labels.insert(label.clone());
+        split.push(Block {
+            label: Some(label),
+            ..Default::default()
+        });
+    }
+    for block_idx in order {
+        split.push(blocks.remove(&block_idx).unwrap());
+        split.extend(split_blocks.remove(&block_idx).unwrap_or_default());
+    }
+    (split, split_labels)
+}
+
+/// Computes every expression into a temporary where lazy code motion places
+/// it and replaces the computations made redundant with copies of it.
+fn move_computations(cfg: &Cfg, stats: &mut PreStats) -> Vec<Block> {
+    let lcm = lazy_code_motion(cfg);
+    let mut next_var = 0;
+    for parameter in &cfg.prototype.parameters {
+        next_var = next_var.max(parameter.0 + 1);
+    }
+    for block in cfg.vertices.values() {
+        for instruction in block.instructions {
+            for variable in
+                instruction.operands().into_iter().chain(instruction.dest())
+            {
+                next_var = next_var.max(variable.0 + 1);
+            }
+        }
+    }
+    let mut temporaries: Vec<Option<Variable>> =
+        vec![None; lcm.expressions.len()];
+    let mut temporary = |expression: usize| {
+        *temporaries[expression].get_or_insert_with(|| {
+            let ty = lcm.expressions[expression].dest().unwrap().1;
+            next_var += 1;
+            Variable(next_var - 1, ty)
+        })
+    };
+
+    let mut blocks = vec![];
+    for block_idx in layout(cfg) {
+        let mut block = Block::from_cfg(cfg, block_idx);
+        let (Some(insert), Some(replace)) =
+            (lcm.insert.get(block_idx), lcm.replace.get(block_idx))
+        else {
+            blocks.push(block);
+            continue;
+        };
+        let mut instructions: Vec<_> = insert
+            .ones()
+            .map(|expression| {
+                let mut computation = lcm.expressions[expression].clone();
+                *computation.dest_mut().unwrap() = temporary(expression);
+                computation
+            })
+            .collect();
+        stats.inserted += instructions.len();
+
+        // only the first computation in the block is replaced, and only
+        // before any of its operands is redefined
+        let mut seen = HashSet::new();
+        let mut killed: HashSet<u32> = HashSet::new();
+        for instruction in block.instructions {
+            let expression = lcm.expression(&instruction).filter(|_| {
+                instruction
+                    .operands()
+                    .iter()
+                    .all(|operand| !killed.contains(&operand.0))
+            });
+            if let Some(dest) = instruction.dest() {
+                killed.insert(dest.0);
+            }
+            match expression {
+                Some(expression)
+                    if seen.insert(expression)
+                        && replace.contains(expression) =>
+                {
+                    let dest = instruction.dest().unwrap();
+                    instructions
+                        .push(Instruction::Id(dest, temporary(expression)));
+                    stats.replaced += 1;
+                }
+                _ => instructions.push(instruction),
+            }
+        }
+        block.instructions = instructions;
+        blocks.push(block);
+    }
+    blocks
+}
+
+/// Removes the blocks [`split_joins`] added, moving what was computed in
+/// them into their predecessor unless it ends with a `br`.
+fn unsplit(blocks: Vec<Block>, split_labels: &HashSet<String>) -> Vec<Block> {
+    let mut unsplit: Vec<Block> = vec![];
+    let mut predecessor_idx = None;
+    let mut retargeted = HashMap::new();
+    let mut blocks = blocks.into_iter().peekable();
+    while let Some(mut block) = blocks.next() {
+        if !block
+            .label
+            .as_ref()
+            .is_some_and(|label| split_labels.contains(label))
+        {
+            predecessor_idx = Some(unsplit.len());
+            unsplit.push(block);
+            continue;
+        }
+        // in front of the entry, entered by falling through only
+        let Some(predecessor_idx) = predecessor_idx else {
+            if !block.instructions.is_empty() {
+                unsplit.push(block);
+            }
+            continue;
+        };
+        let predecessor = &mut unsplit[predecessor_idx];
+        if matches!(predecessor.instructions.last(), Some(Instruction::Br(..)))
+        {
+            if block.instructions.len() > 1 {
+                unsplit.push(block);
+            } else {
+                retargeted
+                    .insert(block.label.unwrap(), block.targets[0].clone());
+            }
+            continue;
+        }
+
+        let target = block.targets.pop().unwrap();
+        block.instructions.pop();
+        match predecessor.instructions.last() {
+            Some(Instruction::Jmp(_)) => {
+                let at = predecessor.instructions.len() - 1;
+                predecessor.instructions.splice(at..at, block.instructions);
+                predecessor.targets = vec![target];
+            }
+            // fell through into the split block
+            _ => {
+                predecessor.instructions.extend(block.instructions);
+                let next = blocks.peek().and_then(|next| next.label.as_ref());
+                if next != Some(&target) {
+                    predecessor
+                        .instructions
+                        .push(Instruction::Jmp(Default::default()));
+                    predecessor.targets = vec![target];
+                }
+            }
+        }
+    }
+    for block in &mut unsplit {
+        for target in &mut block.targets {
+            if let Some(original) = retargeted.get(target) {
+                *target = original.clone();
+            }
+        }
+    }
+    unsplit
+}
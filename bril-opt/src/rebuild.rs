@@ -0,0 +1,103 @@
+use bril::{
+    builder::{BasicBlockBuilder, BasicBlockIdx, ProgramBuilder},
+    ir::{FunctionIdx, Instruction, Program},
+};
+use bril_cfg::{Cfg, Exit, build_cfg};
+use std::collections::HashSet;
+
+use crate::function_indices;
+
+/// A basic block of a function being rebuilt. Calls refer to the functions
+/// of the original program, which are resolved by name when lowering.
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct Block {
+    /// unlabeled blocks can only be entered by falling through
+    pub label: Option<String>,
+    /// a `jmp` or `br` may only come last
+    pub instructions: Vec<Instruction>,
+    /// labels the final `jmp` or `br` targets, in the order of its operands
+    pub targets: Vec<String>,
+}
+
+impl Block {
+    /// A copy of `block_idx` of `cfg`.
+    pub fn from_cfg(cfg: &Cfg, block_idx: BasicBlockIdx) -> Self {
+        let block = &cfg.vertices[block_idx];
+        let label_name = |block_idx: BasicBlockIdx| {
+            cfg.vertices[block_idx].label.unwrap().name.to_string()
+        };
+        let targets = match (block.instructions.last(), &cfg.edges[block_idx]) {
+            (Some(Instruction::Jmp(_)), Exit::Unconditional(target)) => {
+                vec![label_name(*target)]
+            }
+            (
+                Some(Instruction::Br(..)),
+                Exit::Conditional { if_true, if_false },
+            ) => vec![label_name(*if_true), label_name(*if_false)],
+            _ => vec![],
+        };
+        Self {
+            label: block.label.map(|label| label.name.to_string()),
+            instructions: block.instructions.to_vec(),
+            targets,
+        }
+    }
+}
+
+/// Blocks of `cfg` in the order they appear in the function, which
+/// [`build_cfg`] inserts them in.
+pub fn layout(cfg: &Cfg) -> Vec<BasicBlockIdx> {
+    cfg.vertices.keys().collect()
+}
+
+/// Rebuilds every function of `program` from the blocks `rewrite` returns
+/// for its CFG, laid out in the returned order.
+pub fn rebuild(
+    program: &Program,
+    mut rewrite: impl FnMut(FunctionIdx, &Cfg) -> Vec<Block>,
+) -> Program {
+    let mut program_builder = ProgramBuilder::new();
+    for function_idx in function_indices(program) {
+        let function = program.get_function(function_idx);
+        let cfg = build_cfg(&function);
+        let blocks = rewrite(function_idx, &cfg);
+
+        let mut function_builder =
+            program_builder.new_function(function.name.to_string());
+        function_builder.parameters(function.parameters);
+        if let Some(return_type) = function.return_type {
+            function_builder.return_type(return_type);
+        }
+        // every block ends up labeled, so unlabeled ones are given names the
+        // function does not use yet
+        let used: HashSet<String> = blocks
+            .iter()
+            .filter_map(|block| block.label.clone())
+            .collect();
+        let mut fresh = (0..)
+            .map(|i| format!("L{i}"))
+            .filter(|name| !used.contains(name));
+        for block in blocks {
+            let label = block.label.unwrap_or_else(|| fresh.next().unwrap());
+            let mut block_builder = BasicBlockBuilder::with_label(label);
+            for instruction in block.instructions {
+                let symbols = match &instruction {
+                    Instruction::Jmp(_) | Instruction::Br(..) => {
+                        block.targets.clone()
+                    }
+                    Instruction::Call(_, callee, _) => {
+                        vec![program.get_function(*callee).name.to_string()]
+                    }
+                    _ => {
+                        block_builder.add_instr(instruction);
+                        continue;
+                    }
+                };
+                block_builder.add_patched_instr(instruction, symbols);
+            }
+            function_builder.seal_block(block_builder);
+        }
+        function_builder.finish();
+    }
+    program_builder.finish()
+}
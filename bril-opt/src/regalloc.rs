@@ -0,0 +1,444 @@
+use bril::ir::{Function, Instruction, Program, Variable};
+use bril_analysis::{
+    analysis::InstructionExt, interference::InterferenceGraph,
+};
+use bril_cfg::{Exit, OwnedBlock, OwnedCfg, build_cfg, lower_program};
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    fmt, mem,
+};
+
+/// What [`allocate_registers`] did.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct RegAllocStats {
+    /// variables moved to a spill slot
+    pub spilled: usize,
+    /// copies removed because both sides ended up in the same register
+    pub coalesced: usize,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum RegAllocError {
+    /// an instruction reads more variables, or the function takes more
+    /// parameters, than there are registers, or the variables loaded from
+    /// and stored to spill slots around an instruction do not fit in them
+    TooFewRegisters { function: String, needed: usize },
+}
+
+impl fmt::Display for RegAllocError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TooFewRegisters { function, needed } => write!(
+                formatter,
+                "@{function} needs at least {needed} registers"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for RegAllocError {}
+
+/// Rewrites the variables of every function to the registers `0` to
+/// `registers - 1` by coloring their interference graph, Chaitin-Briggs
+/// style. Copies are coalesced when that keeps the graph colorable.
+/// Variables that cannot be given a register are spilled to slots, numbered
+/// from `registers` on, which are only accessed by copies from and to
+/// registers right around the instructions using the variable. Unreachable
+/// blocks are removed.
+pub fn allocate_registers(
+    program: &Program,
+    registers: usize,
+) -> Result<(Program, RegAllocStats), RegAllocError> {
+    for function in program.functions() {
+        let needed = needed_registers(&function);
+        if needed > registers {
+            return Err(RegAllocError::TooFewRegisters {
+                function: function.name.to_string(),
+                needed,
+            });
+        }
+    }
+
+    let mut stats = RegAllocStats::default();
+    let mut allocated = program.clone();
+    let mut spills: Vec<Spills> =
+        program.functions().map(|_| Spills::default()).collect();
+    let mut done = vec![false; spills.len()];
+    // spilling shortens live ranges, so color again until nothing spills
+    while !done.iter().all(|done| *done) {
+        let mut cfgs = vec![];
+        for (i, function) in allocated.functions().enumerate() {
+            let cfg = build_cfg(&function);
+            let mut owned = OwnedCfg::from_cfg(&cfg);
+            if !done[i] {
+                owned.remove_unreachable();
+                let graph = InterferenceGraph::new(&cfg);
+                match spills[i].color(&graph, &owned, registers) {
+                    Ok(colors) => {
+                        stats.coalesced +=
+                            spills[i].assign(&mut owned, &colors, registers);
+                        done[i] = true;
+                    }
+                    Err(spilled) if spilled.is_empty() => {
+                        return Err(RegAllocError::TooFewRegisters {
+                            function: function.name.to_string(),
+                            needed: spills[i].unspillable_registers(&graph),
+                        });
+                    }
+                    Err(spilled) => {
+                        stats.spilled += spilled.len();
+                        spills[i].spill(&mut owned, &spilled);
+                    }
+                }
+            }
+            cfgs.push(owned);
+        }
+        allocated = lower_program(&allocated, &cfgs);
+    }
+    Ok((allocated, stats))
+}
+
+/// Registers every spilled variable of `function` may have to be loaded
+/// into at once.
+fn needed_registers(function: &Function) -> usize {
+    function
+        .instructions
+        .iter()
+        .map(|instruction| {
+            let mut operands: Vec<_> = instruction
+                .operands()
+                .into_iter()
+                .map(|operand| operand.0)
+                .collect();
+            operands.sort_unstable();
+            operands.dedup();
+            operands
+                .len()
+                .max(usize::from(instruction.dest().is_some()))
+        })
+        .chain([function.parameters.len()])
+        .max()
+        .unwrap_or(0)
+}
+
+/// Spill state of a function, kept across rounds of coloring.
+#[derive(Default)]
+struct Spills {
+    /// spill slots in the order they were created
+    slots: Vec<u32>,
+    /// variables living from a load or until a store of a slot only, which
+    /// spilling would not help
+    unspillable: HashSet<u32>,
+}
+
+impl Spills {
+    /// Colors the variables of `graph` with `registers` colors. Returns the
+    /// variables to spill if it fails.
+    fn color(
+        &self,
+        graph: &InterferenceGraph,
+        cfg: &OwnedCfg,
+        registers: usize,
+    ) -> Result<HashMap<u32, usize>, Vec<u32>> {
+        let slots: HashSet<_> = self.slots.iter().copied().collect();
+        let mut adjacency: HashMap<u32, HashSet<u32>> = graph
+            .nodes()
+            .filter(|variable| !slots.contains(variable))
+            .map(|variable| {
+                let neighbors = graph
+                    .neighbors(variable)
+                    .filter(|neighbor| !slots.contains(neighbor))
+                    .collect();
+                (variable, neighbors)
+            })
+            .collect();
+
+        // coalesce copies as long as the merged variable has fewer than
+        // `registers` neighbors of significant degree (Briggs)
+        let mut aliases = HashMap::new();
+        let find = |aliases: &HashMap<u32, u32>, mut variable| {
+            while let Some(alias) = aliases.get(&variable) {
+                variable = *alias;
+            }
+            variable
+        };
+        for &(dest, src) in &graph.moves {
+            let (kept, merged) = (find(&aliases, dest), find(&aliases, src));
+            if kept == merged
+                || [kept, merged].iter().any(|variable| {
+                    slots.contains(variable)
+                        || self.unspillable.contains(variable)
+                })
+                || adjacency[&kept].contains(&merged)
+            {
+                continue;
+            }
+            let significant = adjacency[&kept]
+                .union(&adjacency[&merged])
+                .filter(|neighbor| adjacency[neighbor].len() >= registers)
+                .count();
+            if significant >= registers {
+                continue;
+            }
+            for neighbor in adjacency.remove(&merged).unwrap() {
+                let neighbors = adjacency.get_mut(&neighbor).unwrap();
+                neighbors.remove(&merged);
+                neighbors.insert(kept);
+                adjacency.get_mut(&kept).unwrap().insert(neighbor);
+            }
+            aliases.insert(merged, kept);
+        }
+
+        let mut costs: HashMap<u32, usize> = HashMap::new();
+        for block in cfg.vertices.values() {
+            for instruction in &block.instructions {
+                for variable in
+                    instruction.operands().into_iter().chain(instruction.dest())
+                {
+                    *costs.entry(find(&aliases, variable.0)).or_default() += 1;
+                }
+            }
+            if let Some(condition) = block.condition {
+                *costs.entry(find(&aliases, condition.0)).or_default() += 1;
+            }
+        }
+
+        // simplify, optimistically pushing spill candidates as well
+        let mut degrees: HashMap<_, _> = adjacency
+            .iter()
+            .map(|(variable, neighbors)| (*variable, neighbors.len()))
+            .collect();
+        let mut remaining: BTreeSet<_> = adjacency.keys().copied().collect();
+        let mut stack = vec![];
+        while !remaining.is_empty() {
+            let next = remaining
+                .iter()
+                .find(|variable| degrees[variable] < registers)
+                .or_else(|| {
+                    remaining.iter().min_by_key(|variable| {
+                        let cost = costs.get(variable).copied().unwrap_or(0);
+                        (
+                            self.unspillable.contains(variable),
+                            cost * 1000 / (degrees[variable] + 1),
+                        )
+                    })
+                })
+                .copied()
+                .unwrap();
+            remaining.remove(&next);
+            for neighbor in &adjacency[&next] {
+                if remaining.contains(neighbor) {
+                    *degrees.get_mut(neighbor).unwrap() -= 1;
+                }
+            }
+            stack.push(next);
+        }
+
+        let mut colors = HashMap::new();
+        let mut uncolored = vec![];
+        while let Some(variable) = stack.pop() {
+            let used: HashSet<_> = adjacency[&variable]
+                .iter()
+                .filter_map(|neighbor| colors.get(neighbor))
+                .collect();
+            match (0..registers).find(|color| !used.contains(color)) {
+                Some(color) => {
+                    colors.insert(variable, color);
+                }
+                None => uncolored.push(variable),
+            }
+        }
+
+        let members = |representatives: &HashSet<u32>| -> Vec<u32> {
+            graph
+                .nodes()
+                .filter(|variable| {
+                    !slots.contains(variable)
+                        && !self.unspillable.contains(variable)
+                        && representatives.contains(&find(&aliases, *variable))
+                })
+                .collect()
+        };
+        if uncolored.is_empty() {
+            return Ok(graph
+                .nodes()
+                .filter(|variable| !slots.contains(variable))
+                .map(|variable| (variable, colors[&find(&aliases, variable)]))
+                .collect());
+        }
+        let spilled = members(&uncolored.iter().copied().collect());
+        if !spilled.is_empty() {
+            return Err(spilled);
+        }
+        // only short-lived variables are left uncolored, so make room for
+        // them around their loads and stores
+        Err(members(
+            &uncolored
+                .iter()
+                .flat_map(|variable| adjacency[variable].iter().copied())
+                .collect(),
+        ))
+    }
+
+    /// Registers the unspillable variables of `graph` need at once. They
+    /// only live right around the instruction they are loaded or stored
+    /// for, so each of them forms a group with the unspillable variables it
+    /// interferes with, which all interfere with each other.
+    fn unspillable_registers(&self, graph: &InterferenceGraph) -> usize {
+        self.unspillable
+            .iter()
+            .map(|variable| {
+                let interfering = graph
+                    .neighbors(*variable)
+                    .filter(|neighbor| self.unspillable.contains(neighbor))
+                    .count();
+                interfering + 1
+            })
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Stores `spilled` to fresh slots after every definition and loads them
+    /// into fresh variables before every use.
+    fn spill(&mut self, cfg: &mut OwnedCfg, spilled: &[u32]) {
+        // slots of earlier rounds are still used by their loads and stores
+        let mut next_var = cfg
+            .vertices
+            .values()
+            .flat_map(|block| {
+                block.instructions.iter().flat_map(|instruction| {
+                    instruction.operands().into_iter().chain(instruction.dest())
+                })
+            })
+            .chain(cfg.prototype.parameters.iter().copied())
+            .map(|variable| variable.0 + 1)
+            .max()
+            .unwrap_or(0);
+        let mut fresh = || {
+            next_var += 1;
+            next_var - 1
+        };
+        let slots: HashMap<u32, u32> = spilled
+            .iter()
+            .map(|variable| (*variable, fresh()))
+            .collect();
+        self.slots
+            .extend(spilled.iter().map(|variable| slots[variable]));
+
+        for block in cfg.vertices.values_mut() {
+            let mut rewritten = vec![];
+            for mut instruction in mem::take(&mut block.instructions) {
+                let mut loaded = HashMap::new();
+                for operand in instruction.operands_mut() {
+                    let Some(slot) = slots.get(&operand.0) else {
+                        continue;
+                    };
+                    let temporary =
+                        *loaded.entry(operand.0).or_insert_with(|| {
+                            let temporary = Variable(fresh(), operand.1);
+                            rewritten.push(Instruction::Id(
+                                temporary,
+                                Variable(*slot, operand.1),
+                            ));
+                            temporary
+                        });
+                    self.unspillable.insert(temporary.0);
+                    *operand = temporary;
+                }
+                let store = instruction.dest_mut().and_then(|dest| {
+                    let slot = slots.get(&dest.0)?;
+                    let temporary = Variable(fresh(), dest.1);
+                    self.unspillable.insert(temporary.0);
+                    let store =
+                        Instruction::Id(Variable(*slot, dest.1), temporary);
+                    *dest = temporary;
+                    Some(store)
+                });
+                rewritten.push(instruction);
+                rewritten.extend(store);
+            }
+            if let Some(condition) = &mut block.condition
+                && let Some(slot) = slots.get(&condition.0)
+            {
+                let temporary = Variable(fresh(), condition.1);
+                self.unspillable.insert(temporary.0);
+                rewritten.push(Instruction::Id(
+                    temporary,
+                    Variable(*slot, condition.1),
+                ));
+                *condition = temporary;
+            }
+            block.instructions = rewritten;
+        }
+
+        // parameters are stored once, when the function is entered
+        let stores: Vec<_> = cfg
+            .prototype
+            .parameters
+            .iter()
+            .filter_map(|parameter| {
+                let slot = slots.get(&parameter.0)?;
+                self.unspillable.insert(parameter.0);
+                Some(Instruction::Id(Variable(*slot, parameter.1), *parameter))
+            })
+            .collect();
+        if stores.is_empty() {
+            return;
+        }
+        if cfg.predecessors(cfg.entry).is_empty() {
+            let entry = &mut cfg.vertices[cfg.entry].instructions;
+            entry.splice(0..0, stores);
+        } else {
+            let block = OwnedBlock {
+                instructions: stores,
+                ..Default::default()
+            };
+            let entry =
+                cfg.add_block(block, Exit::Unconditional(cfg.entry), None);
+            cfg.layout.pop();
+            cfg.layout.insert(0, entry);
+            cfg.entry = entry;
+        }
+    }
+
+    /// Renames every variable to its register, or its slot. Returns the
+    /// number of copies removed because both sides share a register.
+    fn assign(
+        &self,
+        cfg: &mut OwnedCfg,
+        colors: &HashMap<u32, usize>,
+        registers: usize,
+    ) -> usize {
+        let slots: HashMap<_, _> = self
+            .slots
+            .iter()
+            .enumerate()
+            .map(|(i, slot)| (*slot, (registers + i) as u32))
+            .collect();
+        let rename = |variable: &mut Variable| {
+            variable.0 = match slots.get(&variable.0) {
+                Some(slot) => *slot,
+                None => colors[&variable.0] as u32,
+            };
+        };
+
+        let mut coalesced = 0;
+        cfg.prototype.parameters.iter_mut().for_each(rename);
+        for block in cfg.vertices.values_mut() {
+            for instruction in &mut block.instructions {
+                instruction.operands_mut().into_iter().for_each(rename);
+                instruction.dest_mut().into_iter().for_each(rename);
+            }
+            block.condition.iter_mut().for_each(rename);
+            block.instructions.retain(|instruction| {
+                let redundant = matches!(
+                    instruction,
+                    Instruction::Id(dest, src) if dest.0 == src.0
+                );
+                coalesced += usize::from(redundant);
+                !redundant
+            });
+        }
+        coalesced
+    }
+}
@@ -0,0 +1,61 @@
+use bril::ir::Program;
+use bril_cfg::{Exit, OwnedCfg, build_cfg, lower_program};
+
+/// Number of blocks of a program before and after [`simplify_cfg`].
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct SimplifyStats {
+    pub blocks_before: usize,
+    pub blocks_after: usize,
+}
+
+/// Simplifies the CFG of every function with [`simplify`].
+pub fn simplify_cfg(program: &Program) -> (Program, SimplifyStats) {
+    let mut stats = SimplifyStats::default();
+    let cfgs: Vec<_> = program
+        .functions()
+        .map(|function| {
+            let mut cfg = OwnedCfg::from_cfg(&build_cfg(&function));
+            stats.blocks_before += cfg.vertices.len();
+            simplify(&mut cfg);
+            stats.blocks_after += cfg.vertices.len();
+            cfg
+        })
+        .collect();
+    (lower_program(program, &cfgs), stats)
+}
+
+/// Threads jumps through empty blocks, turns branches to a single block into
+/// jumps, removes unreachable blocks and merges straight-line chains, until
+/// nothing changes.
+pub fn simplify(cfg: &mut OwnedCfg) {
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for block_idx in cfg.layout.clone() {
+            if let Exit::Conditional { if_true, if_false } =
+                cfg.edges[block_idx]
+                && if_true == if_false
+            {
+                cfg.vertices[block_idx].condition = None;
+                cfg.set_exit(block_idx, Exit::Unconditional(if_true));
+                changed = true;
+            }
+
+            let Exit::Unconditional(target) = cfg.edges[block_idx] else {
+                continue;
+            };
+            if block_idx == cfg.entry
+                || target == block_idx
+                || !cfg.vertices[block_idx].instructions.is_empty()
+            {
+                continue;
+            }
+            for predecessor in cfg.predecessors(block_idx) {
+                cfg.redirect(predecessor, block_idx, target);
+                changed = true;
+            }
+        }
+        changed |= cfg.remove_unreachable() > 0;
+        changed |= cfg.merge_straight_line() > 0;
+    }
+}
@@ -0,0 +1,343 @@
+use bril::{
+    builder::BasicBlockIdx,
+    ir::{Instruction, Type, Variable},
+};
+use bril_analysis::{
+    analysis::{InstructionExt, liveness},
+    dominators::Dominators,
+};
+use bril_cfg::{Cfg, fresh_label};
+use slotmap::SecondaryMap;
+use std::collections::{HashMap, HashSet};
+
+use crate::rebuild::{Block, layout};
+
+/// A φ-function at the start of a block, choosing a value by the edge the
+/// block was entered through.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Phi {
+    pub dest: Variable,
+    /// value flowing in from every predecessor, `None` standing for the
+    /// function entry. The value is `None` where the variable is undefined.
+    pub args: Vec<(Option<BasicBlockIdx>, Option<Variable>)>,
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct SsaBlock {
+    pub phis: Vec<Phi>,
+    pub block: Block,
+}
+
+/// A function in SSA form, where every variable is assigned exactly once.
+/// Only blocks reachable from the entry are kept.
+pub struct SsaFunction<'cfg, 'program> {
+    pub cfg: &'cfg Cfg<'program>,
+    pub dominators: Dominators,
+    pub blocks: SecondaryMap<BasicBlockIdx, SsaBlock>,
+    next_var: u32,
+}
+
+impl<'cfg, 'program> SsaFunction<'cfg, 'program> {
+    /// Converts the function of `cfg` to pruned SSA form. Parameters keep
+    /// their names, every other definition is given a fresh variable.
+    pub fn new(cfg: &'cfg Cfg<'program>) -> Self {
+        let dominators = Dominators::new(cfg);
+        let frontiers = dominators.frontiers(cfg);
+        let live = liveness(cfg);
+
+        let mut types = HashMap::new();
+        let mut def_sites: HashMap<u32, Vec<BasicBlockIdx>> = HashMap::new();
+        for parameter in &cfg.prototype.parameters {
+            types.insert(parameter.0, parameter.1);
+        }
+        for &block_idx in dominators.reverse_postorder() {
+            for instruction in cfg.vertices[block_idx].instructions {
+                for variable in instruction.operands() {
+                    types.insert(variable.0, variable.1);
+                }
+                if let Some(dest) = instruction.dest() {
+                    types.insert(dest.0, dest.1);
+                    def_sites.entry(dest.0).or_default().push(block_idx);
+                }
+            }
+        }
+        let next_var = types.keys().max().map_or(0, |max| max + 1);
+
+        // every variable is considered defined at the entry, undefined ones
+        // included
+        let mut phi_vars: SecondaryMap<BasicBlockIdx, Vec<u32>> =
+            SecondaryMap::new();
+        for &block_idx in dominators.reverse_postorder() {
+            phi_vars.insert(block_idx, vec![]);
+        }
+        for (&variable, sites) in &def_sites {
+            let mut worklist = sites.clone();
+            worklist.push(cfg.entry);
+            let mut placed = HashSet::new();
+            while let Some(block_idx) = worklist.pop() {
+                for &frontier in &frontiers[block_idx] {
+                    if live[frontier].before.contains(variable as usize)
+                        && placed.insert(frontier)
+                    {
+                        phi_vars[frontier].push(variable);
+                        worklist.push(frontier);
+                    }
+                }
+            }
+        }
+
+        let mut blocks = SecondaryMap::new();
+        for &block_idx in dominators.reverse_postorder() {
+            let mut predecessors: Vec<_> = cfg
+                .predecessors(block_idx)
+                .into_iter()
+                .filter(|predecessor| dominators.is_reachable(*predecessor))
+                .map(Some)
+                .collect();
+            if block_idx == cfg.entry {
+                predecessors.push(None);
+            }
+            phi_vars[block_idx].sort_unstable();
+            let phis = phi_vars[block_idx]
+                .iter()
+                .map(|variable| Phi {
+                    dest: Variable(*variable, types[variable]),
+                    args: predecessors
+                        .iter()
+                        .map(|predecessor| (*predecessor, None))
+                        .collect(),
+                })
+                .collect();
+            blocks.insert(
+                block_idx,
+                SsaBlock {
+                    phis,
+                    block: Block::from_cfg(cfg, block_idx),
+                },
+            );
+        }
+
+        let mut ssa = Self {
+            cfg,
+            dominators,
+            blocks,
+            next_var,
+        };
+        let mut stacks: HashMap<u32, Vec<Variable>> = cfg
+            .prototype
+            .parameters
+            .iter()
+            .map(|parameter| (parameter.0, vec![*parameter]))
+            .collect();
+        for phi in &mut ssa.blocks[cfg.entry].phis {
+            for (predecessor, arg) in &mut phi.args {
+                if predecessor.is_none() {
+                    *arg = stacks.get(&phi.dest.0).map(|stack| stack[0]);
+                }
+            }
+        }
+        ssa.rename(cfg.entry, &phi_vars, &mut stacks);
+        ssa
+    }
+
+    pub fn fresh(&mut self, ty: Type) -> Variable {
+        self.next_var += 1;
+        Variable(self.next_var - 1, ty)
+    }
+
+    /// Distinct successors of `block_idx`.
+    pub fn successors(&self, block_idx: BasicBlockIdx) -> Vec<BasicBlockIdx> {
+        let mut successors = self.cfg.successors(block_idx);
+        successors.dedup();
+        successors
+    }
+
+    fn rename(
+        &mut self,
+        block_idx: BasicBlockIdx,
+        phi_vars: &SecondaryMap<BasicBlockIdx, Vec<u32>>,
+        stacks: &mut HashMap<u32, Vec<Variable>>,
+    ) {
+        let mut pushed = vec![];
+        let mut block = std::mem::take(&mut self.blocks[block_idx]);
+        for (phi, variable) in block.phis.iter_mut().zip(&phi_vars[block_idx]) {
+            phi.dest = self.fresh(phi.dest.1);
+            stacks.entry(*variable).or_default().push(phi.dest);
+            pushed.push(*variable);
+        }
+        for instruction in &mut block.block.instructions {
+            // reads of undefined variables keep the original name, which is
+            // never assigned
+            for operand in instruction.operands_mut() {
+                if let Some(current) =
+                    stacks.get(&operand.0).and_then(|stack| stack.last())
+                {
+                    *operand = *current;
+                }
+            }
+            if let Some(dest) = instruction.dest_mut() {
+                let renamed = self.fresh(dest.1);
+                stacks.entry(dest.0).or_default().push(renamed);
+                pushed.push(dest.0);
+                *dest = renamed;
+            }
+        }
+        self.blocks[block_idx] = block;
+
+        for successor in self.successors(block_idx) {
+            let successor_block = &mut self.blocks[successor];
+            for (phi, variable) in
+                successor_block.phis.iter_mut().zip(&phi_vars[successor])
+            {
+                for (predecessor, arg) in &mut phi.args {
+                    if *predecessor == Some(block_idx) {
+                        *arg = stacks
+                            .get(variable)
+                            .and_then(|stack| stack.last())
+                            .copied();
+                    }
+                }
+            }
+        }
+
+        for child in self.dominators.children(block_idx).to_vec() {
+            self.rename(child, phi_vars, stacks);
+        }
+        for variable in pushed {
+            stacks.get_mut(&variable).unwrap().pop();
+        }
+    }
+
+    /// Translates out of SSA form, replacing φ-functions with copies at the
+    /// end of predecessors. Edges from blocks with several successors are
+    /// split so the copies only run when the edge is taken.
+    pub fn into_blocks(mut self) -> Vec<Block> {
+        let order: Vec<_> = layout(self.cfg)
+            .into_iter()
+            .filter(|block_idx| self.dominators.is_reachable(*block_idx))
+            .collect();
+        let mut used_labels: HashSet<String> = order
+            .iter()
+            .filter_map(|block_idx| self.blocks[*block_idx].block.label.clone())
+            .collect();
+
+        let mut blocks: SecondaryMap<BasicBlockIdx, Block> =
+            SecondaryMap::new();
+        let mut split_blocks: SecondaryMap<BasicBlockIdx, Vec<Block>> =
+            SecondaryMap::new();
+        let mut entry_copies = vec![];
+        for &block_idx in &order {
+            blocks.insert(block_idx, self.blocks[block_idx].block.clone());
+            split_blocks.insert(block_idx, vec![]);
+        }
+        for &block_idx in &order {
+            let phis = std::mem::take(&mut self.blocks[block_idx].phis);
+            if phis.is_empty() {
+                continue;
+            }
+            let mut predecessors = vec![];
+            for (predecessor, _) in &phis[0].args {
+                if !predecessors.contains(predecessor) {
+                    predecessors.push(*predecessor);
+                }
+            }
+            for predecessor in predecessors {
+                let copies: Vec<_> = phis
+                    .iter()
+                    .filter_map(|phi| {
+                        let (_, arg) = phi
+                            .args
+                            .iter()
+                            .find(|(from, _)| *from == predecessor)
+                            .unwrap();
+                        arg.map(|arg| (phi.dest, arg))
+                    })
+                    .collect();
+                let copies = self.sequentialize(copies);
+                let Some(predecessor) = predecessor else {
+                    entry_copies = copies;
+                    continue;
+                };
+
+                // a `br` may read a variable the copies overwrite, even if
+                // both its targets are the same
+                let block = &mut blocks[predecessor];
+                match block.instructions.last() {
+                    Some(Instruction::Br(..)) => {}
+                    Some(Instruction::Jmp(_)) => {
+                        let at = block.instructions.len() - 1;
+                        block.instructions.splice(at..at, copies);
+                        continue;
+                    }
+                    _ => {
+                        block.instructions.extend(copies);
+                        continue;
+                    }
+                }
+
+                // nothing falls through to the block placed after the `br`
+                let target = blocks[block_idx].label.clone().unwrap();
+                let label =
+                    fresh_label(&mut used_labels, &format!("{target}.split"));
+                for redirected in &mut blocks[predecessor].targets {
+                    if *redirected == target {
+                        *redirected = label.clone();
+                    }
+                }
+                let mut instructions = copies;
+                instructions.push(Instruction::Jmp(Default::default()));
+                split_blocks[predecessor].push(Block {
+                    label: Some(label),
+                    instructions,
+                    targets: vec![target],
+                });
+            }
+        }
+
+        let mut lowered = vec![];
+        if !entry_copies.is_empty() {
+            lowered.push(Block {
+                label: None,
+                instructions: entry_copies,
+                targets: vec![],
+            });
+        }
+        for block_idx in order {
+            lowered.push(blocks.remove(block_idx).unwrap());
+            lowered.append(&mut split_blocks[block_idx]);
+        }
+        lowered
+    }
+
+    /// Orders the parallel copies `(dest, src)` so that no source is read
+    /// after being overwritten, breaking cycles with fresh variables.
+    fn sequentialize(
+        &mut self,
+        mut copies: Vec<(Variable, Variable)>,
+    ) -> Vec<Instruction> {
+        copies.retain(|(dest, src)| dest.0 != src.0);
+        let mut sequential = vec![];
+        while !copies.is_empty() {
+            let ready = copies.iter().position(|(dest, _)| {
+                copies.iter().all(|(_, src)| src.0 != dest.0)
+            });
+            match ready {
+                Some(i) => {
+                    let (dest, src) = copies.remove(i);
+                    sequential.push(Instruction::Id(dest, src));
+                }
+                None => {
+                    let (dest, _) = copies[0];
+                    let saved = self.fresh(dest.1);
+                    sequential.push(Instruction::Id(saved, dest));
+                    for (_, src) in &mut copies {
+                        if src.0 == dest.0 {
+                            *src = saved;
+                        }
+                    }
+                }
+            }
+        }
+        sequential
+    }
+}
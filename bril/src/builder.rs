@@ -27,7 +27,7 @@ impl ProgramBuilder {
         Self::default()
     }
 
-    pub fn new_function(&mut self, name: String) -> FunctionBuilder {
+    pub fn new_function(&mut self, name: String) -> FunctionBuilder<'_> {
         FunctionBuilder {
             name,
             blocks: SlotMap::with_key(),
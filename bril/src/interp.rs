@@ -0,0 +1,262 @@
+// Copyright (C) 2025 Zihan Li and Ethan Uppal.
+
+use std::{collections::HashMap, fmt};
+
+use crate::ir::{
+    Function, FunctionIdx, Instruction, LabelIdx, Program, Value, Variable,
+};
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum InterpError {
+    NoMainFunction,
+    UndefinedVariable(Variable),
+    /// the variable holds a value of another type than it was declared with
+    TypeMismatch(Variable),
+    DivisionByZero,
+    ArgumentCountMismatch {
+        function: String,
+        expected: usize,
+        found: usize,
+    },
+    /// the program did not terminate within the given number of steps
+    OutOfFuel,
+}
+
+impl fmt::Display for InterpError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoMainFunction => formatter.write_str("no main function"),
+            Self::UndefinedVariable(variable) => {
+                write!(formatter, "undefined variable x{}", variable.0)
+            }
+            Self::TypeMismatch(variable) => {
+                write!(
+                    formatter,
+                    "x{} is not of type {}",
+                    variable.0, variable.1
+                )
+            }
+            Self::DivisionByZero => formatter.write_str("division by zero"),
+            Self::ArgumentCountMismatch {
+                function,
+                expected,
+                found,
+            } => write!(
+                formatter,
+                "@{function} expects {expected} arguments, found {found}"
+            ),
+            Self::OutOfFuel => formatter.write_str("out of fuel"),
+        }
+    }
+}
+
+impl std::error::Error for InterpError {}
+
+/// Observable behavior of a terminated program.
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct Outcome {
+    /// everything printed, one line per `print`
+    pub output: String,
+    pub return_value: Option<Value>,
+    /// number of executed instructions, `nop`s included
+    pub steps: usize,
+}
+
+struct Frame {
+    function: usize,
+    variables: HashMap<u32, Value>,
+    pc: usize,
+    /// variable of the caller receiving the return value
+    dest: Option<Variable>,
+}
+
+/// Runs `@main` of `program` with `args`, executing at most `fuel`
+/// instructions. Integer arithmetic wraps around on overflow.
+pub fn interpret(
+    program: &Program,
+    args: &[Value],
+    fuel: usize,
+) -> Result<Outcome, InterpError> {
+    interpret_observed(program, args, fuel, |_, _, _| {})
+}
+
+/// Same as [`interpret`], calling `observe` right before executing every
+/// instruction with the function it belongs to, its offset in the function
+/// and the variables of the frame, keyed by variable numbering.
+pub fn interpret_observed(
+    program: &Program,
+    args: &[Value],
+    fuel: usize,
+    mut observe: impl FnMut(FunctionIdx, usize, &HashMap<u32, Value>),
+) -> Result<Outcome, InterpError> {
+    let functions: Vec<Function> = program.functions().collect();
+    let label_offsets: Vec<HashMap<LabelIdx, usize>> = functions
+        .iter()
+        .map(|function| {
+            function
+                .labels
+                .iter()
+                .map(|label| (label.idx, label.offset))
+                .collect()
+        })
+        .collect();
+    let main = program
+        .find_function_symbol("main")
+        .ok_or(InterpError::NoMainFunction)?;
+
+    let mut outcome = Outcome::default();
+    let mut stack = vec![enter(&functions, main, args.iter().cloned(), None)?];
+    loop {
+        let frame = stack.last_mut().unwrap();
+        let function = &functions[frame.function];
+        let Some(instruction) = function.instructions.get(frame.pc) else {
+            // falling off the end returns nothing
+            stack.pop();
+            if stack.is_empty() {
+                return Ok(outcome);
+            }
+            continue;
+        };
+        outcome.steps += 1;
+        if outcome.steps > fuel {
+            return Err(InterpError::OutOfFuel);
+        }
+        observe(
+            FunctionIdx(frame.function as u32),
+            frame.pc,
+            &frame.variables,
+        );
+        frame.pc += 1;
+
+        let variables = &frame.variables;
+        let get = |variable: &Variable| {
+            variables
+                .get(&variable.0)
+                .cloned()
+                .ok_or(InterpError::UndefinedVariable(*variable))
+        };
+        let int = |variable: &Variable| match get(variable)? {
+            Value::Int(value) => Ok(value),
+            _ => Err(InterpError::TypeMismatch(*variable)),
+        };
+        let bool = |variable: &Variable| match get(variable)? {
+            Value::Bool(value) => Ok(value),
+            _ => Err(InterpError::TypeMismatch(*variable)),
+        };
+        let (dest, value) = match instruction {
+            Instruction::Add(dest, arg0, arg1) => {
+                (dest, Value::Int(int(arg0)?.wrapping_add(int(arg1)?)))
+            }
+            Instruction::Sub(dest, arg0, arg1) => {
+                (dest, Value::Int(int(arg0)?.wrapping_sub(int(arg1)?)))
+            }
+            Instruction::Mul(dest, arg0, arg1) => {
+                (dest, Value::Int(int(arg0)?.wrapping_mul(int(arg1)?)))
+            }
+            Instruction::Div(dest, arg0, arg1) => {
+                let divisor = int(arg1)?;
+                if divisor == 0 {
+                    return Err(InterpError::DivisionByZero);
+                }
+                (dest, Value::Int(int(arg0)?.wrapping_div(divisor)))
+            }
+            Instruction::Eq(dest, arg0, arg1) => {
+                (dest, Value::Bool(int(arg0)? == int(arg1)?))
+            }
+            Instruction::Lt(dest, arg0, arg1) => {
+                (dest, Value::Bool(int(arg0)? < int(arg1)?))
+            }
+            Instruction::Gt(dest, arg0, arg1) => {
+                (dest, Value::Bool(int(arg0)? > int(arg1)?))
+            }
+            Instruction::Le(dest, arg0, arg1) => {
+                (dest, Value::Bool(int(arg0)? <= int(arg1)?))
+            }
+            Instruction::Ge(dest, arg0, arg1) => {
+                (dest, Value::Bool(int(arg0)? >= int(arg1)?))
+            }
+            Instruction::Not(dest, arg0) => (dest, Value::Bool(!bool(arg0)?)),
+            Instruction::And(dest, arg0, arg1) => {
+                (dest, Value::Bool(bool(arg0)? && bool(arg1)?))
+            }
+            Instruction::Or(dest, arg0, arg1) => {
+                (dest, Value::Bool(bool(arg0)? || bool(arg1)?))
+            }
+            Instruction::Const(dest, value) => (dest, value.clone()),
+            Instruction::Id(dest, arg0) => (dest, get(arg0)?),
+            Instruction::Jmp(label) => {
+                frame.pc = label_offsets[frame.function][label];
+                continue;
+            }
+            Instruction::Br(condition, if_true, if_false) => {
+                let label = if bool(condition)? { if_true } else { if_false };
+                frame.pc = label_offsets[frame.function][label];
+                continue;
+            }
+            Instruction::Call(dest, callee, args) => {
+                let args =
+                    args.iter().map(get).collect::<Result<Vec<_>, _>>()?;
+                let callee = enter(&functions, *callee, args, *dest)?;
+                stack.push(callee);
+                continue;
+            }
+            Instruction::Ret(value) => {
+                let value = value.as_ref().map(get).transpose()?;
+                let frame = stack.pop().unwrap();
+                match stack.last_mut() {
+                    Some(caller) => {
+                        if let (Some(dest), Some(value)) = (frame.dest, value) {
+                            caller.variables.insert(dest.0, value);
+                        }
+                    }
+                    None => {
+                        outcome.return_value = value;
+                        return Ok(outcome);
+                    }
+                }
+                continue;
+            }
+            Instruction::Print(args) => {
+                let values = args
+                    .iter()
+                    .map(|arg| get(arg).map(|value| value.to_string()))
+                    .collect::<Result<Vec<_>, _>>()?;
+                outcome.output.push_str(&values.join(" "));
+                outcome.output.push('\n');
+                continue;
+            }
+            Instruction::Nop => continue,
+        };
+        frame.variables.insert(dest.0, value);
+    }
+}
+
+/// Creates the frame of a call to `function` with `args` bound to its
+/// parameters.
+fn enter(
+    functions: &[Function],
+    function: FunctionIdx,
+    args: impl IntoIterator<Item = Value>,
+    dest: Option<Variable>,
+) -> Result<Frame, InterpError> {
+    let callee = &functions[function.0 as usize];
+    let args: Vec<_> = args.into_iter().collect();
+    if args.len() != callee.parameters.len() {
+        return Err(InterpError::ArgumentCountMismatch {
+            function: callee.name.to_string(),
+            expected: callee.parameters.len(),
+            found: args.len(),
+        });
+    }
+    Ok(Frame {
+        function: function.0 as usize,
+        variables: callee
+            .parameters
+            .iter()
+            .map(|parameter| parameter.0)
+            .zip(args)
+            .collect(),
+        pc: 0,
+        dest,
+    })
+}
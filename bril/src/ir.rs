@@ -140,7 +140,7 @@ pub struct Program {
 }
 
 impl Program {
-    pub fn functions(&self) -> impl Iterator<Item = Function> {
+    pub fn functions(&self) -> impl Iterator<Item = Function<'_>> {
         (0..self.functions.len())
             .map(|idx| self.get_function(FunctionIdx(idx as u32)))
     }
@@ -169,7 +169,7 @@ impl Program {
         &self.strings[idx.0 as usize]
     }
 
-    pub fn get_function(&self, idx: FunctionIdx) -> Function {
+    pub fn get_function(&self, idx: FunctionIdx) -> Function<'_> {
         let function = &self.functions[idx.0 as usize];
         let start = function.range.start;
         let mut labels: Vec<_> = function
@@ -191,6 +191,39 @@ impl Program {
         }
     }
 
+    /// The instructions of function `idx`, for rewriting them in place.
+    pub fn function_instructions_mut(
+        &mut self,
+        idx: FunctionIdx,
+    ) -> &mut [Instruction] {
+        let range = self.functions[idx.0 as usize].range.clone();
+        &mut self.instructions[range]
+    }
+
+    /// Removes all `nop` instructions, moving labels and function boundaries
+    /// along with the instructions following them.
+    pub fn remove_nops(&mut self) {
+        let mut new_offsets = Vec::with_capacity(self.instructions.len() + 1);
+        let mut next_offset = 0;
+        for instruction in &self.instructions {
+            new_offsets.push(next_offset);
+            if !matches!(instruction, Instruction::Nop) {
+                next_offset += 1;
+            }
+        }
+        new_offsets.push(next_offset);
+
+        self.instructions
+            .retain(|instruction| !matches!(instruction, Instruction::Nop));
+        for (offset, _) in &mut self.labels {
+            *offset = new_offsets[*offset];
+        }
+        for function in &mut self.functions {
+            function.range = new_offsets[function.range.start]
+                ..new_offsets[function.range.end];
+        }
+    }
+
     pub fn find_function_symbol(&self, name: &str) -> Option<FunctionIdx> {
         self.functions()
             .position(|function| function.name == name)
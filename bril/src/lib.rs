@@ -3,6 +3,7 @@
 pub mod ast;
 pub mod ast_to_ir;
 pub mod builder;
+pub mod interp;
 pub mod ir;
 pub mod printer;
 pub mod shim;
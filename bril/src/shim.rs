@@ -23,14 +23,13 @@ fn basic_block_split(
 ) -> impl Iterator<Item = &[bril_rs::Code]> {
     let mut basic_blocks = vec![];
     let mut haystack = instrs;
-    if !matches!(instrs.first().unwrap(), bril_rs::Code::Label { .. }) {
-        if let Some(next_label) = instrs
+    if !matches!(instrs.first().unwrap(), bril_rs::Code::Label { .. })
+        && let Some(next_label) = instrs
             .iter()
             .position(|instr| matches!(instr, bril_rs::Code::Label { .. }))
-        {
-            basic_blocks.push(&instrs[..next_label]);
-            haystack = &instrs[next_label..];
-        }
+    {
+        basic_blocks.push(&instrs[..next_label]);
+        haystack = &instrs[next_label..];
     }
     while !haystack.is_empty() {
         if let Some(next_label) = haystack[1..]
@@ -271,10 +270,10 @@ impl<'a> InstrBuilder<'a> {
             bril_rs::Type::Int => ir::Type::Int,
             bril_rs::Type::Bool => ir::Type::Bool,
         };
-        if let Some(variable) = self.var_map.get(lit) {
-            if variable.1 == ir_ty {
-                return *variable;
-            }
+        if let Some(variable) = self.var_map.get(lit)
+            && variable.1 == ir_ty
+        {
+            return *variable;
         }
         let next_var = ir::Variable(self.next_var, ir_ty);
         self.next_var += 1;